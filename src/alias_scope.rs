@@ -0,0 +1,149 @@
+//! Scoped noalias metadata, which tells LLVM that memory accesses in one
+//! alias scope never alias those in another, such as accesses through
+//! `restrict` pointers. Unlike `noalias` parameter attributes, these facts
+//! survive inlining.
+//!
+//! Scopes belong to a domain, and each memory access may be tagged with the
+//! scopes it belongs to (`!alias.scope`) and the scopes it does not alias
+//! (`!noalias`). Two accesses are known not to alias if, for some domain,
+//! every scope of that domain in one access's `!alias.scope` list appears in
+//! the other's `!noalias` list.
+//!
+//! ```no_run
+//! use inkwell::alias_scope::AliasScopeBuilder;
+//! use inkwell::context::Context;
+//! use inkwell::AddressSpace;
+//!
+//! let context = Context::create();
+//! let module = context.create_module("scopes");
+//! let builder = context.create_builder();
+//! let i32_type = context.i32_type();
+//! let i32_ptr_type = i32_type.ptr_type(AddressSpace::default());
+//! let fn_type = context.void_type().fn_type(&[i32_ptr_type.into(), i32_ptr_type.into()], false);
+//! let function = module.add_function("copy", fn_type, None);
+//! let entry = context.append_basic_block(function, "entry");
+//! let dst = function.get_nth_param(0).unwrap().into_pointer_value();
+//! let src = function.get_nth_param(1).unwrap().into_pointer_value();
+//!
+//! builder.position_at_end(entry);
+//!
+//! let store = builder.build_store(dst, i32_type.const_zero());
+//!
+//! builder.build_return(None);
+//!
+//! let scopes = AliasScopeBuilder::new(&context);
+//! let domain = scopes.create_domain(Some("copy"));
+//! let dst_scope = scopes.create_scope(domain, Some("copy: dst"));
+//! let src_scope = scopes.create_scope(domain, Some("copy: src"));
+//!
+//! // The store writes through `dst`, which never aliases `src`.
+//! scopes.set_alias_scopes(store, &[dst_scope]).unwrap();
+//! scopes.set_noalias_scopes(store, &[src_scope]).unwrap();
+//! ```
+
+use crate::context::{AsContextRef, ContextRef};
+use crate::values::{BasicMetadataValueEnum, InstructionOpcode, InstructionValue, MetadataValue};
+
+/// Creates alias scope domains and scopes, and attaches scope lists to
+/// memory instructions.
+#[derive(Debug)]
+pub struct AliasScopeBuilder<'ctx> {
+    context: ContextRef<'ctx>,
+    alias_scope_kind_id: u32,
+    noalias_kind_id: u32,
+}
+
+impl<'ctx> AliasScopeBuilder<'ctx> {
+    /// Creates an `AliasScopeBuilder` for `context`.
+    pub fn new(context: impl AsContextRef<'ctx>) -> Self {
+        let context = unsafe { ContextRef::new(context.as_ctx_ref()) };
+        let alias_scope_kind_id = context.get_kind_id("alias.scope");
+        let noalias_kind_id = context.get_kind_id("noalias");
+
+        AliasScopeBuilder {
+            context,
+            alias_scope_kind_id,
+            noalias_kind_id,
+        }
+    }
+
+    /// Gets the metadata kind id of `!alias.scope` attachments.
+    pub fn get_alias_scope_kind_id(&self) -> u32 {
+        self.alias_scope_kind_id
+    }
+
+    /// Gets the metadata kind id of `!noalias` attachments.
+    pub fn get_noalias_kind_id(&self) -> u32 {
+        self.noalias_kind_id
+    }
+
+    /// Creates a new alias scope domain, optionally described by `name`.
+    /// Every call creates a distinct domain, even when given the same name.
+    pub fn create_domain(&self, name: Option<&str>) -> MetadataValue<'ctx> {
+        let mut operands = Vec::new();
+
+        if let Some(name) = name {
+            operands.push(self.context.metadata_string(name));
+        }
+
+        self.context.self_referential_metadata_node(&operands)
+    }
+
+    /// Creates a new alias scope in `domain`, optionally described by `name`.
+    /// Every call creates a distinct scope, even when given the same name.
+    pub fn create_scope(&self, domain: MetadataValue<'ctx>, name: Option<&str>) -> MetadataValue<'ctx> {
+        let mut operands = vec![domain];
+
+        if let Some(name) = name {
+            operands.push(self.context.metadata_string(name));
+        }
+
+        self.context.self_referential_metadata_node(&operands)
+    }
+
+    /// Creates a list of the given scopes, as used by `!alias.scope` and
+    /// `!noalias` attachments.
+    pub fn create_scope_list(&self, scopes: &[MetadataValue<'ctx>]) -> MetadataValue<'ctx> {
+        let scopes: Vec<BasicMetadataValueEnum<'ctx>> = scopes.iter().map(|&scope| scope.into()).collect();
+
+        self.context.metadata_node(&scopes)
+    }
+
+    /// Attaches the list of `scopes` that `instruction` accesses memory in as
+    /// `!alias.scope` metadata. `instruction` should be a load, store, atomic
+    /// or call. Returns an error if it is something else.
+    pub fn set_alias_scopes(
+        &self,
+        instruction: InstructionValue<'ctx>,
+        scopes: &[MetadataValue<'ctx>],
+    ) -> Result<(), &'static str> {
+        self.set_scope_list(instruction, scopes, self.alias_scope_kind_id)
+    }
+
+    /// Attaches the list of `scopes` that `instruction` does not alias as
+    /// `!noalias` metadata. `instruction` should be a load, store, atomic or
+    /// call. Returns an error if it is something else.
+    pub fn set_noalias_scopes(
+        &self,
+        instruction: InstructionValue<'ctx>,
+        scopes: &[MetadataValue<'ctx>],
+    ) -> Result<(), &'static str> {
+        self.set_scope_list(instruction, scopes, self.noalias_kind_id)
+    }
+
+    fn set_scope_list(
+        &self,
+        instruction: InstructionValue<'ctx>,
+        scopes: &[MetadataValue<'ctx>],
+        kind_id: u32,
+    ) -> Result<(), &'static str> {
+        match instruction.get_opcode() {
+            InstructionOpcode::Load
+            | InstructionOpcode::Store
+            | InstructionOpcode::AtomicRMW
+            | InstructionOpcode::AtomicCmpXchg
+            | InstructionOpcode::Call => instruction.set_metadata(self.create_scope_list(scopes), kind_id),
+            _ => Err("Alias scopes can only be attached to memory accesses."),
+        }
+    }
+}
@@ -9,9 +9,10 @@ use llvm_sys::core::{LLVMGetTypeAttributeValue, LLVMIsTypeAttribute};
 use llvm_sys::prelude::LLVMAttributeRef;
 
 use std::ffi::CStr;
+use std::fmt;
 
-#[llvm_versions(12.0..=latest)]
-use crate::types::AnyTypeEnum;
+use crate::context::{AsContextRef, ContextRef};
+use crate::types::{AnyType, AnyTypeEnum};
 
 // SubTypes: Attribute<Enum>, Attribute<String>
 // REVIEW: Should Attributes have a 'ctx lifetime?
@@ -309,3 +310,394 @@ impl AttributeLoc {
         }
     }
 }
+
+/// Which kinds of memory access a function may perform at a location.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MemoryAccess {
+    /// The location is not accessed.
+    None,
+    /// The location may be read.
+    Read,
+    /// The location may be written.
+    Write,
+    /// The location may be read and written.
+    ReadWrite,
+}
+
+impl MemoryAccess {
+    fn from_bits(bits: u64) -> Self {
+        match bits & 0b11 {
+            0 => MemoryAccess::None,
+            1 => MemoryAccess::Read,
+            2 => MemoryAccess::Write,
+            _ => MemoryAccess::ReadWrite,
+        }
+    }
+
+    fn as_bits(self) -> u64 {
+        match self {
+            MemoryAccess::None => 0,
+            MemoryAccess::Read => 1,
+            MemoryAccess::Write => 2,
+            MemoryAccess::ReadWrite => 3,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            MemoryAccess::None => "none",
+            MemoryAccess::Read => "read",
+            MemoryAccess::Write => "write",
+            MemoryAccess::ReadWrite => "readwrite",
+        }
+    }
+}
+
+/// The memory a function may access, as described by the `memory` attribute
+/// in LLVM 16 and later.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryEffects {
+    /// Accesses to memory pointed to by the function's pointer arguments.
+    pub arg_mem: MemoryAccess,
+    /// Accesses to memory not accessible by the caller, such as a library's
+    /// internal state.
+    pub inaccessible_mem: MemoryAccess,
+    /// Accesses to any other memory.
+    pub other_mem: MemoryAccess,
+}
+
+impl MemoryEffects {
+    /// Creates `MemoryEffects` with the same access to every location.
+    pub fn all(access: MemoryAccess) -> Self {
+        MemoryEffects {
+            arg_mem: access,
+            inaccessible_mem: access,
+            other_mem: access,
+        }
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        MemoryEffects {
+            arg_mem: MemoryAccess::from_bits(bits),
+            inaccessible_mem: MemoryAccess::from_bits(bits >> 2),
+            other_mem: MemoryAccess::from_bits(bits >> 4),
+        }
+    }
+
+    fn as_bits(self) -> u64 {
+        self.arg_mem.as_bits() | (self.inaccessible_mem.as_bits() << 2) | (self.other_mem.as_bits() << 4)
+    }
+}
+
+impl fmt::Display for MemoryEffects {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.arg_mem == self.inaccessible_mem && self.arg_mem == self.other_mem {
+            return write!(f, "memory({})", self.arg_mem.as_str());
+        }
+
+        let locations = [
+            ("argmem", self.arg_mem),
+            ("inaccessiblemem", self.inaccessible_mem),
+            ("other", self.other_mem),
+        ];
+        let accessed: Vec<String> = locations
+            .iter()
+            .filter(|(_, access)| *access != MemoryAccess::None)
+            .map(|(location, access)| format!("{}: {}", location, access.as_str()))
+            .collect();
+
+        write!(f, "memory({})", accessed.join(", "))
+    }
+}
+
+/// A builtin attribute, which can be converted to an `Attribute` without
+/// looking up its kind id by name. Not every attribute exists in every LLVM
+/// version; converting one that doesn't fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Attr<'ctx> {
+    /// `noalias`: the pointer does not alias any other pointer visible to the function.
+    NoAlias,
+    /// `nonnull`: the pointer is never null.
+    NonNull,
+    /// `nocapture`: the function does not keep a copy of the pointer.
+    NoCapture,
+    /// `noundef`: the value is neither undef nor poison.
+    NoUndef,
+    /// `readonly`: the pointer (or function) only reads memory.
+    ReadOnly,
+    /// `readnone`: the pointer (or function) does not access memory.
+    ReadNone,
+    /// `writeonly`: the pointer (or function) only writes memory.
+    WriteOnly,
+    /// `returned`: the function always returns this argument.
+    Returned,
+    /// `signext`: the value should be sign extended by the caller or callee.
+    SExt,
+    /// `zeroext`: the value should be zero extended by the caller or callee.
+    ZExt,
+    /// `inreg`: the value should be passed in a register.
+    InReg,
+    /// `nest`: the pointer is a trampoline's static chain.
+    Nest,
+    /// `swiftself`: the value is a Swift `self` parameter.
+    SwiftSelf,
+    /// `swifterror`: the pointer is a Swift error parameter.
+    SwiftError,
+    /// `immarg`: the intrinsic argument must be an immediate.
+    ImmArg,
+    /// `dereferenceable(n)`: the pointer can be dereferenced for `n` bytes.
+    Dereferenceable(u64),
+    /// `dereferenceable_or_null(n)`: the pointer is null or can be dereferenced for `n` bytes.
+    DereferenceableOrNull(u64),
+    /// `align n`: the pointer is aligned to `n` bytes, which must be a power of two.
+    Align(u64),
+    /// `byval(ty)`: the pointee, of type `ty`, is passed by value. Before LLVM
+    /// 12, the type is not recorded.
+    ByVal(AnyTypeEnum<'ctx>),
+    /// `sret(ty)`: the pointer is where a struct of type `ty` is returned.
+    /// Before LLVM 12, the type is not recorded.
+    SRet(AnyTypeEnum<'ctx>),
+    /// `nounwind`: the function never unwinds.
+    NoUnwind,
+    /// `noreturn`: the function never returns.
+    NoReturn,
+    /// `noinline`: the function should never be inlined.
+    NoInline,
+    /// `alwaysinline`: the function should always be inlined.
+    AlwaysInline,
+    /// `inlinehint`: the function should preferably be inlined.
+    InlineHint,
+    /// `optnone`: the function should not be optimized. Requires `NoInline`.
+    OptimizeNone,
+    /// `optsize`: the function should be optimized for size.
+    OptimizeForSize,
+    /// `minsize`: the function should be made as small as possible.
+    MinSize,
+    /// `cold`: the function is rarely called.
+    Cold,
+    /// `hot`: the function is frequently called.
+    Hot,
+    /// `norecurse`: the function never calls itself, directly or indirectly.
+    NoRecurse,
+    /// `willreturn`: the function always returns or unwinds.
+    WillReturn,
+    /// `mustprogress`: the function must return, unwind or interact with the environment.
+    MustProgress,
+    /// `nosync`: the function does not synchronize with other threads.
+    NoSync,
+    /// `nofree`: the function (or pointer) does not free memory.
+    NoFree,
+    /// `naked`: the function has no prologue or epilogue.
+    Naked,
+    /// `speculatable`: the function may be called speculatively.
+    Speculatable,
+    /// `argmemonly`: the function only accesses memory through its arguments.
+    /// Replaced by `MemoryEffects` in LLVM 16.
+    ArgMemOnly,
+    /// `memory(...)`: the memory the function may access. LLVM 16 and later.
+    MemoryEffects(MemoryEffects),
+}
+
+impl<'ctx> Attr<'ctx> {
+    const SIMPLE: [Attr<'static>; 33] = [
+        Attr::NoAlias,
+        Attr::NonNull,
+        Attr::NoCapture,
+        Attr::NoUndef,
+        Attr::ReadOnly,
+        Attr::ReadNone,
+        Attr::WriteOnly,
+        Attr::Returned,
+        Attr::SExt,
+        Attr::ZExt,
+        Attr::InReg,
+        Attr::Nest,
+        Attr::SwiftSelf,
+        Attr::SwiftError,
+        Attr::ImmArg,
+        Attr::NoUnwind,
+        Attr::NoReturn,
+        Attr::NoInline,
+        Attr::AlwaysInline,
+        Attr::InlineHint,
+        Attr::OptimizeNone,
+        Attr::OptimizeForSize,
+        Attr::MinSize,
+        Attr::Cold,
+        Attr::Hot,
+        Attr::NoRecurse,
+        Attr::WillReturn,
+        Attr::MustProgress,
+        Attr::NoSync,
+        Attr::NoFree,
+        Attr::Naked,
+        Attr::Speculatable,
+        Attr::ArgMemOnly,
+    ];
+
+    /// Gets the name LLVM knows this attribute by, such as `"noalias"`.
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Attr::NoAlias => "noalias",
+            Attr::NonNull => "nonnull",
+            Attr::NoCapture => "nocapture",
+            Attr::NoUndef => "noundef",
+            Attr::ReadOnly => "readonly",
+            Attr::ReadNone => "readnone",
+            Attr::WriteOnly => "writeonly",
+            Attr::Returned => "returned",
+            Attr::SExt => "signext",
+            Attr::ZExt => "zeroext",
+            Attr::InReg => "inreg",
+            Attr::Nest => "nest",
+            Attr::SwiftSelf => "swiftself",
+            Attr::SwiftError => "swifterror",
+            Attr::ImmArg => "immarg",
+            Attr::Dereferenceable(_) => "dereferenceable",
+            Attr::DereferenceableOrNull(_) => "dereferenceable_or_null",
+            Attr::Align(_) => "align",
+            Attr::ByVal(_) => "byval",
+            Attr::SRet(_) => "sret",
+            Attr::NoUnwind => "nounwind",
+            Attr::NoReturn => "noreturn",
+            Attr::NoInline => "noinline",
+            Attr::AlwaysInline => "alwaysinline",
+            Attr::InlineHint => "inlinehint",
+            Attr::OptimizeNone => "optnone",
+            Attr::OptimizeForSize => "optsize",
+            Attr::MinSize => "minsize",
+            Attr::Cold => "cold",
+            Attr::Hot => "hot",
+            Attr::NoRecurse => "norecurse",
+            Attr::WillReturn => "willreturn",
+            Attr::MustProgress => "mustprogress",
+            Attr::NoSync => "nosync",
+            Attr::NoFree => "nofree",
+            Attr::Naked => "naked",
+            Attr::Speculatable => "speculatable",
+            Attr::ArgMemOnly => "argmemonly",
+            Attr::MemoryEffects(_) => "memory",
+        }
+    }
+
+    /// Gets the enum kind id of this attribute, or `None` if this LLVM
+    /// version does not know it.
+    pub fn get_kind_id(self) -> Option<u32> {
+        match Attribute::get_named_enum_kind_id(self.get_name()) {
+            0 => None,
+            kind_id => Some(kind_id),
+        }
+    }
+
+    /// Creates an `Attribute` for this attribute in `context`. Returns an
+    /// error if this LLVM version does not know it, or its value is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::attributes::{Attr, AttributeLoc};
+    /// use inkwell::context::Context;
+    /// use inkwell::AddressSpace;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let i8_ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+    /// let fn_type = context.void_type().fn_type(&[i8_ptr_type.into()], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    ///
+    /// fn_value.add_attribute(AttributeLoc::Param(0), Attr::NoAlias.to_attribute(&context).unwrap());
+    /// fn_value.add_attribute(AttributeLoc::Param(0), Attr::Align(16).to_attribute(&context).unwrap());
+    /// fn_value.add_attribute(AttributeLoc::Function, Attr::NoUnwind.to_attribute(&context).unwrap());
+    ///
+    /// assert!(Attr::Align(3).to_attribute(&context).is_err());
+    /// ```
+    pub fn to_attribute(self, context: impl AsContextRef<'ctx>) -> Result<Attribute, &'static str> {
+        let context = unsafe { ContextRef::new(context.as_ctx_ref()) };
+        let kind_id = self
+            .get_kind_id()
+            .ok_or("Attribute is not supported by this LLVM version.")?;
+
+        let value = match self {
+            Attr::Align(alignment) if !alignment.is_power_of_two() => {
+                return Err("Alignment must be a power of two.");
+            },
+            Attr::Dereferenceable(0) | Attr::DereferenceableOrNull(0) => {
+                return Err("Dereferenceable byte count must be non-zero.");
+            },
+            Attr::Align(value) | Attr::Dereferenceable(value) | Attr::DereferenceableOrNull(value) => value,
+            Attr::MemoryEffects(effects) => effects.as_bits(),
+            Attr::ByVal(ty) | Attr::SRet(ty) => return Ok(create_type_attribute(&context, kind_id, ty)),
+            _ => 0,
+        };
+
+        Ok(context.create_enum_attribute(kind_id, value))
+    }
+
+    /// Gets the `Attr` an `Attribute` represents, or `None` if it is a string
+    /// attribute or not an attribute `Attr` covers. Before LLVM 12, `byval`
+    /// and `sret` attributes can't be converted, as they don't record a type.
+    pub fn from_attribute(attribute: Attribute) -> Option<Self> {
+        if attribute.is_string() {
+            return None;
+        }
+
+        let kind_id = attribute.get_enum_kind_id();
+        let is_kind = |name: &str| Attribute::get_named_enum_kind_id(name) == kind_id;
+
+        if is_kind("align") {
+            Some(Attr::Align(attribute.get_enum_value()))
+        } else if is_kind("dereferenceable") {
+            Some(Attr::Dereferenceable(attribute.get_enum_value()))
+        } else if is_kind("dereferenceable_or_null") {
+            Some(Attr::DereferenceableOrNull(attribute.get_enum_value()))
+        } else if is_kind("memory") {
+            Some(Attr::MemoryEffects(MemoryEffects::from_bits(
+                attribute.get_enum_value(),
+            )))
+        } else if is_kind("byval") {
+            get_type_attribute_value(attribute).map(Attr::ByVal)
+        } else if is_kind("sret") {
+            get_type_attribute_value(attribute).map(Attr::SRet)
+        } else {
+            Attr::SIMPLE.iter().find(|attr| is_kind(attr.get_name())).copied()
+        }
+    }
+}
+
+impl fmt::Display for Attr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attr::Dereferenceable(bytes) | Attr::DereferenceableOrNull(bytes) => {
+                write!(f, "{}({})", self.get_name(), bytes)
+            },
+            Attr::Align(alignment) => write!(f, "align {}", alignment),
+            Attr::ByVal(ty) | Attr::SRet(ty) => write!(f, "{}({})", self.get_name(), ty.print_to_string().to_string()),
+            Attr::MemoryEffects(effects) => write!(f, "{}", effects),
+            _ => write!(f, "{}", self.get_name()),
+        }
+    }
+}
+
+#[llvm_versions(4.0..=11.0)]
+fn create_type_attribute(context: &ContextRef<'_>, kind_id: u32, _ty: AnyTypeEnum<'_>) -> Attribute {
+    context.create_enum_attribute(kind_id, 0)
+}
+
+#[llvm_versions(12.0..=latest)]
+fn create_type_attribute(context: &ContextRef<'_>, kind_id: u32, ty: AnyTypeEnum<'_>) -> Attribute {
+    context.create_type_attribute(kind_id, ty)
+}
+
+#[llvm_versions(4.0..=11.0)]
+fn get_type_attribute_value<'ctx>(_attribute: Attribute) -> Option<AnyTypeEnum<'ctx>> {
+    None
+}
+
+#[llvm_versions(12.0..=latest)]
+fn get_type_attribute_value<'ctx>(attribute: Attribute) -> Option<AnyTypeEnum<'ctx>> {
+    if !attribute.is_type() {
+        return None;
+    }
+
+    unsafe { Some(AnyTypeEnum::new(LLVMGetTypeAttributeValue(attribute.attribute))) }
+}
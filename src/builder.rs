@@ -197,7 +197,11 @@ impl<'ctx> Builder<'ctx> {
             )
         };
 
-        unsafe { CallSiteValue::new(value) }
+        let call_site_value = unsafe { CallSiteValue::new(value) };
+
+        call_site_value.inherit_calling_convention();
+
+        call_site_value
     }
 
     /// Builds a function call instruction. Alias for [Builder::build_direct_call].
@@ -320,7 +324,11 @@ impl<'ctx> Builder<'ctx> {
             )
         };
 
-        unsafe { CallSiteValue::new(value) }
+        let call_site_value = unsafe { CallSiteValue::new(value) };
+
+        call_site_value.inherit_calling_convention();
+
+        call_site_value
     }
 
     /// An invoke is similar to a normal function call, but used to
@@ -435,7 +443,11 @@ impl<'ctx> Builder<'ctx> {
             )
         };
 
-        unsafe { CallSiteValue::new(value) }
+        let call_site_value = unsafe { CallSiteValue::new(value) };
+
+        call_site_value.inherit_calling_convention();
+
+        call_site_value
     }
 
     /// An invoke is similar to a normal function call, but used to
@@ -599,7 +611,11 @@ impl<'ctx> Builder<'ctx> {
             )
         };
 
-        unsafe { CallSiteValue::new(value) }
+        let call_site_value = unsafe { CallSiteValue::new(value) };
+
+        call_site_value.inherit_calling_convention();
+
+        call_site_value
     }
 
     /// Landing pads are places where control flow jumps to if a [`Builder::build_invoke`] triggered an exception.
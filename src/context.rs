@@ -23,7 +23,13 @@ use llvm_sys::core::{
     LLVMModuleCreateWithNameInContext, LLVMPPCFP128TypeInContext, LLVMStructCreateNamed, LLVMStructTypeInContext,
     LLVMVoidTypeInContext, LLVMX86FP80TypeInContext,
 };
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::core::{LLVMMDNodeInContext2, LLVMMetadataAsValue};
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::debuginfo::{LLVMMetadataReplaceAllUsesWith, LLVMTemporaryMDNode};
 use llvm_sys::ir_reader::LLVMParseIRInContext;
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::prelude::LLVMMetadataRef;
 use llvm_sys::prelude::{LLVMContextRef, LLVMDiagnosticInfoRef, LLVMTypeRef, LLVMValueRef};
 use llvm_sys::target::{LLVMIntPtrTypeForASInContext, LLVMIntPtrTypeInContext};
 use once_cell::sync::Lazy;
@@ -332,6 +338,24 @@ impl ContextImpl {
         }
     }
 
+    #[llvm_versions(8.0..=latest)]
+    fn self_referential_metadata_node<'ctx>(&self, operands: &[MetadataValue<'ctx>]) -> MetadataValue<'ctx> {
+        unsafe {
+            // A node can only refer to itself by replacing a temporary placeholder
+            // once it exists. This also makes LLVM treat the node as distinct.
+            let placeholder = LLVMTemporaryMDNode(self.0, ptr::null_mut(), 0);
+            let mut refs: Vec<LLVMMetadataRef> = vec![placeholder];
+
+            refs.extend(operands.iter().map(|operand| operand.as_metadata_ref()));
+
+            let node = LLVMMDNodeInContext2(self.0, refs.as_mut_ptr(), refs.len());
+
+            LLVMMetadataReplaceAllUsesWith(placeholder, node);
+
+            MetadataValue::new(LLVMMetadataAsValue(self.0, node))
+        }
+    }
+
     fn metadata_string<'ctx>(&self, string: &str) -> MetadataValue<'ctx> {
         let c_string = to_c_str(string);
 
@@ -2101,6 +2125,14 @@ impl<'ctx> ContextRef<'ctx> {
         self.context.const_string(string, null_terminated)
     }
 
+    /// Creates a distinct `MetadataValue` node whose first operand is the node
+    /// itself, followed by `operands`, as loop ids and alias scopes require.
+    #[llvm_versions(8.0..=latest)]
+    #[inline]
+    pub(crate) fn self_referential_metadata_node(&self, operands: &[MetadataValue<'ctx>]) -> MetadataValue<'ctx> {
+        self.context.self_referential_metadata_node(operands)
+    }
+
     #[inline]
     pub(crate) fn set_diagnostic_handler(
         &self,
@@ -0,0 +1,629 @@
+//! Source based code coverage, which counts how often regions of a program's
+//! source were executed.
+//!
+//! Functions are registered with `Coverage` along with the source regions
+//! they cover, and counters are incremented with `build_counter_increment`
+//! wherever a region begins executing. `Coverage::emit` then writes coverage
+//! mapping records into the module and lowers the counters, like
+//! `clang -fprofile-instr-generate -fcoverage-mapping`.
+//!
+//! A binary linked against the profile runtime, `libclang_rt.profile`, writes
+//! its counters to a `.profraw` file on exit, which can be reported on with
+//! `llvm-profdata merge` and `llvm-cov`. The runtime is only pulled in by
+//! `-u__llvm_profile_runtime` on Linux, which clang passes automatically.
+//! Alternatively, the `.profraw` file can be read with
+//! `InstrProfile::parse_raw`, given the names of the covered functions, and
+//! turned into a `CoverageReport` by `create_report`. Code run through an
+//! `ExecutionEngine` instead needs `emit_for_jit`, after which its counters
+//! are read by `read_counters`.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::coverage::{Coverage, SourceRegion};
+//! use inkwell::passes::PassBuilderOptions;
+//! use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+//! use inkwell::OptimizationLevel;
+//!
+//! Target::initialize_native(&InitializationConfig::default()).unwrap();
+//!
+//! let triple = TargetMachine::get_default_triple();
+//! let machine = Target::from_triple(&triple)
+//!     .unwrap()
+//!     .create_target_machine(&triple, "generic", "", OptimizationLevel::Default, RelocMode::Default, CodeModel::JITDefault)
+//!     .unwrap();
+//! let context = Context::create();
+//! let module = context.create_module("my_module");
+//! let fn_type = context.void_type().fn_type(&[], false);
+//! let function = module.add_function("main", fn_type, None);
+//! let builder = context.create_builder();
+//! let mut coverage = Coverage::new("/home/user/project");
+//! let region = SourceRegion {
+//!     file: "main.src".to_string(),
+//!     line_start: 1,
+//!     column_start: 1,
+//!     line_end: 3,
+//!     column_end: 2,
+//! };
+//!
+//! module.set_triple(&triple);
+//! coverage.add_function(&module, function, vec![region]).unwrap();
+//! builder.position_at_end(context.append_basic_block(function, "entry"));
+//! coverage.build_counter_increment(&builder, function, 0).unwrap();
+//! builder.build_return(None);
+//!
+//! coverage.emit_for_jit(&module, &machine, &PassBuilderOptions::create()).unwrap();
+//!
+//! let execution_engine = module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+//!
+//! unsafe {
+//!     execution_engine.run_function(function, &[]);
+//! }
+//!
+//! let report = coverage.read_counters(&execution_engine);
+//!
+//! assert_eq!(report.regions[0].count, 1);
+//! ```
+
+use llvm_sys::core::{LLVMGetNumOperands, LLVMGetOperand};
+
+use crate::builder::Builder;
+use crate::execution_engine::ExecutionEngine;
+use crate::intrinsics::Intrinsic;
+use crate::module::{Linkage, Module};
+use crate::passes::PassBuilderOptions;
+use crate::pgo::{define_profile_runtime, expose_counters, read_jit_counters, InstrProfile};
+use crate::support::LLVMString;
+use crate::targets::TargetMachine;
+use crate::values::{AsValueRef, FunctionValue, GlobalValue, PointerValue};
+use crate::{AddressSpace, GlobalVisibility};
+
+/// Version 6 of the coverage mapping format, which LLVM 13 through 16 read,
+/// as encoded in the mapping header, where versions are numbered from zero.
+const COVERAGE_MAPPING_VERSION: u64 = 5;
+
+/// A region of source code, such as a statement or block. Lines and columns
+/// start at one, and the end column is exclusive.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SourceRegion {
+    /// The path of the source file, which may be relative to the compilation
+    /// directory.
+    pub file: String,
+    /// The line the region starts on.
+    pub line_start: u32,
+    /// The column the region starts at.
+    pub column_start: u32,
+    /// The line the region ends on.
+    pub line_end: u32,
+    /// The column the region ends before.
+    pub column_end: u32,
+}
+
+/// How often a `SourceRegion` was executed.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RegionCoverage {
+    /// The name of the function the region is in.
+    pub function: String,
+    /// The region of source code.
+    pub region: SourceRegion,
+    /// How often the region was executed.
+    pub count: u64,
+}
+
+/// How often each region of every covered function was executed.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CoverageReport {
+    /// The regions, grouped by function in the order they were added.
+    pub regions: Vec<RegionCoverage>,
+}
+
+impl CoverageReport {
+    /// Gets the regions which were never executed.
+    pub fn get_uncovered_regions(&self) -> Vec<&RegionCoverage> {
+        self.regions.iter().filter(|region| region.count == 0).collect()
+    }
+}
+
+#[derive(Debug)]
+struct CoveredFunction<'ctx> {
+    function: FunctionValue<'ctx>,
+    name: String,
+    hash: u64,
+    regions: Vec<SourceRegion>,
+    name_var: GlobalValue<'ctx>,
+    increment: FunctionValue<'ctx>,
+}
+
+/// Source based code coverage instrumentation of a module's functions.
+#[derive(Debug)]
+pub struct Coverage<'ctx> {
+    compilation_dir: String,
+    functions: Vec<CoveredFunction<'ctx>>,
+}
+
+impl<'ctx> Coverage<'ctx> {
+    /// Creates coverage instrumentation for a module compiled in
+    /// `compilation_dir`, which relative source paths are resolved against.
+    pub fn new(compilation_dir: &str) -> Self {
+        Coverage {
+            compilation_dir: compilation_dir.to_string(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Registers a function of `module` and the source regions it covers. The
+    /// first region should span the whole function. Each region is given a
+    /// counter, identified by the region's index.
+    pub fn add_function(
+        &mut self,
+        module: &Module<'ctx>,
+        function: FunctionValue<'ctx>,
+        regions: Vec<SourceRegion>,
+    ) -> Result<(), &'static str> {
+        if self.functions.iter().any(|covered| covered.function == function) {
+            return Err("Function has already been added.");
+        }
+
+        if regions.is_empty() {
+            return Err("Function must cover at least one region.");
+        }
+
+        let is_ordered =
+            |region: &SourceRegion| (region.line_start, region.column_start) <= (region.line_end, region.column_end);
+
+        if !regions.iter().all(is_ordered) {
+            return Err("Region ends before it starts.");
+        }
+
+        let context = module.get_context();
+        let name = function.get_name().to_string_lossy().into_owned();
+        let name_value = context.const_string(name.as_bytes(), false);
+        let name_var = module.add_global(name_value.get_type(), None, &format!("__profn_{}", name));
+
+        name_var.set_constant(true);
+        name_var.set_linkage(Linkage::Private);
+        name_var.set_initializer(&name_value);
+
+        let increment = Intrinsic::find("llvm.instrprof.increment")
+            .and_then(|intrinsic| intrinsic.get_declaration(module, &[]))
+            .expect("llvm.instrprof.increment should always exist");
+
+        // The hash only needs to change when the regions do, so that stale
+        // profiles aren't matched to them
+        let mut regions_data = Vec::new();
+
+        for region in &regions {
+            regions_data.extend_from_slice(region.file.as_bytes());
+            regions_data.push(0);
+
+            for position in [
+                region.line_start,
+                region.column_start,
+                region.line_end,
+                region.column_end,
+            ] {
+                regions_data.extend_from_slice(&position.to_le_bytes());
+            }
+        }
+
+        self.functions.push(CoveredFunction {
+            function,
+            name,
+            hash: md5_hash(&regions_data),
+            regions,
+            name_var,
+            increment,
+        });
+
+        Ok(())
+    }
+
+    /// Gets the hash of an added function's regions, which identifies its
+    /// counters in a profile.
+    pub fn get_function_hash(&self, function: FunctionValue<'ctx>) -> Option<u64> {
+        self.functions
+            .iter()
+            .find(|covered| covered.function == function)
+            .map(|covered| covered.hash)
+    }
+
+    /// Builds an increment of the counter of a function's region at the
+    /// builder's position, which should be where the region begins executing.
+    pub fn build_counter_increment(
+        &self,
+        builder: &Builder<'ctx>,
+        function: FunctionValue<'ctx>,
+        region: u32,
+    ) -> Result<(), &'static str> {
+        let covered = self
+            .functions
+            .iter()
+            .find(|covered| covered.function == function)
+            .ok_or("Function has not been added.")?;
+
+        if region as usize >= covered.regions.len() {
+            return Err("Region index is out of bounds.");
+        }
+
+        let context = function.get_type().get_context();
+        let name_type = covered.increment.get_type().get_param_types()[0].into_pointer_type();
+        let name_ptr = covered.name_var.as_pointer_value().const_cast(name_type);
+        let i32_type = context.i32_type();
+
+        builder.build_call(
+            covered.increment,
+            &[
+                name_ptr.into(),
+                context.i64_type().const_int(covered.hash, false).into(),
+                i32_type.const_int(covered.regions.len() as u64, false).into(),
+                i32_type.const_int(region as u64, false).into(),
+            ],
+            "",
+        );
+
+        Ok(())
+    }
+
+    /// Writes the coverage mapping records into a module and lowers its
+    /// counters, for it to be linked against the profile runtime.
+    pub fn emit(
+        &self,
+        module: &Module<'ctx>,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+    ) -> Result<(), LLVMString> {
+        self.emit_mapping(module);
+
+        module.run_passes_with_options("instrprof", machine, options)
+    }
+
+    /// Writes the coverage mapping records into a module and lowers its
+    /// counters, for it to be run without the profile runtime, such as
+    /// through an `ExecutionEngine`. The module's triple must be set, as for
+    /// `PgoInstrumentation::instrument_for_jit`.
+    pub fn emit_for_jit(
+        &self,
+        module: &Module<'ctx>,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+    ) -> Result<(), LLVMString> {
+        define_profile_runtime(module);
+
+        self.emit(module, machine, options)?;
+
+        let counters: Vec<_> = self.functions.iter().map(CoveredFunction::counters_name).collect();
+
+        expose_counters(module, &counters);
+
+        Ok(())
+    }
+
+    /// Reads the counters of a module prepared by `emit_for_jit` from the
+    /// `ExecutionEngine` it was run in. Functions without counters, because
+    /// no increments were built in them or they were optimized away, are
+    /// reported as never executed.
+    pub fn read_counters(&self, execution_engine: &ExecutionEngine<'ctx>) -> CoverageReport {
+        let mut report = CoverageReport::default();
+
+        for covered in &self.functions {
+            let num_counters = covered.regions.len();
+            let counts = read_jit_counters(execution_engine, &covered.counters_name(), num_counters)
+                .unwrap_or_else(|| vec![0; num_counters]);
+
+            report.add_function(covered, &counts);
+        }
+
+        report
+    }
+
+    /// Creates a report from a profile written by a binary linked against the
+    /// profile runtime, as read by `InstrProfile::parse_raw` or converted with
+    /// `llvm-profdata merge --text`.
+    /// Functions missing from the profile, or whose regions have changed since
+    /// it was written, are reported as never executed.
+    pub fn create_report(&self, profile: &InstrProfile) -> CoverageReport {
+        let mut report = CoverageReport::default();
+
+        for covered in &self.functions {
+            let function_profile = profile.functions.iter().find(|function_profile| {
+                function_profile.name == covered.name
+                    && function_profile.hash == covered.hash
+                    && function_profile.counts.len() == covered.regions.len()
+            });
+
+            match function_profile {
+                Some(function_profile) => report.add_function(covered, &function_profile.counts),
+                None => report.add_function(covered, &vec![0; covered.regions.len()]),
+            }
+        }
+
+        report
+    }
+
+    fn emit_mapping(&self, module: &Module<'ctx>) {
+        let context = module.get_context();
+        let triple = module.get_triple();
+        let triple = triple.as_str().to_string_lossy();
+        let is_macho = ["apple", "darwin", "macos", "ios"].iter().any(|os| triple.contains(os));
+        let is_coff = triple.contains("windows");
+        let (covmap_section, covfun_section) = if is_macho {
+            ("__LLVM_COV,__llvm_covmap", "__LLVM_COV,__llvm_covfun")
+        } else if is_coff {
+            (".lcovmap$M", ".lcovfun$M")
+        } else {
+            ("__llvm_covmap", "__llvm_covfun")
+        };
+
+        // Since version 6 of the format, the first filename is the
+        // compilation directory
+        let mut filenames = vec![self.compilation_dir.as_str()];
+
+        for covered in &self.functions {
+            for region in &covered.regions {
+                if !filenames.contains(&region.file.as_str()) {
+                    filenames.push(&region.file);
+                }
+            }
+        }
+
+        let mut uncompressed_filenames = Vec::new();
+
+        for filename in &filenames {
+            write_uleb128(&mut uncompressed_filenames, filename.len() as u64);
+            uncompressed_filenames.extend_from_slice(filename.as_bytes());
+        }
+
+        let mut encoded_filenames = Vec::new();
+
+        write_uleb128(&mut encoded_filenames, filenames.len() as u64);
+        write_uleb128(&mut encoded_filenames, uncompressed_filenames.len() as u64);
+        // A compressed length of zero means the filenames aren't compressed
+        write_uleb128(&mut encoded_filenames, 0);
+        encoded_filenames.extend_from_slice(&uncompressed_filenames);
+
+        let filenames_ref = md5_hash(&encoded_filenames);
+        let i32_type = context.i32_type();
+        let i64_type = context.i64_type();
+        let mut records = Vec::new();
+
+        for covered in &self.functions {
+            let mapping = encode_mapping(&covered.regions, &filenames);
+            let name_ref = md5_hash(covered.name.as_bytes());
+            let record = context.const_struct(
+                &[
+                    i64_type.const_int(name_ref, false).into(),
+                    i32_type.const_int(mapping.len() as u64, false).into(),
+                    i64_type.const_int(covered.hash, false).into(),
+                    i64_type.const_int(filenames_ref, false).into(),
+                    context.const_string(&mapping, false).into(),
+                ],
+                true,
+            );
+            let record_name = format!("__covrec_{:X}", name_ref);
+            let global = module.add_global(record.get_type(), None, &record_name);
+
+            global.set_constant(true);
+            global.set_linkage(Linkage::LinkOnceODR);
+            global.set_visibility(GlobalVisibility::Hidden);
+            global.set_section(Some(covfun_section));
+            global.set_alignment(8);
+            global.set_initializer(&record);
+
+            if !is_macho {
+                global.set_comdat(module.get_or_insert_comdat(&record_name));
+            }
+
+            records.push(global);
+        }
+
+        let header = context.const_struct(
+            &[
+                // Function records have their own section since version 4
+                i32_type.const_zero().into(),
+                i32_type.const_int(encoded_filenames.len() as u64, false).into(),
+                i32_type.const_zero().into(),
+                i32_type.const_int(COVERAGE_MAPPING_VERSION, false).into(),
+            ],
+            false,
+        );
+        let mapping = context.const_struct(
+            &[header.into(), context.const_string(&encoded_filenames, false).into()],
+            false,
+        );
+        let global = module.add_global(mapping.get_type(), None, "__llvm_coverage_mapping");
+
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_section(Some(covmap_section));
+        global.set_alignment(8);
+        global.set_initializer(&mapping);
+
+        // Nothing references the records, so they'd be removed by
+        // optimizations unless marked as used
+        records.push(global);
+        add_compiler_used(module, &records);
+    }
+}
+
+impl CoveredFunction<'_> {
+    fn counters_name(&self) -> String {
+        format!("__profc_{}", self.name)
+    }
+}
+
+impl CoverageReport {
+    fn add_function(&mut self, covered: &CoveredFunction, counts: &[u64]) {
+        for (region, count) in covered.regions.iter().zip(counts) {
+            self.regions.push(RegionCoverage {
+                function: covered.name.clone(),
+                region: region.clone(),
+                count: *count,
+            });
+        }
+    }
+}
+
+// Appends globals to llvm.compiler.used, which keeps them from being removed
+// before code generation
+fn add_compiler_used<'ctx>(module: &Module<'ctx>, globals: &[GlobalValue<'ctx>]) {
+    let ptr_type = module.get_context().i8_type().ptr_type(AddressSpace::default());
+    let mut used = Vec::new();
+
+    if let Some(existing) = module.get_global("llvm.compiler.used") {
+        if let Some(initializer) = existing.get_initializer() {
+            let initializer = initializer.as_value_ref();
+
+            for index in 0..unsafe { LLVMGetNumOperands(initializer) } {
+                used.push(unsafe { PointerValue::new(LLVMGetOperand(initializer, index as u32)) });
+            }
+        }
+
+        unsafe { existing.delete() };
+    }
+
+    used.extend(
+        globals
+            .iter()
+            .map(|global| global.as_pointer_value().const_cast(ptr_type)),
+    );
+
+    let used_value = ptr_type.const_array(&used);
+    let global = module.add_global(used_value.get_type(), None, "llvm.compiler.used");
+
+    global.set_linkage(Linkage::Appending);
+    global.set_section(Some("llvm.metadata"));
+    global.set_initializer(&used_value);
+}
+
+// Encodes a function's regions, whose counters are numbered by their index
+fn encode_mapping(regions: &[SourceRegion], filenames: &[&str]) -> Vec<u8> {
+    // Regions are grouped by file, numbered in order of first appearance, so
+    // the function's own file comes first
+    let mut files: Vec<&str> = Vec::new();
+
+    for region in regions {
+        if !files.contains(&region.file.as_str()) {
+            files.push(&region.file);
+        }
+    }
+
+    let file_id = |region: &SourceRegion| files.iter().position(|file| *file == region.file).unwrap();
+    let mut sorted_regions: Vec<_> = regions.iter().enumerate().collect();
+
+    sorted_regions.sort_by_key(|(_, region)| (file_id(region), region.line_start, region.column_start));
+
+    let mut mapping = Vec::new();
+
+    write_uleb128(&mut mapping, files.len() as u64);
+
+    for file in &files {
+        let index = filenames.iter().position(|filename| filename == file).unwrap();
+
+        write_uleb128(&mut mapping, index as u64);
+    }
+
+    // No counter expressions are used
+    write_uleb128(&mut mapping, 0);
+
+    for (id, _) in files.iter().enumerate() {
+        let file_regions: Vec<_> = sorted_regions
+            .iter()
+            .filter(|(_, region)| file_id(region) == id)
+            .collect();
+        let mut previous_line_start = 0;
+
+        write_uleb128(&mut mapping, file_regions.len() as u64);
+
+        for (counter, region) in file_regions {
+            // A reference to a counter is tagged with 1 in its low two bits
+            write_uleb128(&mut mapping, ((*counter as u64) << 2) | 1);
+            write_uleb128(&mut mapping, (region.line_start - previous_line_start) as u64);
+            write_uleb128(&mut mapping, region.column_start as u64);
+            write_uleb128(&mut mapping, (region.line_end - region.line_start) as u64);
+            write_uleb128(&mut mapping, region.column_end as u64);
+
+            previous_line_start = region.line_start;
+        }
+    }
+
+    mapping
+}
+
+fn write_uleb128(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+// LLVM identifies names and filename tables by the low half of their MD5
+pub(crate) fn md5_hash(data: &[u8]) -> u64 {
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14,
+        20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6,
+        10, 15, 21,
+    ];
+    const CONSTANTS: [u32; 64] = [
+        0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501, 0x698098d8,
+        0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340,
+        0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8, 0x21e1cde6, 0xc33707d6, 0xf4d50d87,
+        0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+        0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039,
+        0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, 0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92,
+        0xffeff47d, 0x85845dd1, 0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb,
+        0xeb86d391,
+    ];
+
+    let mut message = data.to_vec();
+
+    message.push(0x80);
+
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    for chunk in message.chunks(64) {
+        let words: Vec<u32> = chunk
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(CONSTANTS[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[i]);
+
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    state[0] as u64 | (state[1] as u64) << 32
+}
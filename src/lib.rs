@@ -17,6 +17,9 @@ extern crate inkwell_internals;
 #[macro_use]
 pub mod support;
 #[deny(missing_docs)]
+#[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0", feature = "llvm7-0")))]
+pub mod alias_scope;
+#[deny(missing_docs)]
 pub mod attributes;
 #[deny(missing_docs)]
 pub mod basic_block;
@@ -26,17 +29,76 @@ pub mod builder;
 pub mod comdat;
 #[deny(missing_docs)]
 pub mod context;
+#[deny(missing_docs)]
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0"
+)))]
+pub mod coverage;
 pub mod data_layout;
 #[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0")))]
 pub mod debug_info;
 pub mod execution_engine;
 pub mod intrinsics;
+#[deny(missing_docs)]
+#[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0", feature = "llvm7-0")))]
+pub mod loop_hints;
+#[deny(missing_docs)]
+#[cfg(feature = "lto")]
+pub mod lto;
 pub mod memory_buffer;
 #[deny(missing_docs)]
 pub mod module;
 pub mod object_file;
 pub mod passes;
+#[deny(missing_docs)]
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0"
+)))]
+pub mod pgo;
+#[deny(missing_docs)]
+pub mod pipeline;
+#[deny(missing_docs)]
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0"
+)))]
+pub mod remarks;
+#[deny(missing_docs)]
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0",
+    feature = "llvm13-0"
+)))]
+pub mod sanitizers;
 pub mod targets;
+#[deny(missing_docs)]
+pub mod tbaa;
 pub mod types;
 pub mod values;
 
@@ -455,3 +517,169 @@ pub enum InlineAsmDialect {
     #[llvm_variant(LLVMInlineAsmDialectIntel)]
     Intel,
 }
+
+/// A function's calling convention, which must match the calling convention
+/// of any call to it, or the behavior is undefined.
+///
+/// # Remarks
+/// See also: https://llvm.org/docs/LangRef.html#calling-conventions
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CallingConvention {
+    /// The C calling convention, which is the default.
+    C,
+    /// Makes calls as fast as possible, possibly using a non-standard ABI.
+    Fast,
+    /// Makes calls to rarely called functions as cheap as possible for the caller.
+    Cold,
+    /// The Glasgow Haskell Compiler calling convention.
+    Ghc,
+    /// The High-Performance Erlang calling convention.
+    HiPE,
+    /// Preserves every register but the return value's, for dynamic call sites.
+    AnyReg,
+    /// Preserves most registers, making calls cheap for the caller.
+    PreserveMost,
+    /// Preserves all registers, including floating point ones.
+    PreserveAll,
+    /// The Swift calling convention.
+    Swift,
+    /// The calling convention for C++ thread local variable access functions.
+    CxxFastTls,
+    /// Guarantees tail calls, for functional language runtimes.
+    Tail,
+    /// The Control Flow Guard check function's calling convention.
+    CfGuardCheck,
+    /// The Swift calling convention, with guaranteed tail calls.
+    SwiftTail,
+    /// The x86 `stdcall` calling convention.
+    X86StdCall,
+    /// The x86 `fastcall` calling convention.
+    X86FastCall,
+    /// The ARM APCS calling convention.
+    ArmApcs,
+    /// The ARM AAPCS calling convention.
+    ArmAapcs,
+    /// The ARM AAPCS calling convention, passing floating point values in VFP registers.
+    ArmAapcsVfp,
+    /// The MSP430 interrupt handler calling convention.
+    Msp430Interrupt,
+    /// The x86 `thiscall` calling convention.
+    X86ThisCall,
+    /// The calling convention of PTX kernel entry points.
+    PtxKernel,
+    /// The calling convention of PTX device functions.
+    PtxDevice,
+    /// The calling convention of SPIR non-kernel functions.
+    SpirFunc,
+    /// The calling convention of SPIR kernel entry points.
+    SpirKernel,
+    /// The Intel OpenCL built-ins calling convention.
+    IntelOclBi,
+    /// The x86-64 System V calling convention, used on most non-Windows systems.
+    X86_64SysV,
+    /// The x86-64 Windows calling convention.
+    Win64,
+    /// The x86 `vectorcall` calling convention.
+    X86VectorCall,
+    /// The x86 interrupt handler calling convention.
+    X86Interrupt,
+    /// The AVR interrupt handler calling convention.
+    AvrInterrupt,
+    /// The AVR signal handler calling convention.
+    AvrSignal,
+    /// The calling convention of AMDGPU kernel entry points.
+    AmdgpuKernel,
+    /// The x86 `regcall` calling convention.
+    X86RegCall,
+    /// Any other calling convention, by its numeric id.
+    Other(u32),
+}
+
+impl From<u32> for CallingConvention {
+    fn from(id: u32) -> Self {
+        match id {
+            0 => CallingConvention::C,
+            8 => CallingConvention::Fast,
+            9 => CallingConvention::Cold,
+            10 => CallingConvention::Ghc,
+            11 => CallingConvention::HiPE,
+            13 => CallingConvention::AnyReg,
+            14 => CallingConvention::PreserveMost,
+            15 => CallingConvention::PreserveAll,
+            16 => CallingConvention::Swift,
+            17 => CallingConvention::CxxFastTls,
+            18 => CallingConvention::Tail,
+            19 => CallingConvention::CfGuardCheck,
+            20 => CallingConvention::SwiftTail,
+            64 => CallingConvention::X86StdCall,
+            65 => CallingConvention::X86FastCall,
+            66 => CallingConvention::ArmApcs,
+            67 => CallingConvention::ArmAapcs,
+            68 => CallingConvention::ArmAapcsVfp,
+            69 => CallingConvention::Msp430Interrupt,
+            70 => CallingConvention::X86ThisCall,
+            71 => CallingConvention::PtxKernel,
+            72 => CallingConvention::PtxDevice,
+            75 => CallingConvention::SpirFunc,
+            76 => CallingConvention::SpirKernel,
+            77 => CallingConvention::IntelOclBi,
+            78 => CallingConvention::X86_64SysV,
+            79 => CallingConvention::Win64,
+            80 => CallingConvention::X86VectorCall,
+            83 => CallingConvention::X86Interrupt,
+            84 => CallingConvention::AvrInterrupt,
+            85 => CallingConvention::AvrSignal,
+            91 => CallingConvention::AmdgpuKernel,
+            92 => CallingConvention::X86RegCall,
+            id => CallingConvention::Other(id),
+        }
+    }
+}
+
+impl From<CallingConvention> for u32 {
+    fn from(calling_convention: CallingConvention) -> Self {
+        match calling_convention {
+            CallingConvention::C => 0,
+            CallingConvention::Fast => 8,
+            CallingConvention::Cold => 9,
+            CallingConvention::Ghc => 10,
+            CallingConvention::HiPE => 11,
+            CallingConvention::AnyReg => 13,
+            CallingConvention::PreserveMost => 14,
+            CallingConvention::PreserveAll => 15,
+            CallingConvention::Swift => 16,
+            CallingConvention::CxxFastTls => 17,
+            CallingConvention::Tail => 18,
+            CallingConvention::CfGuardCheck => 19,
+            CallingConvention::SwiftTail => 20,
+            CallingConvention::X86StdCall => 64,
+            CallingConvention::X86FastCall => 65,
+            CallingConvention::ArmApcs => 66,
+            CallingConvention::ArmAapcs => 67,
+            CallingConvention::ArmAapcsVfp => 68,
+            CallingConvention::Msp430Interrupt => 69,
+            CallingConvention::X86ThisCall => 70,
+            CallingConvention::PtxKernel => 71,
+            CallingConvention::PtxDevice => 72,
+            CallingConvention::SpirFunc => 75,
+            CallingConvention::SpirKernel => 76,
+            CallingConvention::IntelOclBi => 77,
+            CallingConvention::X86_64SysV => 78,
+            CallingConvention::Win64 => 79,
+            CallingConvention::X86VectorCall => 80,
+            CallingConvention::X86Interrupt => 83,
+            CallingConvention::AvrInterrupt => 84,
+            CallingConvention::AvrSignal => 85,
+            CallingConvention::AmdgpuKernel => 91,
+            CallingConvention::X86RegCall => 92,
+            CallingConvention::Other(id) => id,
+        }
+    }
+}
+
+impl Default for CallingConvention {
+    /// Returns the default value for `CallingConvention`, namely `CallingConvention::C`.
+    fn default() -> Self {
+        CallingConvention::C
+    }
+}
@@ -0,0 +1,209 @@
+//! `llvm.loop` metadata, which passes hints such as unroll counts and
+//! vectorization widths on to LLVM's loop optimizations.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::loop_hints::{LoopHints, LoopUnroll};
+//!
+//! let context = Context::create();
+//! let module = context.create_module("loops");
+//! let builder = context.create_builder();
+//! let fn_type = context.void_type().fn_type(&[context.bool_type().into()], false);
+//! let function = module.add_function("my_fn", fn_type, None);
+//! let entry = context.append_basic_block(function, "entry");
+//! let body = context.append_basic_block(function, "body");
+//! let exit = context.append_basic_block(function, "exit");
+//! let done = function.get_first_param().unwrap().into_int_value();
+//!
+//! builder.position_at_end(entry);
+//! builder.build_unconditional_branch(body);
+//! builder.position_at_end(body);
+//!
+//! let latch_branch = builder.build_conditional_branch(done, exit, body);
+//!
+//! builder.position_at_end(exit);
+//! builder.build_return(None);
+//!
+//! let loop_id = LoopHints::new()
+//!     .unroll(LoopUnroll::Count(4))
+//!     .vectorize_width(8)
+//!     .must_progress(true)
+//!     .attach(latch_branch)
+//!     .unwrap();
+//!
+//! assert_eq!(latch_branch.get_metadata(context.get_kind_id("llvm.loop")), Some(loop_id));
+//! ```
+
+use llvm_sys::core::{LLVMGetTypeContext, LLVMTypeOf};
+
+use crate::context::ContextRef;
+use crate::values::{AsValueRef, BasicMetadataValueEnum, InstructionOpcode, InstructionValue, MetadataValue};
+
+/// How a loop should be unrolled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum LoopUnroll {
+    /// Never unroll the loop (`llvm.loop.unroll.disable`).
+    Disable,
+    /// Unroll the loop if profitable, even when it otherwise wouldn't be
+    /// considered (`llvm.loop.unroll.enable`).
+    Enable,
+    /// Fully unroll the loop if its trip count is known (`llvm.loop.unroll.full`).
+    Full,
+    /// Unroll the loop by the given factor (`llvm.loop.unroll.count`).
+    Count(u32),
+}
+
+/// A set of hints for a loop, which becomes its `llvm.loop` metadata once
+/// attached to the loop's latch branch. Hints left unset are left to LLVM's
+/// heuristics.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct LoopHints<'ctx> {
+    unroll: Option<LoopUnroll>,
+    vectorize_enable: Option<bool>,
+    vectorize_width: Option<u32>,
+    interleave_count: Option<u32>,
+    distribute: Option<bool>,
+    must_progress: bool,
+    parallel_accesses: Vec<MetadataValue<'ctx>>,
+}
+
+impl<'ctx> LoopHints<'ctx> {
+    /// Creates an empty set of `LoopHints`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the loop should be unrolled.
+    pub fn unroll(mut self, unroll: LoopUnroll) -> Self {
+        self.unroll = Some(unroll);
+        self
+    }
+
+    /// Enables or disables vectorization of the loop (`llvm.loop.vectorize.enable`).
+    pub fn vectorize_enable(mut self, enable: bool) -> Self {
+        self.vectorize_enable = Some(enable);
+        self
+    }
+
+    /// Sets the vectorization width of the loop (`llvm.loop.vectorize.width`).
+    /// A width of 1 disables vectorization.
+    pub fn vectorize_width(mut self, width: u32) -> Self {
+        self.vectorize_width = Some(width);
+        self
+    }
+
+    /// Sets the interleave count of the loop (`llvm.loop.interleave.count`).
+    /// A count of 1 disables interleaving.
+    pub fn interleave_count(mut self, count: u32) -> Self {
+        self.interleave_count = Some(count);
+        self
+    }
+
+    /// Enables or disables loop distribution (`llvm.loop.distribute.enable`).
+    pub fn distribute(mut self, enable: bool) -> Self {
+        self.distribute = Some(enable);
+        self
+    }
+
+    /// Sets whether the loop must make forward progress, meaning it may be
+    /// assumed to terminate if it has no side effects (`llvm.loop.mustprogress`).
+    pub fn must_progress(mut self, must_progress: bool) -> Self {
+        self.must_progress = must_progress;
+        self
+    }
+
+    /// Marks the memory accesses in the given access groups as free of loop
+    /// carried dependencies within this loop (`llvm.loop.parallel_accesses`).
+    ///
+    /// Memory instructions are added to an access group by attaching it as
+    /// `llvm.access.group` metadata. LLVM requires access groups to be
+    /// distinct empty nodes, which the LLVM C API cannot create, so they must
+    /// come from elsewhere, such as IR parsed by `Context::create_module_from_ir`.
+    pub fn parallel_accesses(mut self, access_groups: &[MetadataValue<'ctx>]) -> Self {
+        self.parallel_accesses.extend_from_slice(access_groups);
+        self
+    }
+
+    /// Builds the self-referential `llvm.loop` node for these hints and
+    /// attaches it to `latch_branch`, the branch back to the loop's header.
+    /// Returns the node, or an error if `latch_branch` is not a branch.
+    pub fn attach(&self, latch_branch: InstructionValue<'ctx>) -> Result<MetadataValue<'ctx>, &'static str> {
+        if latch_branch.get_opcode() != InstructionOpcode::Br {
+            return Err("Loop hints can only be attached to a branch.");
+        }
+
+        let context_ref = unsafe { LLVMGetTypeContext(LLVMTypeOf(latch_branch.as_value_ref())) };
+        let context = unsafe { ContextRef::new(context_ref) };
+        let i1_type = context.bool_type();
+        let i32_type = context.i32_type();
+        let hint = |name: &str, operand: Option<BasicMetadataValueEnum<'ctx>>| {
+            let name = context.metadata_string(name).into();
+
+            match operand {
+                Some(operand) => context.metadata_node(&[name, operand]),
+                None => context.metadata_node(&[name]),
+            }
+        };
+        let mut hints = Vec::new();
+
+        match self.unroll {
+            Some(LoopUnroll::Disable) => hints.push(hint("llvm.loop.unroll.disable", None)),
+            Some(LoopUnroll::Enable) => hints.push(hint("llvm.loop.unroll.enable", None)),
+            Some(LoopUnroll::Full) => hints.push(hint("llvm.loop.unroll.full", None)),
+            Some(LoopUnroll::Count(count)) => hints.push(hint(
+                "llvm.loop.unroll.count",
+                Some(i32_type.const_int(count as u64, false).into()),
+            )),
+            None => {},
+        }
+
+        if let Some(enable) = self.vectorize_enable {
+            hints.push(hint(
+                "llvm.loop.vectorize.enable",
+                Some(i1_type.const_int(enable as u64, false).into()),
+            ));
+        }
+
+        if let Some(width) = self.vectorize_width {
+            hints.push(hint(
+                "llvm.loop.vectorize.width",
+                Some(i32_type.const_int(width as u64, false).into()),
+            ));
+        }
+
+        if let Some(count) = self.interleave_count {
+            hints.push(hint(
+                "llvm.loop.interleave.count",
+                Some(i32_type.const_int(count as u64, false).into()),
+            ));
+        }
+
+        if let Some(enable) = self.distribute {
+            hints.push(hint(
+                "llvm.loop.distribute.enable",
+                Some(i1_type.const_int(enable as u64, false).into()),
+            ));
+        }
+
+        if self.must_progress {
+            hints.push(hint("llvm.loop.mustprogress", None));
+        }
+
+        if !self.parallel_accesses.is_empty() {
+            let mut operands = vec![context.metadata_string("llvm.loop.parallel_accesses").into()];
+
+            operands.extend(
+                self.parallel_accesses
+                    .iter()
+                    .map(|&group| BasicMetadataValueEnum::from(group)),
+            );
+            hints.push(context.metadata_node(&operands));
+        }
+
+        let loop_id = context.self_referential_metadata_node(&hints);
+
+        latch_branch.set_metadata(loop_id, context.get_kind_id("llvm.loop"))?;
+
+        Ok(loop_id)
+    }
+}
@@ -0,0 +1,419 @@
+//! Link time optimization through libLTO, the library linkers such as ld64 and
+//! lld's legacy LTO support use to merge, optimize and compile bitcode.
+//!
+//! `LtoCodeGenerator` performs full LTO, merging every module into one before
+//! optimizing and compiling it to a single object. `ThinLtoCodeGenerator`
+//! performs ThinLTO, which optimizes and compiles each module separately using
+//! a summary of the others, and can cache its results between builds.
+//!
+//! ThinLTO needs bitcode with a summary, as written by `clang -flto=thin` or
+//! `opt -module-summary`. The C API can't write summaries, so bitcode from
+//! `Module::write_bitcode_to_memory` can only be used for full LTO.
+//!
+//! libLTO is a separate library from the rest of LLVM, so this module is only
+//! available with the `lto` feature, which links against it. Bitcode must be
+//! readable by the libLTO being linked against, so it should come from the same
+//! version of LLVM.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::lto::{LtoCodeGenerator, LtoModule};
+//!
+//! let context = Context::create();
+//! let module = context.create_module("my_module");
+//! let fn_type = context.void_type().fn_type(&[], false);
+//! let function = module.add_function("main", fn_type, None);
+//! let builder = context.create_builder();
+//!
+//! builder.position_at_end(context.append_basic_block(function, "entry"));
+//! builder.build_return(None);
+//!
+//! let bitcode = module.write_bitcode_to_memory();
+//! let lto_module = LtoModule::create_from_memory_buffer(&bitcode).unwrap();
+//! let mut codegen = LtoCodeGenerator::create();
+//!
+//! codegen.add_module(lto_module).unwrap();
+//! codegen.add_must_preserve_symbol("main");
+//!
+//! let object = codegen.compile().unwrap();
+//! ```
+
+use llvm_sys::lto::{
+    lto_code_gen_t, lto_codegen_add_module, lto_codegen_add_must_preserve_symbol, lto_codegen_compile,
+    lto_codegen_create, lto_codegen_dispose, lto_codegen_model, lto_codegen_set_cpu, lto_codegen_set_pic_model,
+    lto_codegen_set_should_internalize, lto_codegen_write_merged_modules, lto_get_error_message,
+    lto_module_create_from_memory, lto_module_create_from_memory_with_path, lto_module_dispose,
+    lto_module_get_num_symbols, lto_module_get_symbol_name, lto_module_get_target_triple, lto_module_is_thinlto,
+    lto_module_t, thinlto_code_gen_t, thinlto_codegen_add_cross_referenced_symbol, thinlto_codegen_add_module,
+    thinlto_codegen_add_must_preserve_symbol, thinlto_codegen_dispose, thinlto_codegen_process,
+    thinlto_codegen_set_cache_dir, thinlto_codegen_set_cache_entry_expiration,
+    thinlto_codegen_set_cache_pruning_interval, thinlto_codegen_set_cpu,
+    thinlto_codegen_set_final_cache_size_relative_to_available_space, thinlto_codegen_set_pic_model,
+    thinlto_create_codegen, thinlto_debug_options, thinlto_module_get_num_objects, thinlto_module_get_object,
+};
+
+use crate::memory_buffer::MemoryBuffer;
+use crate::support::{to_c_str, LLVMString};
+use crate::targets::RelocMode;
+use crate::OptimizationLevel;
+
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::path::Path;
+
+// The lto_* and thinlto_* symbols are only exported by libLTO
+#[link(name = "LTO")]
+extern "C" {}
+
+// llvm-sys doesn't expose the fields of `LTOObjectBuffer`, so it's read
+// through a struct with the same layout
+#[repr(C)]
+struct ObjectBuffer {
+    buffer: *const ::libc::c_char,
+    size: ::libc::size_t,
+}
+
+fn get_error_message() -> LLVMString {
+    unsafe { LLVMString::create_from_c_str(CStr::from_ptr(lto_get_error_message())) }
+}
+
+fn to_codegen_model(reloc_mode: RelocMode) -> lto_codegen_model {
+    match reloc_mode {
+        RelocMode::Default => lto_codegen_model::LTO_CODEGEN_PIC_MODEL_DEFAULT,
+        RelocMode::Static => lto_codegen_model::LTO_CODEGEN_PIC_MODEL_STATIC,
+        RelocMode::PIC => lto_codegen_model::LTO_CODEGEN_PIC_MODEL_DYNAMIC,
+        RelocMode::DynamicNoPic => lto_codegen_model::LTO_CODEGEN_PIC_MODEL_DYNAMIC_NO_PIC,
+    }
+}
+
+/// Sets the optimization level of code generators created afterwards, both
+/// full and ThinLTO. It defaults to `OptimizationLevel::Default`.
+///
+/// libLTO only reads its optimization level from a process wide option, so
+/// this affects every code generator in the process.
+pub fn set_opt_level(opt_level: OptimizationLevel) {
+    let option = match opt_level {
+        OptimizationLevel::None => "-O0\0",
+        OptimizationLevel::Less => "-O1\0",
+        OptimizationLevel::Default => "-O2\0",
+        OptimizationLevel::Aggressive => "-O3\0",
+    };
+    let options = [option.as_ptr() as *const ::libc::c_char];
+
+    unsafe { thinlto_debug_options(options.as_ptr(), options.len() as i32) }
+}
+
+/// A bitcode module loaded by libLTO, to be added to an `LtoCodeGenerator`.
+#[derive(Debug)]
+pub struct LtoModule {
+    module: lto_module_t,
+}
+
+impl LtoModule {
+    unsafe fn new(module: lto_module_t) -> Result<Self, LLVMString> {
+        if module.is_null() {
+            return Err(get_error_message());
+        }
+
+        Ok(LtoModule { module })
+    }
+
+    /// Loads a module from a `MemoryBuffer` holding bitcode, such as one from
+    /// `Module::write_bitcode_to_memory`.
+    pub fn create_from_memory_buffer(memory_buffer: &MemoryBuffer) -> Result<Self, LLVMString> {
+        let bytes = memory_buffer.as_slice();
+
+        unsafe { LtoModule::new(lto_module_create_from_memory(bytes.as_ptr().cast(), bytes.len())) }
+    }
+
+    /// Loads a module from a `MemoryBuffer` holding bitcode, using `path` as
+    /// its identifier in diagnostics.
+    pub fn create_from_memory_buffer_with_path(memory_buffer: &MemoryBuffer, path: &str) -> Result<Self, LLVMString> {
+        let bytes = memory_buffer.as_slice();
+        let path = to_c_str(path);
+
+        unsafe {
+            LtoModule::new(lto_module_create_from_memory_with_path(
+                bytes.as_ptr().cast(),
+                bytes.len(),
+                path.as_ptr(),
+            ))
+        }
+    }
+
+    /// Gets the target triple this module was compiled for.
+    pub fn get_target_triple(&self) -> &CStr {
+        unsafe { CStr::from_ptr(lto_module_get_target_triple(self.module)) }
+    }
+
+    /// Counts the symbols this module defines or references.
+    pub fn count_symbols(&self) -> u32 {
+        unsafe { lto_module_get_num_symbols(self.module) }
+    }
+
+    /// Gets the name of the symbol at `index`, if there is one.
+    pub fn get_symbol_name(&self, index: u32) -> Option<&CStr> {
+        if index >= self.count_symbols() {
+            return None;
+        }
+
+        unsafe { Some(CStr::from_ptr(lto_module_get_symbol_name(self.module, index))) }
+    }
+
+    /// Determines whether this module has a ThinLTO summary, as written by
+    /// `clang -flto=thin`. Bitcode from `Module::write_bitcode_to_memory`
+    /// never has one.
+    pub fn is_thinlto(&self) -> bool {
+        unsafe { lto_module_is_thinlto(self.module) != 0 }
+    }
+}
+
+impl Drop for LtoModule {
+    fn drop(&mut self) {
+        unsafe { lto_module_dispose(self.module) }
+    }
+}
+
+/// A full LTO code generator, which links modules together, optimizes the
+/// result and compiles it to a single native object.
+#[derive(Debug)]
+pub struct LtoCodeGenerator {
+    code_gen: lto_code_gen_t,
+    // Added modules have their contents moved into the merged module but
+    // must not be disposed of before the code generator
+    modules: Vec<LtoModule>,
+}
+
+impl LtoCodeGenerator {
+    /// Creates a code generator, using the optimization level last given to
+    /// `set_opt_level`.
+    pub fn create() -> Self {
+        let code_gen = unsafe { lto_codegen_create() };
+
+        assert!(!code_gen.is_null());
+
+        LtoCodeGenerator {
+            code_gen,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Links a module into the merged module.
+    pub fn add_module(&mut self, module: LtoModule) -> Result<(), LLVMString> {
+        let failed = unsafe { lto_codegen_add_module(self.code_gen, module.module) != 0 };
+
+        self.modules.push(module);
+
+        if failed {
+            return Err(get_error_message());
+        }
+
+        Ok(())
+    }
+
+    /// Keeps a symbol from being internalized or removed, such as one which is
+    /// referenced by native objects being linked in.
+    pub fn add_must_preserve_symbol(&mut self, symbol: &str) {
+        let symbol = to_c_str(symbol);
+
+        unsafe { lto_codegen_add_must_preserve_symbol(self.code_gen, symbol.as_ptr()) }
+    }
+
+    /// Sets the CPU to generate code for, such as `x86-64` or `apple-m1`.
+    pub fn set_cpu(&mut self, cpu: &str) {
+        let cpu = to_c_str(cpu);
+
+        unsafe { lto_codegen_set_cpu(self.code_gen, cpu.as_ptr()) }
+    }
+
+    /// Sets the relocation model of the generated code.
+    pub fn set_pic_model(&mut self, reloc_mode: RelocMode) {
+        unsafe {
+            lto_codegen_set_pic_model(self.code_gen, to_codegen_model(reloc_mode));
+        }
+    }
+
+    /// Sets whether symbols which aren't preserved are internalized, letting
+    /// them be removed or inlined away. This defaults to true.
+    pub fn set_should_internalize(&mut self, should_internalize: bool) {
+        unsafe { lto_codegen_set_should_internalize(self.code_gen, should_internalize as u8) }
+    }
+
+    /// Writes the merged module's bitcode to `path`, before it is optimized.
+    pub fn write_merged_modules(&mut self, path: &Path) -> Result<(), LLVMString> {
+        let path = to_c_str(path.to_str().expect("Did not find a valid Unicode path string"));
+
+        if unsafe { lto_codegen_write_merged_modules(self.code_gen, path.as_ptr()) } != 0 {
+            return Err(get_error_message());
+        }
+
+        Ok(())
+    }
+
+    /// Optimizes the merged module and compiles it to a native object.
+    pub fn compile(&mut self) -> Result<MemoryBuffer, LLVMString> {
+        let mut length = 0;
+        let object = unsafe { lto_codegen_compile(self.code_gen, &mut length) };
+
+        if object.is_null() {
+            return Err(get_error_message());
+        }
+
+        // The object is owned by the code generator and freed by its next call
+        let bytes = unsafe { std::slice::from_raw_parts(object as *const u8, length) };
+
+        Ok(MemoryBuffer::create_from_memory_range_copy(bytes, "lto.o"))
+    }
+}
+
+impl Drop for LtoCodeGenerator {
+    fn drop(&mut self) {
+        unsafe { lto_codegen_dispose(self.code_gen) }
+    }
+}
+
+/// A ThinLTO code generator, which optimizes and compiles each module to its
+/// own native object using a summary of the others.
+///
+/// Modules must have a ThinLTO summary, as written by `clang -flto=thin` or
+/// `opt -module-summary`, which `Module::write_bitcode_to_memory` can't write.
+/// The C API can't report errors while processing, so LLVM aborts on them
+/// instead.
+#[derive(Debug)]
+pub struct ThinLtoCodeGenerator<'a> {
+    code_gen: thinlto_code_gen_t,
+    identifiers: Vec<CString>,
+    _marker: PhantomData<&'a MemoryBuffer>,
+}
+
+impl<'a> ThinLtoCodeGenerator<'a> {
+    /// Creates a code generator, using the optimization level last given to
+    /// `set_opt_level`.
+    pub fn create() -> Result<Self, LLVMString> {
+        let code_gen = unsafe { thinlto_create_codegen() };
+
+        if code_gen.is_null() {
+            return Err(get_error_message());
+        }
+
+        Ok(ThinLtoCodeGenerator {
+            code_gen,
+            identifiers: Vec::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Adds a module's bitcode, which must outlive the code generator. The
+    /// identifier, usually the module's path, must be unique and is used as
+    /// the key of its cache entries.
+    ///
+    /// Returns an error if the bitcode can't be read or has no ThinLTO
+    /// summary, such as bitcode from `Module::write_bitcode_to_memory`, since
+    /// `process` would crash on it.
+    pub fn add_module(&mut self, identifier: &str, memory_buffer: &'a MemoryBuffer) -> Result<(), LLVMString> {
+        if !LtoModule::create_from_memory_buffer(memory_buffer)?.is_thinlto() {
+            return Err(LLVMString::create_from_c_str(&to_c_str(
+                "The module has no ThinLTO summary.",
+            )));
+        }
+
+        let identifier = CString::new(identifier).expect("Identifier must not contain a null byte");
+        let bytes = memory_buffer.as_slice();
+
+        unsafe {
+            thinlto_codegen_add_module(
+                self.code_gen,
+                identifier.as_ptr(),
+                bytes.as_ptr().cast(),
+                bytes.len() as i32,
+            );
+        }
+
+        self.identifiers.push(identifier);
+
+        Ok(())
+    }
+
+    /// Keeps a symbol from being internalized or removed, such as one which is
+    /// referenced by native objects being linked in.
+    pub fn add_must_preserve_symbol(&mut self, symbol: &str) {
+        unsafe { thinlto_codegen_add_must_preserve_symbol(self.code_gen, symbol.as_ptr().cast(), symbol.len() as i32) }
+    }
+
+    /// Marks a symbol as referenced from outside the module defining it, so
+    /// it isn't made local to that module.
+    pub fn add_cross_referenced_symbol(&mut self, symbol: &str) {
+        unsafe {
+            thinlto_codegen_add_cross_referenced_symbol(self.code_gen, symbol.as_ptr().cast(), symbol.len() as i32)
+        }
+    }
+
+    /// Sets the CPU to generate code for, such as `x86-64` or `apple-m1`.
+    pub fn set_cpu(&mut self, cpu: &str) {
+        let cpu = to_c_str(cpu);
+
+        unsafe { thinlto_codegen_set_cpu(self.code_gen, cpu.as_ptr()) }
+    }
+
+    /// Sets the relocation model of the generated code.
+    pub fn set_pic_model(&mut self, reloc_mode: RelocMode) {
+        unsafe {
+            thinlto_codegen_set_pic_model(self.code_gen, to_codegen_model(reloc_mode));
+        }
+    }
+
+    /// Caches the objects generated for each module in `cache_dir`, which
+    /// must already exist, so modules whose inputs haven't changed aren't
+    /// optimized again by later builds. Only modules whose summary includes a
+    /// module hash are cached, as written by `clang -flto=thin` or
+    /// `opt -module-summary -module-hash`.
+    pub fn set_cache_dir(&mut self, cache_dir: &Path) {
+        let cache_dir = to_c_str(cache_dir.to_str().expect("Did not find a valid Unicode path string"));
+
+        unsafe { thinlto_codegen_set_cache_dir(self.code_gen, cache_dir.as_ptr()) }
+    }
+
+    /// Sets the minimum number of seconds between prunings of the cache. A
+    /// negative interval disables pruning.
+    pub fn set_cache_pruning_interval(&mut self, seconds: i32) {
+        unsafe { thinlto_codegen_set_cache_pruning_interval(self.code_gen, seconds) }
+    }
+
+    /// Sets the number of seconds after which unused cache entries are pruned.
+    pub fn set_cache_entry_expiration(&mut self, seconds: u32) {
+        unsafe { thinlto_codegen_set_cache_entry_expiration(self.code_gen, seconds) }
+    }
+
+    /// Sets the percentage of the available disk space the cache may grow to
+    /// before being pruned.
+    pub fn set_final_cache_size_relative_to_available_space(&mut self, percentage: u32) {
+        unsafe { thinlto_codegen_set_final_cache_size_relative_to_available_space(self.code_gen, percentage) }
+    }
+
+    /// Optimizes and compiles every module. The objects can then be retrieved
+    /// with `get_objects`.
+    pub fn process(&mut self) {
+        unsafe { thinlto_codegen_process(self.code_gen) }
+    }
+
+    /// Gets the native objects generated by `process`, usually one per module.
+    pub fn get_objects(&self) -> Vec<MemoryBuffer> {
+        let count = unsafe { thinlto_module_get_num_objects(self.code_gen) };
+
+        (0..count.max(0) as u32)
+            .map(|index| {
+                let object: ObjectBuffer =
+                    unsafe { std::mem::transmute(thinlto_module_get_object(self.code_gen, index)) };
+                let bytes = unsafe { std::slice::from_raw_parts(object.buffer as *const u8, object.size) };
+
+                MemoryBuffer::create_from_memory_range_copy(bytes, "thinlto.o")
+            })
+            .collect()
+    }
+}
+
+impl Drop for ThinLtoCodeGenerator<'_> {
+    fn drop(&mut self) {
+        unsafe { thinlto_codegen_dispose(self.code_gen) }
+    }
+}
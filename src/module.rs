@@ -7,6 +7,11 @@ use llvm_sys::bit_writer::{LLVMWriteBitcodeToFile, LLVMWriteBitcodeToMemoryBuffe
 #[llvm_versions(4.0..=14.0)]
 use llvm_sys::core::LLVMGetTypeByName;
 
+#[llvm_versions(4.0..=13.0)]
+#[allow(deprecated)]
+use llvm_sys::core::LLVMAddAlias;
+#[llvm_versions(14.0..=latest)]
+use llvm_sys::core::LLVMAddAlias2;
 use llvm_sys::core::{
     LLVMAddFunction, LLVMAddGlobal, LLVMAddGlobalInAddressSpace, LLVMAddNamedMetadataOperand, LLVMCloneModule,
     LLVMDisposeModule, LLVMDumpModule, LLVMGetFirstFunction, LLVMGetFirstGlobal, LLVMGetLastFunction,
@@ -14,13 +19,19 @@ use llvm_sys::core::{
     LLVMGetNamedMetadataNumOperands, LLVMGetNamedMetadataOperands, LLVMGetTarget, LLVMPrintModuleToFile,
     LLVMPrintModuleToString, LLVMSetDataLayout, LLVMSetModuleIdentifier, LLVMSetTarget,
 };
+#[llvm_versions(9.0..=latest)]
+use llvm_sys::core::{LLVMAddGlobalIFunc, LLVMGetFirstGlobalIFunc, LLVMGetNamedGlobalIFunc};
+#[llvm_versions(7.0..=latest)]
+use llvm_sys::core::{LLVMAddModuleFlag, LLVMGetFirstGlobalAlias, LLVMGetModuleFlag, LLVMGetNamedGlobalAlias};
 #[llvm_versions(7.0..=latest)]
-use llvm_sys::core::{LLVMAddModuleFlag, LLVMGetModuleFlag};
+use llvm_sys::core::{LLVMGetFirstNamedMetadata, LLVMGetNamedMetadataName, LLVMGetNextNamedMetadata};
 #[llvm_versions(13.0..=latest)]
 use llvm_sys::error::LLVMGetErrorMessage;
 use llvm_sys::execution_engine::{
     LLVMCreateExecutionEngineForModule, LLVMCreateInterpreterForModule, LLVMCreateJITCompilerForModule,
 };
+#[llvm_versions(7.0..=latest)]
+use llvm_sys::prelude::LLVMNamedMDNodeRef;
 use llvm_sys::prelude::{LLVMModuleRef, LLVMValueRef};
 #[llvm_versions(13.0..=latest)]
 use llvm_sys::transforms::pass_builder::LLVMRunPasses;
@@ -37,6 +48,8 @@ use std::path::Path;
 use std::ptr;
 use std::rc::Rc;
 
+#[llvm_versions(9.0..=latest)]
+use crate::builder::Builder;
 #[llvm_versions(7.0..=latest)]
 use crate::comdat::Comdat;
 use crate::context::{AsContextRef, Context, ContextRef};
@@ -54,7 +67,11 @@ use crate::targets::{InitializationConfig, Target, TargetTriple};
 use crate::types::{AsTypeRef, BasicType, FunctionType, StructType};
 #[llvm_versions(7.0..=latest)]
 use crate::values::BasicValue;
-use crate::values::{AsValueRef, FunctionValue, GlobalValue, MetadataValue};
+use crate::values::{AsValueRef, FunctionValue, GlobalAliasValue, GlobalValue, MetadataValue};
+#[llvm_versions(9.0..=latest)]
+use crate::values::{IFuncValue, IntValue, PointerValue};
+#[llvm_versions(9.0..=latest)]
+use crate::IntPredicate;
 use crate::{AddressSpace, OptimizationLevel};
 
 #[llvm_enum(LLVMLinkage)]
@@ -1015,6 +1032,35 @@ impl<'ctx> Module<'ctx> {
         vec.iter().map(|val| unsafe { MetadataValue::new(*val) }).collect()
     }
 
+    /// An iterator over the named metadata in this `Module`, yielding each
+    /// name along with its operands as returned by `get_global_metadata`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_module");
+    /// let md_string = context.metadata_string("hello");
+    ///
+    /// module.add_global_metadata("my_md", &context.metadata_node(&[md_string.into()])).unwrap();
+    ///
+    /// let mut named_metadata = module.named_metadata();
+    /// let (name, operands) = named_metadata.next().unwrap();
+    ///
+    /// assert_eq!(name, "my_md");
+    /// assert_eq!(operands.len(), 1);
+    /// assert!(named_metadata.next().is_none());
+    /// ```
+    #[llvm_versions(7.0..=latest)]
+    pub fn named_metadata(&self) -> NamedMetadataIterator<'_, 'ctx> {
+        NamedMetadataIterator {
+            module: self,
+            node: unsafe { LLVMGetFirstNamedMetadata(self.module.get()) },
+        }
+    }
+
     /// Gets the first `GlobalValue` in a module.
     ///
     /// # Example
@@ -1105,6 +1151,340 @@ impl<'ctx> Module<'ctx> {
         GlobalIterator::from_module(self)
     }
 
+    /// Creates a `GlobalAliasValue` named `name`, which is another symbol for
+    /// the global variable, function or alias `aliasee`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::module::Linkage;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("mod");
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("my_fn_v2", fn_type, None);
+    /// let alias = module.add_alias("my_fn", fn_value.as_global_value());
+    ///
+    /// alias.as_global_value().set_linkage(Linkage::External);
+    ///
+    /// assert_eq!(alias.get_name().to_str(), Ok("my_fn"));
+    /// ```
+    #[llvm_versions(4.0..=13.0)]
+    #[allow(deprecated)]
+    pub fn add_alias(&self, name: &str, aliasee: GlobalValue<'ctx>) -> GlobalAliasValue<'ctx> {
+        use llvm_sys::core::LLVMTypeOf;
+
+        let c_string = to_c_str(name);
+
+        unsafe {
+            let value = LLVMAddAlias(
+                self.module.get(),
+                LLVMTypeOf(aliasee.as_value_ref()),
+                aliasee.as_value_ref(),
+                c_string.as_ptr(),
+            );
+
+            GlobalAliasValue::new(value)
+        }
+    }
+
+    /// Creates a `GlobalAliasValue` named `name`, which is another symbol for
+    /// the global variable, function or alias `aliasee`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::module::Linkage;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("mod");
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("my_fn_v2", fn_type, None);
+    /// let alias = module.add_alias("my_fn", fn_value.as_global_value());
+    ///
+    /// alias.as_global_value().set_linkage(Linkage::External);
+    ///
+    /// assert_eq!(module.get_alias("my_fn"), Some(alias));
+    /// assert_eq!(alias.get_aliasee(), fn_value.as_global_value().as_pointer_value());
+    /// ```
+    #[llvm_versions(14.0..=latest)]
+    pub fn add_alias(&self, name: &str, aliasee: GlobalValue<'ctx>) -> GlobalAliasValue<'ctx> {
+        use llvm_sys::core::{LLVMGetPointerAddressSpace, LLVMGlobalGetValueType, LLVMTypeOf};
+
+        let c_string = to_c_str(name);
+
+        unsafe {
+            let value = LLVMAddAlias2(
+                self.module.get(),
+                LLVMGlobalGetValueType(aliasee.as_value_ref()),
+                LLVMGetPointerAddressSpace(LLVMTypeOf(aliasee.as_value_ref())),
+                aliasee.as_value_ref(),
+                c_string.as_ptr(),
+            );
+
+            GlobalAliasValue::new(value)
+        }
+    }
+
+    /// Gets a named `GlobalAliasValue` in this `Module`.
+    #[llvm_versions(7.0..=latest)]
+    pub fn get_alias(&self, name: &str) -> Option<GlobalAliasValue<'ctx>> {
+        let value =
+            unsafe { LLVMGetNamedGlobalAlias(self.module.get(), name.as_ptr() as *const ::libc::c_char, name.len()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(GlobalAliasValue::new(value)) }
+    }
+
+    /// An iterator over the aliases in this `Module`.
+    #[llvm_versions(7.0..=latest)]
+    pub fn get_aliases(&self) -> GlobalAliasIterator<'ctx> {
+        GlobalAliasIterator::from_module(self)
+    }
+
+    /// Creates an `IFuncValue` named `name` of type `fn_type`, whose
+    /// implementation is chosen at load time by calling `resolver`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::AddressSpace;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("mod");
+    /// let builder = context.create_builder();
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_ptr_type = fn_type.ptr_type(AddressSpace::default());
+    /// let impl_fn = module.add_function("my_fn_generic", fn_type, None);
+    /// let resolver = module.add_function("my_fn_resolver", fn_ptr_type.fn_type(&[], false), None);
+    /// let entry = context.append_basic_block(resolver, "entry");
+    ///
+    /// builder.position_at_end(entry);
+    /// builder.build_return(Some(&impl_fn.as_global_value().as_pointer_value()));
+    ///
+    /// let ifunc = module.add_ifunc("my_fn", fn_type, None, resolver);
+    ///
+    /// assert_eq!(ifunc.get_resolver(), Some(resolver));
+    /// assert_eq!(module.get_ifunc("my_fn"), Some(ifunc));
+    /// ```
+    #[llvm_versions(9.0..=latest)]
+    pub fn add_ifunc(
+        &self,
+        name: &str,
+        fn_type: FunctionType<'ctx>,
+        address_space: Option<AddressSpace>,
+        resolver: FunctionValue<'ctx>,
+    ) -> IFuncValue<'ctx> {
+        let address_space = address_space.unwrap_or_default();
+
+        unsafe {
+            let value = LLVMAddGlobalIFunc(
+                self.module.get(),
+                name.as_ptr() as *const ::libc::c_char,
+                name.len(),
+                fn_type.as_type_ref(),
+                address_space.0,
+                resolver.as_value_ref(),
+            );
+
+            IFuncValue::new(value)
+        }
+    }
+
+    /// Gets a named `IFuncValue` in this `Module`.
+    #[llvm_versions(9.0..=latest)]
+    pub fn get_ifunc(&self, name: &str) -> Option<IFuncValue<'ctx>> {
+        let value =
+            unsafe { LLVMGetNamedGlobalIFunc(self.module.get(), name.as_ptr() as *const ::libc::c_char, name.len()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(IFuncValue::new(value)) }
+    }
+
+    /// An iterator over the ifuncs in this `Module`.
+    #[llvm_versions(9.0..=latest)]
+    pub fn get_ifuncs(&self) -> IFuncIterator<'ctx> {
+        IFuncIterator::from_module(self)
+    }
+
+    /// Compiles `function` once per `(target_cpu, target_features)` variant and
+    /// dispatches to the best one at load time through an `IFuncValue`, which
+    /// takes over the function's name, linkage and uses.
+    ///
+    /// Each variant is a clone of `function` with `"target-cpu"` and
+    /// `"target-features"` function attributes, such as
+    /// `("skylake-avx512", "+avx512f,+avx512vl")`. The resolver checks them in
+    /// order and picks the first whose features the running CPU supports,
+    /// falling back to the original function, which is renamed with a
+    /// `.default` suffix. `TargetMachine::get_host_cpu_name` and
+    /// `TargetMachine::get_host_cpu_features` can provide a variant tuned for
+    /// the host.
+    ///
+    /// The resolver uses the x86 CPU detection of compiler-rt and libgcc
+    /// (`__cpu_indicator_init` and `__cpu_model`), so this is only supported on
+    /// x86 targets. The enabled features must be ones those libraries can
+    /// detect, like `+avx2` or `+avx512f`; disabled (`-`) features and the
+    /// target CPU are not checked. Returns an error if this module's triple is
+    /// not an x86 one, a variant enables a feature which can't be detected or
+    /// none at all, or `function` does not have a body in this module.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::targets::TargetTriple;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("mod");
+    /// let builder = context.create_builder();
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let kernel = module.add_function("kernel", fn_type, None);
+    ///
+    /// module.set_triple(&TargetTriple::create("x86_64-pc-linux-gnu"));
+    /// let entry = context.append_basic_block(kernel, "entry");
+    ///
+    /// builder.position_at_end(entry);
+    /// builder.build_return(None);
+    ///
+    /// let variants = [("haswell", "+avx2,+fma"), ("skylake-avx512", "+avx512f,+avx512vl")];
+    /// let ifunc = module.multiversion_function(kernel, &variants).unwrap();
+    ///
+    /// assert_eq!(ifunc.get_name().to_str(), Ok("kernel"));
+    /// assert!(module.get_function("kernel.haswell").is_some());
+    /// assert!(module.get_function("kernel.default").is_some());
+    /// ```
+    #[llvm_versions(9.0..=latest)]
+    pub fn multiversion_function(
+        &self,
+        function: FunctionValue<'ctx>,
+        variants: &[(&str, &str)],
+    ) -> Result<IFuncValue<'ctx>, &'static str> {
+        use llvm_sys::core::LLVMGetGlobalParent;
+
+        use crate::attributes::AttributeLoc;
+
+        if unsafe { LLVMGetGlobalParent(function.as_value_ref()) } != self.module.get() {
+            return Err("Function does not belong to this module.");
+        }
+
+        if function.count_basic_blocks() == 0 {
+            return Err("Function must have a body to be multiversioned.");
+        }
+
+        if !is_x86_triple(&self.get_triple().as_str().to_string_lossy()) {
+            return Err("Multiversioning is only supported for x86 targets.");
+        }
+
+        let feature_masks = variants
+            .iter()
+            .map(|&(_, features)| x86_cpu_feature_masks(features))
+            .collect::<Result<Vec<_>, _>>()?;
+        let name = function.get_name().to_string_lossy().into_owned();
+        let linkage = function.get_linkage();
+        let context = self.get_context();
+        let fn_type = function.get_type();
+
+        let mut versions = Vec::with_capacity(variants.len());
+
+        for &(cpu, _) in variants {
+            match function.clone_into(self, &format!("{}.{}", name, cpu)) {
+                Ok(version) => versions.push(version),
+                Err(err) => {
+                    for version in versions {
+                        unsafe { version.delete() }
+                    }
+
+                    return Err(err);
+                },
+            }
+        }
+
+        for (version, &(cpu, features)) in versions.iter().zip(variants) {
+            version.add_attribute(
+                AttributeLoc::Function,
+                context.create_string_attribute("target-cpu", cpu),
+            );
+            version.add_attribute(
+                AttributeLoc::Function,
+                context.create_string_attribute("target-features", features),
+            );
+            version.set_linkage(Linkage::Internal);
+        }
+
+        function.as_global_value().set_name(&format!("{}.default", name));
+
+        let fn_ptr_type = fn_type.ptr_type(AddressSpace::default());
+        let resolver = self.add_function(&format!("{}.resolver", name), fn_ptr_type.fn_type(&[], false), None);
+
+        resolver.set_linkage(Linkage::Internal);
+
+        let ifunc = self.add_ifunc(&name, fn_type, None, resolver);
+
+        ifunc.as_global_value().set_linkage(linkage);
+        function
+            .as_global_value()
+            .as_pointer_value()
+            .replace_all_uses_with(ifunc.as_pointer_value());
+        function.set_linkage(Linkage::Internal);
+
+        // Mirrors how clang lowers __builtin_cpu_supports
+        let i32_type = context.i32_type();
+        let cpu_model_type = context.struct_type(&[i32_type.into(); 4], false);
+        let cpu_features2_type = context.struct_type(&[i32_type.into()], false);
+        let cpu_model = self
+            .get_global("__cpu_model")
+            .unwrap_or_else(|| self.add_global(cpu_model_type, None, "__cpu_model"));
+        let cpu_features2 = self
+            .get_global("__cpu_features2")
+            .unwrap_or_else(|| self.add_global(cpu_features2_type, None, "__cpu_features2"));
+        let cpu_indicator_init = self.get_function("__cpu_indicator_init").unwrap_or_else(|| {
+            self.add_function("__cpu_indicator_init", context.void_type().fn_type(&[], false), None)
+        });
+
+        let builder = context.create_builder();
+        let entry = context.append_basic_block(resolver, "entry");
+
+        builder.position_at_end(entry);
+        builder.build_call(cpu_indicator_init, &[], "");
+
+        let features = build_load_i32_field(&builder, cpu_model_type, cpu_model.as_pointer_value(), 3);
+        let features2 = build_load_i32_field(&builder, cpu_features2_type, cpu_features2.as_pointer_value(), 0);
+
+        for (version, (mask, mask2)) in versions.into_iter().zip(feature_masks) {
+            let supported_block = context.append_basic_block(resolver, "supported");
+            let next_block = context.append_basic_block(resolver, "next");
+            let mut is_supported = context.bool_type().const_all_ones();
+
+            for (features, mask) in [(features, mask), (features2, mask2)] {
+                if mask != 0 {
+                    let mask = i32_type.const_int(mask as u64, false);
+                    let masked = builder.build_and(features, mask, "masked");
+                    let has_features = builder.build_int_compare(IntPredicate::EQ, masked, mask, "has_features");
+
+                    is_supported = builder.build_and(is_supported, has_features, "is_supported");
+                }
+            }
+
+            builder.build_conditional_branch(is_supported, supported_block, next_block);
+            builder.position_at_end(supported_block);
+            builder.build_return(Some(&version.as_global_value().as_pointer_value()));
+            builder.position_at_end(next_block);
+        }
+
+        builder.build_return(Some(&function.as_global_value().as_pointer_value()));
+
+        Ok(ifunc)
+    }
+
     /// Creates a new `Module` from a `MemoryBuffer` with bitcode.
     ///
     /// # Example
@@ -1486,6 +1866,17 @@ impl<'ctx> Module<'ctx> {
         passes: &str,
         machine: &TargetMachine,
         options: PassBuilderOptions,
+    ) -> Result<(), LLVMString> {
+        self.run_passes_with_options(passes, machine, &options)
+    }
+
+    // Like `run_passes`, but leaves the options usable for further runs
+    #[llvm_versions(13.0..=latest)]
+    pub(crate) fn run_passes_with_options(
+        &self,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
     ) -> Result<(), LLVMString> {
         unsafe {
             let error = LLVMRunPasses(
@@ -1662,3 +2053,206 @@ impl<'ctx> Iterator for GlobalIterator<'ctx> {
         }
     }
 }
+
+/// Iterate over all `GlobalAliasValue`s in an llvm module
+#[llvm_versions(7.0..=latest)]
+#[derive(Debug)]
+pub struct GlobalAliasIterator<'ctx>(Option<GlobalAliasValue<'ctx>>);
+
+#[llvm_versions(7.0..=latest)]
+impl<'ctx> GlobalAliasIterator<'ctx> {
+    fn from_module(module: &Module<'ctx>) -> Self {
+        let value = unsafe { LLVMGetFirstGlobalAlias(module.module.get()) };
+
+        if value.is_null() {
+            return Self(None);
+        }
+
+        unsafe { Self(Some(GlobalAliasValue::new(value))) }
+    }
+}
+
+#[llvm_versions(7.0..=latest)]
+impl<'ctx> Iterator for GlobalAliasIterator<'ctx> {
+    type Item = GlobalAliasValue<'ctx>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.0?;
+
+        self.0 = current.get_next_alias();
+
+        Some(current)
+    }
+}
+
+/// Iterate over all named metadata in an llvm module
+#[llvm_versions(7.0..=latest)]
+#[derive(Debug)]
+pub struct NamedMetadataIterator<'a, 'ctx> {
+    module: &'a Module<'ctx>,
+    node: LLVMNamedMDNodeRef,
+}
+
+#[llvm_versions(7.0..=latest)]
+impl<'ctx> Iterator for NamedMetadataIterator<'_, 'ctx> {
+    type Item = (String, Vec<MetadataValue<'ctx>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.node.is_null() {
+            return None;
+        }
+
+        let name = unsafe {
+            let mut len = 0;
+            let ptr = LLVMGetNamedMetadataName(self.node, &mut len);
+
+            String::from_utf8_lossy(std::slice::from_raw_parts(ptr as *const u8, len)).into_owned()
+        };
+
+        self.node = unsafe { LLVMGetNextNamedMetadata(self.node) };
+
+        let operands = self.module.get_global_metadata(&name);
+
+        Some((name, operands))
+    }
+}
+
+/// Iterate over all `IFuncValue`s in an llvm module
+#[llvm_versions(9.0..=latest)]
+#[derive(Debug)]
+pub struct IFuncIterator<'ctx>(Option<IFuncValue<'ctx>>);
+
+#[llvm_versions(9.0..=latest)]
+impl<'ctx> IFuncIterator<'ctx> {
+    fn from_module(module: &Module<'ctx>) -> Self {
+        let value = unsafe { LLVMGetFirstGlobalIFunc(module.module.get()) };
+
+        if value.is_null() {
+            return Self(None);
+        }
+
+        unsafe { Self(Some(IFuncValue::new(value))) }
+    }
+}
+
+#[llvm_versions(9.0..=latest)]
+impl<'ctx> Iterator for IFuncIterator<'ctx> {
+    type Item = IFuncValue<'ctx>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.0?;
+
+        self.0 = current.get_next_ifunc();
+
+        Some(current)
+    }
+}
+
+// Bit positions in `__cpu_model.__cpu_features[0]` and, from 32 on, in
+// `__cpu_features2`, as defined by LLVM's X86TargetParser.def.
+#[llvm_versions(9.0..=latest)]
+const X86_CPU_FEATURE_BITS: &[(&str, u32)] = &[
+    ("cmov", 0),
+    ("mmx", 1),
+    ("popcnt", 2),
+    ("sse", 3),
+    ("sse2", 4),
+    ("sse3", 5),
+    ("ssse3", 6),
+    ("sse4.1", 7),
+    ("sse4.2", 8),
+    ("avx", 9),
+    ("avx2", 10),
+    ("sse4a", 11),
+    ("fma4", 12),
+    ("xop", 13),
+    ("fma", 14),
+    ("avx512f", 15),
+    ("bmi", 16),
+    ("bmi2", 17),
+    ("aes", 18),
+    ("pclmul", 19),
+    ("avx512vl", 20),
+    ("avx512bw", 21),
+    ("avx512dq", 22),
+    ("avx512cd", 23),
+    ("avx512er", 24),
+    ("avx512pf", 25),
+    ("avx512vbmi", 26),
+    ("avx512ifma", 27),
+    ("avx5124vnniw", 28),
+    ("avx5124fmaps", 29),
+    ("avx512vpopcntdq", 30),
+    ("avx512vbmi2", 31),
+    ("gfni", 32),
+    ("vpclmulqdq", 33),
+    ("avx512vnni", 34),
+    ("avx512bitalg", 35),
+    ("avx512bf16", 36),
+    ("avx512vp2intersect", 37),
+];
+
+// Whether the architecture of `triple` is 32 or 64 bit x86, spelled any way
+// LLVM accepts.
+#[llvm_versions(9.0..=latest)]
+fn is_x86_triple(triple: &str) -> bool {
+    let arch = triple.split('-').next().unwrap_or_default();
+
+    arch.starts_with("x86_64")
+        || arch == "amd64"
+        || arch == "x86"
+        || (arch.len() == 4 && arch.starts_with('i') && arch.ends_with("86"))
+}
+
+// Gets the `__cpu_features` and `__cpu_features2` bits required by the enabled
+// ("+feature") features, which must all be detectable at runtime.
+#[llvm_versions(9.0..=latest)]
+fn x86_cpu_feature_masks(features: &str) -> Result<(u32, u32), &'static str> {
+    let mut masks = (0, 0);
+
+    for feature in features
+        .split(',')
+        .filter_map(|feature| feature.trim().strip_prefix('+'))
+    {
+        match X86_CPU_FEATURE_BITS.iter().find(|&&(name, _)| name == feature) {
+            Some(&(_, bit)) if bit < 32 => masks.0 |= 1 << bit,
+            Some(&(_, bit)) => masks.1 |= 1 << (bit - 32),
+            None => return Err("Variant enables a target feature which can't be detected at runtime."),
+        }
+    }
+
+    if masks == (0, 0) {
+        return Err("Variant has no target features which can be detected at runtime.");
+    }
+
+    Ok(masks)
+}
+
+#[llvm_versions(9.0..=14.0)]
+fn build_load_i32_field<'ctx>(
+    builder: &Builder<'ctx>,
+    _struct_type: StructType<'ctx>,
+    ptr: PointerValue<'ctx>,
+    index: u32,
+) -> IntValue<'ctx> {
+    let field = builder
+        .build_struct_gep(ptr, index, "field")
+        .expect("Pointer should be to a struct");
+
+    builder.build_load(field, "features").into_int_value()
+}
+
+#[llvm_versions(15.0..=latest)]
+fn build_load_i32_field<'ctx>(
+    builder: &Builder<'ctx>,
+    struct_type: StructType<'ctx>,
+    ptr: PointerValue<'ctx>,
+    index: u32,
+) -> IntValue<'ctx> {
+    let i32_type = struct_type.get_context().i32_type();
+    let field = builder
+        .build_struct_gep(struct_type, ptr, index, "field")
+        .expect("Pointer should be to a struct");
+
+    builder.build_load(i32_type, field, "features").into_int_value()
+}
@@ -0,0 +1,714 @@
+//! Instrumentation based profile guided optimization.
+//!
+//! A module is first instrumented with counters on its edges by
+//! `PgoInstrumentation`, then run on representative inputs. The counters are
+//! either written to a `.profraw` file by the profile runtime of an ahead of
+//! time compiled binary and read with `read_raw_profile`, or read back from an
+//! `ExecutionEngine` with `read_counters`. The resulting `InstrProfile`s are
+//! merged, written in the indexed format by `write_indexed`, given to
+//! `set_profile_use_file` and applied to the uninstrumented module by the
+//! `pgo-instr-use` pass.
+//!
+//! Raw profiles are only read from 64 bit little endian targets, in the
+//! version written by the profile runtime of the LLVM inkwell is built
+//! against. `InstrProfile` also reads and writes LLVM's text format, as used
+//! by `llvm-profdata merge --text`.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::execution_engine::JitFunction;
+//! use inkwell::passes::PassBuilderOptions;
+//! use inkwell::pgo::{set_profile_use_file, PgoInstrumentation};
+//! use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+//! use inkwell::OptimizationLevel;
+//!
+//! Target::initialize_native(&InitializationConfig::default()).unwrap();
+//!
+//! let triple = TargetMachine::get_default_triple();
+//! let machine = Target::from_triple(&triple)
+//!     .unwrap()
+//!     .create_target_machine(&triple, "generic", "", OptimizationLevel::Default, RelocMode::Default, CodeModel::JITDefault)
+//!     .unwrap();
+//! let context = Context::create();
+//! let module = context.create_module("my_module");
+//! let fn_type = context.i64_type().fn_type(&[], false);
+//! let function = module.add_function("run", fn_type, None);
+//! let builder = context.create_builder();
+//!
+//! module.set_triple(&triple);
+//! builder.position_at_end(context.append_basic_block(function, "entry"));
+//! builder.build_return(Some(&context.i64_type().const_zero()));
+//!
+//! let profiled_module = module.clone();
+//! let instrumentation =
+//!     PgoInstrumentation::instrument_for_jit(&profiled_module, &machine, &PassBuilderOptions::create()).unwrap();
+//! let execution_engine = profiled_module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+//!
+//! unsafe {
+//!     let run: JitFunction<unsafe extern "C" fn() -> u64> = execution_engine.get_function("run").unwrap();
+//!
+//!     run.call();
+//! }
+//!
+//! let profile = instrumentation.read_counters(&execution_engine).unwrap();
+//!
+//! std::fs::write("run.profdata", profile.write_indexed()).unwrap();
+//! set_profile_use_file("run.profdata").unwrap();
+//! module.run_passes("pgo-instr-use", &machine, PassBuilderOptions::create()).unwrap();
+//! ```
+
+use llvm_sys::core::{
+    LLVMConstIntGetZExtValue, LLVMGetAsString, LLVMGetCalledValue, LLVMGetInitializer, LLVMGetOperand,
+    LLVMGetValueName2, LLVMIsACallInst, LLVMIsAConstantExpr,
+};
+use llvm_sys::execution_engine::LLVMGetGlobalValueAddress;
+use llvm_sys::prelude::LLVMValueRef;
+#[cfg(any(feature = "llvm13-0", feature = "llvm14-0"))]
+use once_cell::sync::Lazy;
+#[cfg(any(feature = "llvm13-0", feature = "llvm14-0"))]
+use parking_lot::Mutex;
+
+use crate::coverage::md5_hash;
+use crate::execution_engine::ExecutionEngine;
+use crate::module::{Linkage, Module};
+use crate::passes::PassBuilderOptions;
+use crate::support::{parse_command_line_options, to_c_str, LLVMString};
+use crate::targets::TargetMachine;
+use crate::values::AsValueRef;
+
+use std::fmt::{self, Display, Formatter};
+
+// "\xfflprofr\x81", the magic number of raw profiles of 64 bit targets
+const RAW_MAGIC: u64 = 0xff6c_7072_6f66_7281;
+#[cfg(feature = "llvm13-0")]
+const RAW_VERSION: u64 = 7;
+#[cfg(not(feature = "llvm13-0"))]
+const RAW_VERSION: u64 = 8;
+const RAW_HEADER_SIZE: usize = 11 * 8;
+const RAW_DATA_SIZE: usize = 48;
+// "\xfflprofi\x81", the magic number of indexed profiles
+const INDEXED_MAGIC: u64 = 0x8169_666f_7270_6cff;
+// Every version of LLVM this module supports reads version 7, which needs no
+// memory profile section
+const INDEXED_VERSION: u64 = 7;
+// The top byte of a profile's version holds flags, such as whether it is IR
+// level
+const VARIANT_MASKS_ALL: u64 = 0xff << 56;
+const VARIANT_MASK_IR_PROF: u64 = 1 << 56;
+// The cutoffs LLVM summarizes indexed profiles by, in millionths of the
+// total count
+const SUMMARY_CUTOFFS: [u64; 16] = [
+    10000, 100000, 200000, 300000, 400000, 500000, 600000, 700000, 800000, 900000, 950000, 990000, 999000, 999900,
+    999990, 999999,
+];
+
+#[cfg(any(feature = "llvm13-0", feature = "llvm14-0"))]
+static PROFILE_USE_FILE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Sets the profile the `pgo-instr-use` pass reads, which must be in LLVM's
+/// indexed format, as written by `InstrProfile::write_indexed` or
+/// `llvm-profdata merge`.
+///
+/// LLVM only reads this path from `-pgo-test-profile-file`, a process wide
+/// command line option meant for testing, so it applies to every pipeline
+/// run afterwards, in any context. Before LLVM 15, LLVM ignores the option
+/// after it was first set, so this returns an error if it was already set to
+/// another path.
+pub fn set_profile_use_file(path: &str) -> Result<(), LLVMString> {
+    #[cfg(any(feature = "llvm13-0", feature = "llvm14-0"))]
+    {
+        let mut profile_use_file = PROFILE_USE_FILE.lock();
+
+        match profile_use_file.as_deref() {
+            Some(previous) if previous == path => return Ok(()),
+            Some(_) => {
+                return Err(LLVMString::create_from_c_str(&to_c_str(
+                    "The profile to use can only be set once per process before LLVM 15.",
+                )))
+            },
+            None => *profile_use_file = Some(path.to_string()),
+        }
+    }
+
+    parse_command_line_options(&[format!("-pgo-test-profile-file={}", path)]);
+
+    Ok(())
+}
+
+/// The counters of one function in an `InstrProfile`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FunctionProfile {
+    /// The function's name in the profile. Functions with internal linkage
+    /// are prefixed with the name of their source file.
+    pub name: String,
+    /// A hash of the function's control flow graph when it was instrumented,
+    /// so stale profiles aren't applied to changed functions.
+    pub hash: u64,
+    /// How often each instrumented edge was taken.
+    pub counts: Vec<u64>,
+}
+
+/// An instrumentation profile, in the form of LLVM's text profile format.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct InstrProfile {
+    /// Whether the counters were inserted by `pgo-instr-gen`, rather than by
+    /// a frontend such as for code coverage.
+    pub ir_level: bool,
+    /// The profiled functions.
+    pub functions: Vec<FunctionProfile>,
+}
+
+impl InstrProfile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        InstrProfile::default()
+    }
+
+    /// Parses a profile in LLVM's text format, as written by this type's
+    /// `Display` implementation or `llvm-profdata merge --text`. Profiles with
+    /// value profiles or context sensitive counters are not supported.
+    pub fn parse_text(text: &str) -> Result<Self, &'static str> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+        let mut profile = InstrProfile::new();
+
+        while let Some(flag) = lines.next_if(|line| line.starts_with(':')) {
+            match flag {
+                ":ir" => profile.ir_level = true,
+                ":fe" => profile.ir_level = false,
+                _ => return Err("Unsupported profile kind."),
+            }
+        }
+
+        while let Some(name) = lines.next() {
+            let hash = next_number(&mut lines)?;
+            let num_counters = next_number(&mut lines)?;
+            let counts = (0..num_counters)
+                .map(|_| next_number(&mut lines))
+                .collect::<Result<_, _>>()?;
+
+            if lines.peek().and_then(|line| line.parse::<u64>().ok()).is_some() {
+                return Err("Value profiles are not supported.");
+            }
+
+            profile.functions.push(FunctionProfile {
+                name: name.to_string(),
+                hash,
+                counts,
+            });
+        }
+
+        Ok(profile)
+    }
+
+    /// Parses a raw profile, as written to a `.profraw` file by the profile
+    /// runtime. Raw profiles identify functions by a hash of their name, so
+    /// only the functions named in `names` are read and any others are
+    /// skipped. Value profiles are ignored.
+    ///
+    /// Only profiles of 64 bit little endian targets, written by the profile
+    /// runtime of the LLVM version inkwell is built against, are supported.
+    pub fn parse_raw(data: &[u8], names: &[&str]) -> Result<Self, &'static str> {
+        let read_u64 = |offset: usize| {
+            data.get(offset..offset.saturating_add(8))
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or("Unexpected end of profile.")
+        };
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset.saturating_add(4))
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or("Unexpected end of profile.")
+        };
+
+        if read_u64(0)? != RAW_MAGIC {
+            return Err("Not a raw profile of a 64 bit little endian target.");
+        }
+
+        let version = read_u64(8)?;
+
+        if version & !VARIANT_MASKS_ALL != RAW_VERSION {
+            return Err("Unsupported raw profile version.");
+        }
+
+        let binary_ids_size = read_u64(16)? as usize;
+        let num_records = read_u64(24)? as usize;
+        let padding_before_counters = read_u64(32)? as usize;
+        let num_counters = read_u64(40)?;
+        let mut counters_delta = read_u64(64)?;
+        let records_offset = RAW_HEADER_SIZE.saturating_add(binary_ids_size);
+        let counters_offset = num_records
+            .saturating_mul(RAW_DATA_SIZE)
+            .saturating_add(records_offset)
+            .saturating_add(padding_before_counters);
+        let hashes: Vec<_> = names.iter().map(|name| (md5_hash(name.as_bytes()), *name)).collect();
+        let mut profile = InstrProfile {
+            ir_level: version & VARIANT_MASK_IR_PROF != 0,
+            functions: Vec::new(),
+        };
+
+        for index in 0..num_records {
+            let record = records_offset.saturating_add(index * RAW_DATA_SIZE);
+            let name_ref = read_u64(record)?;
+            let hash = read_u64(record + 8)?;
+            // Counters are addressed relative to their function's record
+            let counters = read_u64(record + 16)?.wrapping_sub(counters_delta);
+            let function_counters = read_u32(record + 40)? as u64;
+
+            counters_delta = counters_delta.wrapping_sub(RAW_DATA_SIZE as u64);
+
+            if counters % 8 != 0 || counters / 8 + function_counters > num_counters {
+                return Err("Counters are out of bounds.");
+            }
+
+            let name = match hashes.iter().find(|(name_hash, _)| *name_hash == name_ref) {
+                Some((_, name)) => name,
+                None => continue,
+            };
+            let counts = (0..function_counters)
+                .map(|counter| read_u64(counters_offset.saturating_add((counters + counter * 8) as usize)))
+                .collect::<Result<_, _>>()?;
+
+            profile.functions.push(FunctionProfile {
+                name: name.to_string(),
+                hash,
+                counts,
+            });
+        }
+
+        Ok(profile)
+    }
+
+    /// Gets the profile of a function by its name in the profile.
+    pub fn get_function(&self, name: &str) -> Option<&FunctionProfile> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Adds the counts of another profile, such as one from another run, to
+    /// this one. Functions are matched by both name and hash, so profiles of
+    /// different versions of a function are kept separate.
+    pub fn merge(&mut self, other: &InstrProfile) -> Result<(), &'static str> {
+        if self.ir_level != other.ir_level {
+            return Err("IR level and frontend profiles cannot be merged.");
+        }
+
+        for other_function in &other.functions {
+            let function = self
+                .functions
+                .iter_mut()
+                .find(|function| function.name == other_function.name && function.hash == other_function.hash);
+
+            match function {
+                Some(function) => {
+                    if function.counts.len() != other_function.counts.len() {
+                        return Err("Function profiles with the same hash have different numbers of counters.");
+                    }
+
+                    for (count, other_count) in function.counts.iter_mut().zip(&other_function.counts) {
+                        *count = count.saturating_add(*other_count);
+                    }
+                },
+                None => self.functions.push(other_function.clone()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the profile in LLVM's indexed format, as read by the
+    /// `pgo-instr-use` pass and written by `llvm-profdata merge`.
+    pub fn write_indexed(&self) -> Vec<u8> {
+        // Functions are looked up by the hash of their name, which may be
+        // shared by several versions of a function with different hashes
+        let mut entries: Vec<(&str, Vec<&FunctionProfile>)> = Vec::new();
+
+        for function in &self.functions {
+            match entries.iter_mut().find(|(name, _)| *name == function.name) {
+                Some((_, versions)) => versions.push(function),
+                None => entries.push((&function.name, vec![function])),
+            }
+        }
+
+        let mut bytes = Vec::new();
+        let version = if self.ir_level {
+            INDEXED_VERSION | VARIANT_MASK_IR_PROF
+        } else {
+            INDEXED_VERSION
+        };
+
+        // The hash type is MD5, and the offset of the hash table is patched
+        // in once it is written
+        for value in [INDEXED_MAGIC, version, 0, 0, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        self.write_summary(&mut bytes);
+
+        let num_buckets = if entries.len() <= 2 {
+            1
+        } else {
+            (entries.len() * 4 / 3 + 1).next_power_of_two()
+        };
+        let mut buckets = vec![Vec::new(); num_buckets];
+
+        for (name, versions) in &entries {
+            let hash = md5_hash(name.as_bytes());
+
+            buckets[hash as usize & (num_buckets - 1)].push((hash, name, versions));
+        }
+
+        let mut bucket_offsets = Vec::new();
+
+        for bucket in &buckets {
+            if bucket.is_empty() {
+                bucket_offsets.push(0);
+                continue;
+            }
+
+            bucket_offsets.push(bytes.len() as u64);
+            bytes.extend_from_slice(&(bucket.len() as u16).to_le_bytes());
+
+            for (hash, name, versions) in bucket {
+                // Each version has its hash, number of counters, counters and
+                // an empty value profile
+                let data_size: usize = versions.iter().map(|function| 24 + function.counts.len() * 8).sum();
+
+                bytes.extend_from_slice(&hash.to_le_bytes());
+                bytes.extend_from_slice(&(name.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(&(data_size as u64).to_le_bytes());
+                bytes.extend_from_slice(name.as_bytes());
+
+                for function in versions.iter() {
+                    bytes.extend_from_slice(&function.hash.to_le_bytes());
+                    bytes.extend_from_slice(&(function.counts.len() as u64).to_le_bytes());
+
+                    for count in &function.counts {
+                        bytes.extend_from_slice(&count.to_le_bytes());
+                    }
+
+                    // The value profile's size and number of value kinds
+                    bytes.extend_from_slice(&8u32.to_le_bytes());
+                    bytes.extend_from_slice(&0u32.to_le_bytes());
+                }
+            }
+        }
+
+        while bytes.len() % 8 != 0 {
+            bytes.push(0);
+        }
+
+        let hash_table_offset = bytes.len() as u64;
+
+        bytes[32..40].copy_from_slice(&hash_table_offset.to_le_bytes());
+        bytes.extend_from_slice(&(num_buckets as u64).to_le_bytes());
+        bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+
+        for offset in bucket_offsets {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    // Writes the summary which LLVM finds hot and cold code with, computed
+    // the same way as llvm-profdata. The first counter of each function is
+    // counted as its entry
+    fn write_summary(&self, bytes: &mut Vec<u8>) {
+        let mut counts: Vec<u64> = Vec::new();
+        let mut num_functions = 0;
+        let mut max_function_count = 0;
+        let mut max_internal_count = 0;
+
+        for function in &self.functions {
+            if let Some((entry_count, internal_counts)) = function.counts.split_first() {
+                num_functions += 1;
+                max_function_count = max_function_count.max(*entry_count);
+                max_internal_count = internal_counts.iter().copied().fold(max_internal_count, u64::max);
+                counts.extend_from_slice(&function.counts);
+            }
+        }
+
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+
+        let total_count = counts.iter().fold(0u64, |total, count| total.wrapping_add(*count));
+        let max_count = counts.first().copied().unwrap_or(0);
+        let fields = [
+            num_functions,
+            counts.len() as u64,
+            max_function_count,
+            max_count,
+            max_internal_count,
+            total_count,
+        ];
+
+        bytes.extend_from_slice(&(fields.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(SUMMARY_CUTOFFS.len() as u64).to_le_bytes());
+
+        for field in fields {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+
+        // Each cutoff records the smallest count, and how many counts are at
+        // least as large, needed to make up that share of the total. Equal
+        // counts are taken together
+        let mut sum = 0u128;
+        let mut min_count = 0;
+        let mut num_counts = 0;
+
+        for cutoff in SUMMARY_CUTOFFS {
+            let desired_sum = total_count as u128 * cutoff as u128 / 1_000_000;
+
+            while sum < desired_sum && num_counts < counts.len() {
+                min_count = counts[num_counts];
+
+                while num_counts < counts.len() && counts[num_counts] == min_count {
+                    sum += min_count as u128;
+                    num_counts += 1;
+                }
+            }
+
+            for value in [cutoff, min_count, num_counts as u64] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Writes the profile in LLVM's text format.
+impl Display for InstrProfile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.ir_level {
+            writeln!(f, "# IR level Instrumentation Flag")?;
+            writeln!(f, ":ir")?;
+        }
+
+        for function in &self.functions {
+            writeln!(f, "{}", function.name)?;
+            writeln!(f, "# Func Hash:")?;
+            writeln!(f, "{}", function.hash)?;
+            writeln!(f, "# Num Counters:")?;
+            writeln!(f, "{}", function.counts.len())?;
+            writeln!(f, "# Counter Values:")?;
+
+            for count in &function.counts {
+                writeln!(f, "{}", count)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct InstrumentedFunction {
+    name: String,
+    counters: String,
+    hash: u64,
+    num_counters: u64,
+}
+
+/// The functions of a module instrumented by the `pgo-instr-gen` and
+/// `instrprof` passes, which is needed to read their counters back.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgoInstrumentation {
+    functions: Vec<InstrumentedFunction>,
+}
+
+impl PgoInstrumentation {
+    /// Instruments a module to be compiled ahead of time and linked against
+    /// the profile runtime, `libclang_rt.profile`. The runtime writes the
+    /// counters to `profile_file` on exit, or to `default.profraw` if none is
+    /// given.
+    pub fn instrument(
+        module: &Module,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+        profile_file: Option<&str>,
+    ) -> Result<Self, LLVMString> {
+        if let Some(profile_file) = profile_file {
+            let context = module.get_context();
+            let name = context.const_string(profile_file.as_bytes(), true);
+            let global = module.add_global(name.get_type(), None, "__llvm_profile_filename");
+
+            global.set_constant(true);
+            global.set_linkage(Linkage::WeakAny);
+            global.set_initializer(&name);
+        }
+
+        PgoInstrumentation::run(module, machine, options)
+    }
+
+    /// Instruments a module to be run without the profile runtime, such as
+    /// through an `ExecutionEngine`. Its counters are then read with
+    /// `read_counters`.
+    ///
+    /// The module's triple must be set, as the counters of modules for an
+    /// unknown operating system are registered with the runtime instead.
+    /// Value profiling of indirect calls and memory intrinsics also calls into
+    /// the runtime, so `__llvm_profile_instrument_target` and
+    /// `__llvm_profile_instrument_memop` need to be mapped if the module uses
+    /// either.
+    pub fn instrument_for_jit(
+        module: &Module,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+    ) -> Result<Self, LLVMString> {
+        define_profile_runtime(module);
+
+        let instrumentation = PgoInstrumentation::run(module, machine, options)?;
+
+        expose_counters(
+            module,
+            instrumentation.functions.iter().map(|function| &function.counters),
+        );
+
+        Ok(instrumentation)
+    }
+
+    fn run(module: &Module, machine: &TargetMachine, options: &PassBuilderOptions) -> Result<Self, LLVMString> {
+        module.run_passes_with_options("pgo-instr-gen", machine, options)?;
+
+        // The names and hashes are only easy to find before the increments
+        // are lowered
+        let mut instrumentation = PgoInstrumentation::default();
+
+        for function in module.get_functions() {
+            for basic_block in function.get_basic_blocks() {
+                let mut next_instruction = basic_block.get_first_instruction();
+
+                while let Some(instruction) = next_instruction {
+                    next_instruction = instruction.get_next_instruction();
+
+                    if let Some(function) = unsafe { get_instrumented_function(instruction.as_value_ref()) } {
+                        if !instrumentation.functions.contains(&function) {
+                            instrumentation.functions.push(function);
+                        }
+                    }
+                }
+            }
+        }
+
+        module.run_passes_with_options("instrprof", machine, options)?;
+
+        Ok(instrumentation)
+    }
+
+    /// Reads the counters of a module instrumented by `instrument` from a raw
+    /// profile written by the profile runtime. Functions from other modules
+    /// are skipped, as described by `InstrProfile::parse_raw`.
+    pub fn read_raw_profile(&self, data: &[u8]) -> Result<InstrProfile, &'static str> {
+        let names: Vec<_> = self.functions.iter().map(|function| function.name.as_str()).collect();
+
+        InstrProfile::parse_raw(data, &names)
+    }
+
+    /// Reads the counters of a module instrumented by `instrument_for_jit`
+    /// from the `ExecutionEngine` it was run in.
+    pub fn read_counters(&self, execution_engine: &ExecutionEngine) -> Result<InstrProfile, &'static str> {
+        let mut profile = InstrProfile {
+            ir_level: true,
+            functions: Vec::new(),
+        };
+
+        for function in &self.functions {
+            let counts = read_jit_counters(execution_engine, &function.counters, function.num_counters as usize)
+                .ok_or("Could not find the counters of an instrumented function.")?;
+
+            profile.functions.push(FunctionProfile {
+                name: function.name.clone(),
+                hash: function.hash,
+                counts,
+            });
+        }
+
+        Ok(profile)
+    }
+}
+
+// The instrprof pass won't reference the profile runtime if the module
+// already defines this, as long as it isn't internal
+pub(crate) fn define_profile_runtime(module: &Module) {
+    if module.get_global("__llvm_profile_runtime").is_none() {
+        let i32_type = module.get_context().i32_type();
+        let global = module.add_global(i32_type, None, "__llvm_profile_runtime");
+
+        global.set_initializer(&i32_type.const_zero());
+    }
+}
+
+// The instrprof pass makes counters private, so they need to be visible to be
+// found in an `ExecutionEngine`
+pub(crate) fn expose_counters<'a>(module: &Module, counters: impl IntoIterator<Item = &'a String>) {
+    for counters in counters {
+        if let Some(counters) = module.get_global(counters) {
+            counters.set_linkage(Linkage::External);
+        }
+    }
+}
+
+// Reads counters exposed by `expose_counters`, if the function they belong to
+// still has any
+pub(crate) fn read_jit_counters(
+    execution_engine: &ExecutionEngine,
+    counters: &str,
+    num_counters: usize,
+) -> Option<Vec<u64>> {
+    let counters = to_c_str(counters);
+    let address = unsafe { LLVMGetGlobalValueAddress(execution_engine.as_mut_ptr(), counters.as_ptr()) };
+
+    if address == 0 {
+        return None;
+    }
+
+    Some(unsafe { std::slice::from_raw_parts(address as *const u64, num_counters) }.to_vec())
+}
+
+fn next_number<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Result<u64, &'static str> {
+    lines
+        .next()
+        .ok_or("Unexpected end of profile.")?
+        .parse()
+        .map_err(|_| "Expected a number.")
+}
+
+// Finds the function a call to llvm.instrprof.increment(.step) counts
+unsafe fn get_instrumented_function(instruction: LLVMValueRef) -> Option<InstrumentedFunction> {
+    if LLVMIsACallInst(instruction).is_null() {
+        return None;
+    }
+
+    if !value_name(LLVMGetCalledValue(instruction)).starts_with("llvm.instrprof.increment") {
+        return None;
+    }
+
+    let mut name_var = LLVMGetOperand(instruction, 0);
+
+    // Typed pointers need the name cast to i8*
+    if !LLVMIsAConstantExpr(name_var).is_null() {
+        name_var = LLVMGetOperand(name_var, 0);
+    }
+
+    let mut length = 0;
+    let name = LLVMGetAsString(LLVMGetInitializer(name_var), &mut length);
+    let name = std::slice::from_raw_parts(name as *const u8, length);
+    let name_var = value_name(name_var);
+
+    Some(InstrumentedFunction {
+        name: String::from_utf8_lossy(name).into_owned(),
+        counters: format!("__profc_{}", name_var.strip_prefix("__profn_").unwrap_or(&name_var)),
+        hash: LLVMConstIntGetZExtValue(LLVMGetOperand(instruction, 1)),
+        num_counters: LLVMConstIntGetZExtValue(LLVMGetOperand(instruction, 2)),
+    })
+}
+
+unsafe fn value_name(value: LLVMValueRef) -> String {
+    let mut length = 0;
+    let name = LLVMGetValueName2(value, &mut length);
+    let name = std::slice::from_raw_parts(name as *const u8, length);
+
+    String::from_utf8_lossy(name).into_owned()
+}
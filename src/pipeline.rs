@@ -0,0 +1,2302 @@
+//! A typed builder for the textual pass pipelines accepted by
+//! `Module::run_passes`, so that typos in pass names are caught before they
+//! reach LLVM.
+//!
+//! A `Pipeline` is a list of module passes, which may nest CGSCC, function
+//! and loop passes through adaptors. It renders to the same format as opt's
+//! `-passes` argument through `Display`.
+//!
+//! ```no_run
+//! use inkwell::pipeline::{FunctionPass, LoopPass, ModulePass, Pipeline, PresetLevel};
+//! use inkwell::OptimizationLevel;
+//!
+//! let pipeline = Pipeline::new()
+//!     .add_pass(ModulePass::GlobalOpt)
+//!     .add_function_passes(vec![
+//!         FunctionPass::Sroa,
+//!         FunctionPass::InstCombine,
+//!         FunctionPass::Loop {
+//!             passes: vec![LoopPass::LoopRotate, LoopPass::Licm],
+//!             use_memory_ssa: true,
+//!         },
+//!         FunctionPass::LoopUnroll {
+//!             opt_level: OptimizationLevel::Aggressive,
+//!             full_unroll_max: Some(16),
+//!         },
+//!     ]);
+//!
+//! assert_eq!(
+//!     pipeline.to_string(),
+//!     "globalopt,function(sroa,instcombine,loop-mssa(loop-rotate,licm),loop-unroll<O3;full-unroll-max=16>)",
+//! );
+//! assert_eq!(Pipeline::parse(&pipeline.to_string()), Ok(pipeline));
+//! assert_eq!(Pipeline::preset(PresetLevel::O2).to_string(), "default<O2>");
+//!
+//! let error = Pipeline::validate("function(instcombin)").unwrap_err();
+//!
+//! assert_eq!(error.get_position(), 9);
+//! ```
+
+#[llvm_versions(13.0..=latest)]
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+#[llvm_versions(15.0..=latest)]
+use std::fs;
+#[llvm_versions(13.0..=latest)]
+use std::time::{Duration, Instant};
+
+#[llvm_versions(13.0..=latest)]
+use crate::module::Module;
+#[llvm_versions(13.0..=latest)]
+use crate::passes::PassBuilderOptions;
+#[llvm_versions(15.0..=latest)]
+use crate::support::parse_command_line_options;
+#[llvm_versions(13.0..=latest)]
+use crate::support::{to_c_str, LLVMString};
+#[llvm_versions(13.0..=latest)]
+use crate::targets::TargetMachine;
+#[llvm_versions(13.0..=latest)]
+use crate::values::FunctionValue;
+use crate::OptimizationLevel;
+
+/// The optimization level of a preset pipeline such as `default<O2>`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PresetLevel {
+    /// No optimization.
+    O0,
+    /// Quick optimizations.
+    O1,
+    /// The usual optimizations.
+    O2,
+    /// Aggressive optimizations.
+    O3,
+    /// The usual optimizations, favoring smaller code.
+    Os,
+    /// The usual optimizations, favoring much smaller code.
+    Oz,
+}
+
+impl PresetLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            PresetLevel::O0 => "O0",
+            PresetLevel::O1 => "O1",
+            PresetLevel::O2 => "O2",
+            PresetLevel::O3 => "O3",
+            PresetLevel::Os => "Os",
+            PresetLevel::Oz => "Oz",
+        }
+    }
+
+    fn from_str(level: &str) -> Option<Self> {
+        match level {
+            "O0" => Some(PresetLevel::O0),
+            "O1" => Some(PresetLevel::O1),
+            "O2" => Some(PresetLevel::O2),
+            "O3" => Some(PresetLevel::O3),
+            "Os" => Some(PresetLevel::Os),
+            "Oz" => Some(PresetLevel::Oz),
+            _ => None,
+        }
+    }
+}
+
+/// A pass or adaptor which runs on a whole module.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ModulePass {
+    /// The default optimization pipeline (`default<O2>`).
+    Default(PresetLevel),
+    /// The pre-link half of the ThinLTO pipeline (`thinlto-pre-link<O2>`).
+    ThinLtoPreLink(PresetLevel),
+    /// The post-link half of the ThinLTO pipeline (`thinlto<O2>`).
+    ThinLto(PresetLevel),
+    /// The pre-link half of the full LTO pipeline (`lto-pre-link<O2>`).
+    LtoPreLink(PresetLevel),
+    /// The post-link half of the full LTO pipeline (`lto<O2>`).
+    Lto(PresetLevel),
+    /// Inlines functions marked `alwaysinline` (`always-inline`).
+    AlwaysInline,
+    /// Merges duplicate global constants (`constmerge`).
+    ConstMerge,
+    /// Removes dead arguments of internal functions (`deadargelim`).
+    DeadArgElim,
+    /// Removes unreachable globals (`globaldce`).
+    GlobalDce,
+    /// Optimizes global variables (`globalopt`).
+    GlobalOpt,
+    /// Infers attributes of library functions (`inferattrs`).
+    InferAttrs,
+    /// Lowers profiling intrinsics to counters and profile data (`instrprof`).
+    InstrProf,
+    /// Propagates constants across functions (`ipsccp`).
+    Ipsccp,
+    /// Merges identical functions (`mergefunc`).
+    MergeFunctions,
+    /// Instruments edges with profiling counters (`pgo-instr-gen`).
+    PgoInstrGen,
+    /// Annotates branches and functions with weights from a profile
+    /// (`pgo-instr-use`).
+    PgoInstrUse,
+    /// Removes unused function declarations (`strip-dead-prototypes`).
+    StripDeadPrototypes,
+    /// Verifies the module (`verify`).
+    Verify,
+    /// Runs CGSCC passes over the call graph, bottom up (`cgscc(...)`).
+    Cgscc(Vec<CgsccPass>),
+    /// Runs function passes over every function (`function(...)`).
+    Function(Vec<FunctionPass>),
+    /// Any other pass or adaptor, rendered verbatim. `Pipeline::parse`
+    /// produces this for passes without a variant of their own, or with
+    /// parameters their variant can't hold.
+    Other(String),
+}
+
+/// A pass or adaptor which runs on a strongly connected component of the
+/// call graph.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CgsccPass {
+    /// Inlines functions (`inline`).
+    Inline,
+    /// Infers function attributes (`function-attrs`).
+    FunctionAttrs,
+    /// Promotes pointer arguments to values (`argpromotion`).
+    ArgPromotion,
+    /// Runs function passes over every function in the SCC (`function(...)`).
+    Function(Vec<FunctionPass>),
+    /// Any other pass or adaptor, rendered verbatim. `Pipeline::parse`
+    /// produces this for passes without a variant of their own, or with
+    /// parameters their variant can't hold.
+    Other(String),
+}
+
+/// A pass or adaptor which runs on a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FunctionPass {
+    /// Aggressive dead code elimination (`adce`).
+    Adce,
+    /// Bit tracking dead code elimination (`bdce`).
+    Bdce,
+    /// Propagates value ranges (`correlated-propagation`).
+    CorrelatedPropagation,
+    /// Dead code elimination (`dce`).
+    Dce,
+    /// Dead store elimination (`dse`).
+    Dse,
+    /// Early common subexpression elimination (`early-cse`).
+    EarlyCse,
+    /// Global value numbering (`gvn`).
+    Gvn,
+    /// Combines redundant instructions (`instcombine`).
+    InstCombine,
+    /// Simplifies instructions without creating new ones (`instsimplify`).
+    InstSimplify,
+    /// Threads jumps through predictable branches (`jump-threading`).
+    JumpThreading,
+    /// Converts loops to LCSSA form (`lcssa`).
+    Lcssa,
+    /// Distributes loops into multiple loops (`loop-distribute`).
+    LoopDistribute,
+    /// Forwards stores to loads across loop iterations (`loop-load-elim`).
+    LoopLoadElim,
+    /// Canonicalizes loops (`loop-simplify`).
+    LoopSimplify,
+    /// Vectorizes loops (`loop-vectorize`).
+    LoopVectorize,
+    /// Promotes allocas to registers (`mem2reg`).
+    Mem2Reg,
+    /// Merges and sinks loads and stores around diamonds (`mldst-motion`).
+    MergedLoadStoreMotion,
+    /// Reassociates expressions (`reassociate`).
+    Reassociate,
+    /// Sparse conditional constant propagation (`sccp`).
+    Sccp,
+    /// Vectorizes straight line code (`slp-vectorizer`).
+    SlpVectorizer,
+    /// Scalar replacement of aggregates (`sroa`).
+    Sroa,
+    /// Eliminates tail calls (`tailcallelim`).
+    TailCallElim,
+    /// Verifies the function (`verify`).
+    Verify,
+    /// Simplifies the control flow graph (`simplifycfg<...>`).
+    SimplifyCfg(SimplifyCfgOptions),
+    /// Unrolls loops (`loop-unroll<...>`).
+    LoopUnroll {
+        /// The optimization level the unroller tunes its thresholds for.
+        opt_level: OptimizationLevel,
+        /// The maximum trip count of loops which are fully unrolled, if not
+        /// the default.
+        full_unroll_max: Option<u32>,
+    },
+    /// Runs loop passes over every loop, innermost first (`loop(...)` or
+    /// `loop-mssa(...)`).
+    Loop {
+        /// The loop passes to run.
+        passes: Vec<LoopPass>,
+        /// Whether to make MemorySSA available, as `licm` needs.
+        use_memory_ssa: bool,
+    },
+    /// Any other pass or adaptor, rendered verbatim. `Pipeline::parse`
+    /// produces this for passes without a variant of their own, or with
+    /// parameters their variant can't hold.
+    Other(String),
+}
+
+/// Options of the `simplifycfg` pass. Options left as `None` keep their
+/// default.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SimplifyCfgOptions {
+    /// How many extra instructions may be speculated to fold a branch (`bonus-inst-threshold=N`).
+    pub bonus_inst_threshold: Option<u32>,
+    /// Whether to forward switch conditions to phi nodes (`forward-switch-cond`).
+    pub forward_switch_cond: Option<bool>,
+    /// Whether to convert switches to lookup tables (`switch-to-lookup`).
+    pub switch_to_lookup: Option<bool>,
+    /// Whether to keep canonical loop structure (`keep-loops`).
+    pub keep_loops: Option<bool>,
+    /// Whether to hoist instructions common to both successors (`hoist-common-insts`).
+    pub hoist_common_insts: Option<bool>,
+    /// Whether to sink instructions common to all predecessors (`sink-common-insts`).
+    pub sink_common_insts: Option<bool>,
+}
+
+impl SimplifyCfgOptions {
+    fn flags(&self) -> [(&'static str, Option<bool>); 5] {
+        [
+            ("forward-switch-cond", self.forward_switch_cond),
+            ("switch-to-lookup", self.switch_to_lookup),
+            ("keep-loops", self.keep_loops),
+            ("hoist-common-insts", self.hoist_common_insts),
+            ("sink-common-insts", self.sink_common_insts),
+        ]
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut Option<bool>> {
+        match name {
+            "forward-switch-cond" => Some(&mut self.forward_switch_cond),
+            "switch-to-lookup" => Some(&mut self.switch_to_lookup),
+            "keep-loops" => Some(&mut self.keep_loops),
+            "hoist-common-insts" => Some(&mut self.hoist_common_insts),
+            "sink-common-insts" => Some(&mut self.sink_common_insts),
+            _ => None,
+        }
+    }
+}
+
+/// A pass which runs on a loop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoopPass {
+    /// Simplifies induction variables (`indvars`).
+    IndVars,
+    /// Hoists and sinks loop invariant code (`licm`).
+    Licm,
+    /// Deletes dead loops (`loop-deletion`).
+    LoopDeletion,
+    /// Fully unrolls loops with small constant trip counts (`loop-unroll-full`).
+    LoopFullUnroll,
+    /// Recognizes idioms such as memset loops (`loop-idiom`).
+    LoopIdiom,
+    /// Simplifies instructions in loops (`loop-instsimplify`).
+    LoopInstSimplify,
+    /// Strength reduces loop induction variables (`loop-reduce`).
+    LoopReduce,
+    /// Rotates loops into do-while form (`loop-rotate`).
+    LoopRotate,
+    /// Unswitches loop invariant branches (`simple-loop-unswitch`).
+    SimpleLoopUnswitch,
+    /// Any other pass or adaptor, rendered verbatim. `Pipeline::parse`
+    /// produces this for passes without a variant of their own, or with
+    /// parameters their variant can't hold.
+    Other(String),
+}
+
+const MODULE_PASSES: [(&str, ModulePass); 13] = [
+    ("always-inline", ModulePass::AlwaysInline),
+    ("constmerge", ModulePass::ConstMerge),
+    ("deadargelim", ModulePass::DeadArgElim),
+    ("globaldce", ModulePass::GlobalDce),
+    ("globalopt", ModulePass::GlobalOpt),
+    ("inferattrs", ModulePass::InferAttrs),
+    ("instrprof", ModulePass::InstrProf),
+    ("ipsccp", ModulePass::Ipsccp),
+    ("mergefunc", ModulePass::MergeFunctions),
+    ("pgo-instr-gen", ModulePass::PgoInstrGen),
+    ("pgo-instr-use", ModulePass::PgoInstrUse),
+    ("strip-dead-prototypes", ModulePass::StripDeadPrototypes),
+    ("verify", ModulePass::Verify),
+];
+
+const CGSCC_PASSES: [(&str, CgsccPass); 3] = [
+    ("inline", CgsccPass::Inline),
+    ("function-attrs", CgsccPass::FunctionAttrs),
+    ("argpromotion", CgsccPass::ArgPromotion),
+];
+
+const FUNCTION_PASSES: [(&str, FunctionPass); 23] = [
+    ("adce", FunctionPass::Adce),
+    ("bdce", FunctionPass::Bdce),
+    ("correlated-propagation", FunctionPass::CorrelatedPropagation),
+    ("dce", FunctionPass::Dce),
+    ("dse", FunctionPass::Dse),
+    ("early-cse", FunctionPass::EarlyCse),
+    ("gvn", FunctionPass::Gvn),
+    ("instcombine", FunctionPass::InstCombine),
+    ("instsimplify", FunctionPass::InstSimplify),
+    ("jump-threading", FunctionPass::JumpThreading),
+    ("lcssa", FunctionPass::Lcssa),
+    ("loop-distribute", FunctionPass::LoopDistribute),
+    ("loop-load-elim", FunctionPass::LoopLoadElim),
+    ("loop-simplify", FunctionPass::LoopSimplify),
+    ("loop-vectorize", FunctionPass::LoopVectorize),
+    ("mem2reg", FunctionPass::Mem2Reg),
+    ("mldst-motion", FunctionPass::MergedLoadStoreMotion),
+    ("reassociate", FunctionPass::Reassociate),
+    ("sccp", FunctionPass::Sccp),
+    ("slp-vectorizer", FunctionPass::SlpVectorizer),
+    ("sroa", FunctionPass::Sroa),
+    ("tailcallelim", FunctionPass::TailCallElim),
+    ("verify", FunctionPass::Verify),
+];
+
+const LOOP_PASSES: [(&str, LoopPass); 9] = [
+    ("indvars", LoopPass::IndVars),
+    ("licm", LoopPass::Licm),
+    ("loop-deletion", LoopPass::LoopDeletion),
+    ("loop-unroll-full", LoopPass::LoopFullUnroll),
+    ("loop-idiom", LoopPass::LoopIdiom),
+    ("loop-instsimplify", LoopPass::LoopInstSimplify),
+    ("loop-reduce", LoopPass::LoopReduce),
+    ("loop-rotate", LoopPass::LoopRotate),
+    ("simple-loop-unswitch", LoopPass::SimpleLoopUnswitch),
+];
+
+// Names of the passes and analyses registered with LLVM 14's `PassBuilder`,
+// by level, to check pipelines against. Parametrized passes are listed by
+// name only, except for analysis printers and verifiers such as
+// `print<domtree>`.
+const MODULE_PASS_NAMES: [&str; 77] = [
+    "always-inline",
+    "attributor",
+    "annotation2metadata",
+    "openmp-opt",
+    "called-value-propagation",
+    "canonicalize-aliases",
+    "cg-profile",
+    "check-debugify",
+    "constmerge",
+    "cross-dso-cfi",
+    "deadargelim",
+    "debugify",
+    "elim-avail-extern",
+    "extract-blocks",
+    "forceattrs",
+    "function-import",
+    "function-specialization",
+    "globaldce",
+    "globalopt",
+    "globalsplit",
+    "hotcoldsplit",
+    "inferattrs",
+    "inliner-wrapper",
+    "print<inline-advisor>",
+    "inliner-wrapper-no-mandatory-first",
+    "insert-gcov-profiling",
+    "instrorderfile",
+    "instrprof",
+    "internalize",
+    "ipsccp",
+    "iroutliner",
+    "print-ir-similarity",
+    "lowertypetests",
+    "metarenamer",
+    "mergefunc",
+    "name-anon-globals",
+    "no-op-module",
+    "objc-arc-apelim",
+    "partial-inliner",
+    "pgo-icall-prom",
+    "pgo-instr-gen",
+    "pgo-instr-use",
+    "print-profile-summary",
+    "print-callgraph",
+    "print",
+    "print-lcg",
+    "print-lcg-dot",
+    "print-must-be-executed-contexts",
+    "print-stack-safety",
+    "print<module-debuginfo>",
+    "rel-lookup-table-converter",
+    "rewrite-statepoints-for-gc",
+    "rewrite-symbols",
+    "rpo-function-attrs",
+    "sample-profile",
+    "scc-oz-module-inliner",
+    "strip",
+    "strip-dead-debug-info",
+    "pseudo-probe",
+    "strip-dead-prototypes",
+    "strip-debug-declare",
+    "strip-nondebug",
+    "strip-nonlinetable-debuginfo",
+    "synthetic-counts-propagation",
+    "verify",
+    "wholeprogramdevirt",
+    "dfsan",
+    "msan-module",
+    "module-inline",
+    "tsan-module",
+    "sancov-module",
+    "memprof-module",
+    "poison-checking",
+    "pseudo-probe-update",
+    "loop-extract",
+    "hwasan",
+    "asan-module",
+];
+
+const CGSCC_PASS_NAMES: [&str; 7] = [
+    "argpromotion",
+    "function-attrs",
+    "attributor-cgscc",
+    "openmp-opt-cgscc",
+    "coro-split",
+    "no-op-cgscc",
+    "inline",
+];
+
+const FUNCTION_PASS_NAMES: [&str; 139] = [
+    "aa-eval",
+    "adce",
+    "add-discriminators",
+    "aggressive-instcombine",
+    "assume-builder",
+    "assume-simplify",
+    "alignment-from-assumptions",
+    "annotation-remarks",
+    "bdce",
+    "bounds-checking",
+    "break-crit-edges",
+    "callsite-splitting",
+    "consthoist",
+    "constraint-elimination",
+    "chr",
+    "coro-early",
+    "coro-elide",
+    "coro-cleanup",
+    "correlated-propagation",
+    "dce",
+    "dfa-jump-threading",
+    "div-rem-pairs",
+    "dse",
+    "dot-cfg",
+    "dot-cfg-only",
+    "dot-dom",
+    "dot-dom-only",
+    "fix-irreducible",
+    "flattencfg",
+    "make-guards-explicit",
+    "gvn-hoist",
+    "gvn-sink",
+    "helloworld",
+    "infer-address-spaces",
+    "instcombine",
+    "instcount",
+    "instsimplify",
+    "irce",
+    "float2int",
+    "no-op-function",
+    "libcalls-shrinkwrap",
+    "lint",
+    "inject-tli-mappings",
+    "instnamer",
+    "loweratomic",
+    "lower-expect",
+    "lower-guard-intrinsic",
+    "lower-constant-intrinsics",
+    "lower-widenable-condition",
+    "guard-widening",
+    "load-store-vectorizer",
+    "loop-simplify",
+    "loop-sink",
+    "lowerinvoke",
+    "lowerswitch",
+    "mem2reg",
+    "memcpyopt",
+    "mergeicmps",
+    "mergereturn",
+    "nary-reassociate",
+    "newgvn",
+    "jump-threading",
+    "partially-inline-libcalls",
+    "lcssa",
+    "loop-data-prefetch",
+    "loop-load-elim",
+    "loop-fusion",
+    "loop-distribute",
+    "loop-versioning",
+    "objc-arc",
+    "objc-arc-contract",
+    "objc-arc-expand",
+    "pgo-memop-opt",
+    "print",
+    "print<assumptions>",
+    "print<block-freq>",
+    "print<branch-prob>",
+    "print<cost-model>",
+    "print<cycles>",
+    "print<da>",
+    "print<divergence>",
+    "print<domtree>",
+    "print<postdomtree>",
+    "print<delinearization>",
+    "print<demanded-bits>",
+    "print<domfrontier>",
+    "print<func-properties>",
+    "print<inline-cost>",
+    "print<inliner-size-estimator>",
+    "print<loops>",
+    "print<memoryssa>",
+    "print<memoryssa-walker>",
+    "print<phi-values>",
+    "print<regions>",
+    "print<scalar-evolution>",
+    "print<stack-safety-local>",
+    "print-alias-sets",
+    "print-predicateinfo",
+    "print-mustexecute",
+    "print-memderefs",
+    "reassociate",
+    "redundant-dbg-inst-elim",
+    "reg2mem",
+    "scalarize-masked-mem-intrin",
+    "scalarizer",
+    "separate-const-offset-from-gep",
+    "sccp",
+    "sink",
+    "slp-vectorizer",
+    "slsr",
+    "speculative-execution",
+    "sroa",
+    "strip-gc-relocates",
+    "structurizecfg",
+    "tailcallelim",
+    "unify-loop-exits",
+    "vector-combine",
+    "verify",
+    "verify<domtree>",
+    "verify<loops>",
+    "verify<memoryssa>",
+    "verify<regions>",
+    "verify<safepoint-ir>",
+    "verify<scalar-evolution>",
+    "view-cfg",
+    "view-cfg-only",
+    "transform-warning",
+    "tsan",
+    "memprof",
+    "early-cse",
+    "ee-instrument",
+    "lower-matrix-intrinsics",
+    "loop-unroll",
+    "asan",
+    "msan",
+    "simplifycfg",
+    "loop-vectorize",
+    "mldst-motion",
+    "gvn",
+];
+
+const LOOP_PASS_NAMES: [&str; 29] = [
+    "lnicm",
+    "loop-flatten",
+    "loop-interchange",
+    "loop-unroll-and-jam",
+    "no-op-loopnest",
+    "canon-freeze",
+    "dot-ddg",
+    "licm",
+    "loop-idiom",
+    "loop-instsimplify",
+    "loop-rotate",
+    "no-op-loop",
+    "print",
+    "loop-deletion",
+    "loop-simplifycfg",
+    "loop-reduce",
+    "indvars",
+    "loop-unroll-full",
+    "print-access-info",
+    "print<ddg>",
+    "print<iv-users>",
+    "print<loopnest>",
+    "print<loop-cache-cost>",
+    "loop-predication",
+    "guard-widening",
+    "loop-bound-split",
+    "loop-reroll",
+    "loop-versioning-licm",
+    "simple-loop-unswitch",
+];
+
+const MODULE_ANALYSIS_NAMES: [&str; 12] = [
+    "callgraph",
+    "lcg",
+    "module-summary",
+    "no-op-module",
+    "profile-summary",
+    "stack-safety",
+    "verify",
+    "pass-instrumentation",
+    "asan-globals-md",
+    "inline-advisor",
+    "ir-similarity",
+    "globals-aa",
+];
+
+const CGSCC_ANALYSIS_NAMES: [&str; 3] = ["no-op-cgscc", "fam-proxy", "pass-instrumentation"];
+
+const FUNCTION_ANALYSIS_NAMES: [&str; 36] = [
+    "aa",
+    "assumptions",
+    "block-freq",
+    "branch-prob",
+    "cycles",
+    "domtree",
+    "postdomtree",
+    "demanded-bits",
+    "domfrontier",
+    "func-properties",
+    "loops",
+    "lazy-value-info",
+    "da",
+    "inliner-size-estimator",
+    "memdep",
+    "memoryssa",
+    "phi-values",
+    "regions",
+    "no-op-function",
+    "opt-remark-emit",
+    "scalar-evolution",
+    "should-not-run-function-passes",
+    "should-run-extra-vector-passes",
+    "stack-safety-local",
+    "targetlibinfo",
+    "targetir",
+    "verify",
+    "pass-instrumentation",
+    "divergence",
+    "basic-aa",
+    "cfl-anders-aa",
+    "cfl-steens-aa",
+    "objc-arc-aa",
+    "scev-aa",
+    "scoped-noalias-aa",
+    "tbaa",
+];
+
+const LOOP_ANALYSIS_NAMES: [&str; 5] = ["no-op-loop", "access-info", "ddg", "iv-users", "pass-instrumentation"];
+
+fn find_name<T: PartialEq>(passes: &[(&'static str, T)], pass: &T) -> Option<&'static str> {
+    passes.iter().find(|(_, known)| known == pass).map(|(name, _)| *name)
+}
+
+fn find_pass<T: Clone>(passes: &[(&'static str, T)], name: &str) -> Option<T> {
+    passes
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, pass)| pass.clone())
+}
+
+/// A pass pipeline for `Module::run_passes`, made up of module passes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pipeline {
+    passes: Vec<ModulePass>,
+}
+
+impl Pipeline {
+    /// Creates an empty `Pipeline`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `Pipeline` running the default optimization pipeline at
+    /// `level`, like clang does.
+    pub fn preset(level: PresetLevel) -> Self {
+        Pipeline::new().add_pass(ModulePass::Default(level))
+    }
+
+    /// Appends a module pass to this `Pipeline`.
+    pub fn add_pass(mut self, pass: ModulePass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Appends CGSCC passes to this `Pipeline`, to be run over the call graph.
+    pub fn add_cgscc_passes(self, passes: Vec<CgsccPass>) -> Self {
+        self.add_pass(ModulePass::Cgscc(passes))
+    }
+
+    /// Appends function passes to this `Pipeline`, to be run over every function.
+    pub fn add_function_passes(self, passes: Vec<FunctionPass>) -> Self {
+        self.add_pass(ModulePass::Function(passes))
+    }
+
+    /// Gets the module passes of this `Pipeline`.
+    pub fn get_passes(&self) -> &[ModulePass] {
+        &self.passes
+    }
+
+    /// Parses a textual pipeline, as accepted by `Module::run_passes`.
+    ///
+    /// Like LLVM, the first pass decides the level of the whole list: a
+    /// pipeline starting with a function pass, for example, is wrapped in
+    /// `function(...)`, so that a module pass later on is an error. Within a
+    /// module or CGSCC pipeline, each pass of a lower level is wrapped in an
+    /// adaptor of its own.
+    ///
+    /// Pass names are checked against those LLVM 14 registers, but the
+    /// parameters of passes are only checked for the options modeled here,
+    /// such as `SimplifyCfgOptions`. Returns an error with the position of
+    /// the first problem, such as an unknown pass name.
+    pub fn parse(text: &str) -> Result<Self, PipelineError> {
+        Ok(Pipeline {
+            passes: pipeline_passes(&Parser::parse(text)?)?,
+        })
+    }
+
+    /// Checks that `text` is a valid textual pipeline, reporting the position
+    /// of the first problem if not.
+    pub fn validate(text: &str) -> Result<(), PipelineError> {
+        Self::parse(text).map(|_| ())
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_list(f, &self.passes)
+    }
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, passes: &[T]) -> fmt::Result {
+    for (index, pass) in passes.iter().enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+        }
+
+        write!(f, "{}", pass)?;
+    }
+
+    Ok(())
+}
+
+fn write_adaptor<T: fmt::Display>(f: &mut fmt::Formatter<'_>, name: &str, passes: &[T]) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    write_list(f, passes)?;
+    write!(f, ")")
+}
+
+impl fmt::Display for ModulePass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModulePass::Default(level) => write!(f, "default<{}>", level.as_str()),
+            ModulePass::ThinLtoPreLink(level) => write!(f, "thinlto-pre-link<{}>", level.as_str()),
+            ModulePass::ThinLto(level) => write!(f, "thinlto<{}>", level.as_str()),
+            ModulePass::LtoPreLink(level) => write!(f, "lto-pre-link<{}>", level.as_str()),
+            ModulePass::Lto(level) => write!(f, "lto<{}>", level.as_str()),
+            ModulePass::Cgscc(passes) => write_adaptor(f, "cgscc", passes),
+            ModulePass::Function(passes) => write_adaptor(f, "function", passes),
+            ModulePass::Other(text) => write!(f, "{}", text),
+            pass => write!(f, "{}", find_name(&MODULE_PASSES, pass).unwrap()),
+        }
+    }
+}
+
+impl fmt::Display for CgsccPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CgsccPass::Function(passes) => write_adaptor(f, "function", passes),
+            CgsccPass::Other(text) => write!(f, "{}", text),
+            pass => write!(f, "{}", find_name(&CGSCC_PASSES, pass).unwrap()),
+        }
+    }
+}
+
+impl fmt::Display for FunctionPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FunctionPass::SimplifyCfg(options) => {
+                let mut params = Vec::new();
+
+                if let Some(threshold) = options.bonus_inst_threshold {
+                    params.push(format!("bonus-inst-threshold={}", threshold));
+                }
+
+                for (name, flag) in options.flags() {
+                    match flag {
+                        Some(true) => params.push(name.to_string()),
+                        Some(false) => params.push(format!("no-{}", name)),
+                        None => {},
+                    }
+                }
+
+                if params.is_empty() {
+                    write!(f, "simplifycfg")
+                } else {
+                    write!(f, "simplifycfg<{}>", params.join(";"))
+                }
+            },
+            FunctionPass::LoopUnroll {
+                opt_level,
+                full_unroll_max,
+            } => {
+                write!(f, "loop-unroll<O{}", *opt_level as u32)?;
+
+                if let Some(full_unroll_max) = full_unroll_max {
+                    write!(f, ";full-unroll-max={}", full_unroll_max)?;
+                }
+
+                write!(f, ">")
+            },
+            FunctionPass::Loop { passes, use_memory_ssa } => {
+                write_adaptor(f, if *use_memory_ssa { "loop-mssa" } else { "loop" }, passes)
+            },
+            FunctionPass::Other(text) => write!(f, "{}", text),
+            pass => write!(f, "{}", find_name(&FUNCTION_PASSES, pass).unwrap()),
+        }
+    }
+}
+
+impl fmt::Display for LoopPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopPass::Other(text) => write!(f, "{}", text),
+            pass => write!(f, "{}", find_name(&LOOP_PASSES, pass).unwrap()),
+        }
+    }
+}
+
+/// An error in a textual pipeline, found by `Pipeline::parse`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PipelineError {
+    position: usize,
+    message: String,
+}
+
+impl PipelineError {
+    /// Gets the byte offset of the problem in the pipeline text.
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    /// Gets a description of the problem.
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for PipelineError {}
+
+fn error<T>(position: usize, message: String) -> Result<T, PipelineError> {
+    Err(PipelineError { position, message })
+}
+
+// A pass as written in the pipeline text, before it is checked against the
+// passes known at its nesting level.
+struct Element<'a> {
+    text: &'a str,
+    name: &'a str,
+    position: usize,
+    params: Option<(&'a str, usize)>,
+    children: Option<Vec<Element<'a>>>,
+}
+
+impl<'a> Element<'a> {
+    fn expect_no_children(&self) -> Result<(), PipelineError> {
+        if self.children.is_some() {
+            return error(
+                self.position,
+                format!("Pass '{}' can't contain other passes", self.name),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn expect_children(&self) -> Result<&[Element<'a>], PipelineError> {
+        if let Some((_, position)) = self.params {
+            return error(position, format!("Adaptor '{}' takes no parameters", self.name));
+        }
+
+        match &self.children {
+            Some(children) => Ok(children),
+            None => error(self.position, format!("Adaptor '{}' needs a list of passes", self.name)),
+        }
+    }
+
+    // Gets the `;` separated parameters, along with their positions
+    fn param_list(&self) -> Vec<(&'a str, usize)> {
+        let (params, mut position) = match self.params {
+            Some(params) => params,
+            None => return Vec::new(),
+        };
+
+        params
+            .split(';')
+            .map(|param| {
+                let start = position;
+
+                position += param.len() + 1;
+
+                (param, start)
+            })
+            .collect()
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    // Parses the structure of a pipeline, without checking pass names
+    fn parse(text: &'a str) -> Result<Vec<Element<'a>>, PipelineError> {
+        let mut parser = Parser { text, position: 0 };
+        let elements = parser.parse_list()?;
+
+        if parser.position < text.len() {
+            return Err(parser.error(format!("Unexpected '{}'", parser.peek().unwrap())));
+        }
+
+        Ok(elements)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: String) -> PipelineError {
+        PipelineError {
+            position: self.position,
+            message,
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<Element<'a>>, PipelineError> {
+        let mut elements = vec![self.parse_element()?];
+
+        while self.eat(',') {
+            elements.push(self.parse_element()?);
+        }
+
+        Ok(elements)
+    }
+
+    fn parse_element(&mut self) -> Result<Element<'a>, PipelineError> {
+        let position = self.position;
+        let length = self.text[position..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .unwrap_or(self.text.len() - position);
+
+        if length == 0 {
+            return Err(self.error("Expected a pass name".to_string()));
+        }
+
+        self.position += length;
+
+        let name = &self.text[position..self.position];
+        let mut params = None;
+        let mut children = None;
+
+        if self.eat('<') {
+            let start = self.position;
+
+            match self.text[start..].find('>') {
+                Some(length) => {
+                    params = Some((&self.text[start..start + length], start));
+                    self.position = start + length + 1;
+                },
+                None => return error(start - 1, "Unterminated '<'".to_string()),
+            }
+        }
+
+        if self.eat('(') {
+            children = Some(if self.eat(')') {
+                Vec::new()
+            } else {
+                let list = self.parse_list()?;
+
+                if !self.eat(')') {
+                    return Err(self.error("Expected ',' or ')'".to_string()));
+                }
+
+                list
+            });
+        }
+
+        Ok(Element {
+            text: &self.text[position..self.position],
+            name,
+            position,
+            params,
+            children,
+        })
+    }
+}
+
+// The nesting level of a pipeline's passes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Level {
+    Module,
+    Cgscc,
+    Function,
+    Loop,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Module => "module",
+            Level::Cgscc => "CGSCC",
+            Level::Function => "function",
+            Level::Loop => "loop",
+        }
+    }
+
+    fn pass_names(self) -> &'static [&'static str] {
+        match self {
+            Level::Module => &MODULE_PASS_NAMES,
+            Level::Cgscc => &CGSCC_PASS_NAMES,
+            Level::Function => &FUNCTION_PASS_NAMES,
+            Level::Loop => &LOOP_PASS_NAMES,
+        }
+    }
+
+    fn analysis_names(self) -> &'static [&'static str] {
+        match self {
+            Level::Module => &MODULE_ANALYSIS_NAMES,
+            Level::Cgscc => &CGSCC_ANALYSIS_NAMES,
+            Level::Function => &FUNCTION_ANALYSIS_NAMES,
+            Level::Loop => &LOOP_ANALYSIS_NAMES,
+        }
+    }
+
+    // The adaptors which may contain other passes at this level
+    fn adaptor_names(self) -> &'static [&'static str] {
+        match self {
+            Level::Module => &["module", "cgscc", "function", "repeat"],
+            Level::Cgscc => &["cgscc", "function", "repeat", "devirt"],
+            Level::Function => &["function", "loop", "loop-mssa", "repeat"],
+            Level::Loop => &["loop", "repeat"],
+        }
+    }
+
+    // Infers the level of a top-level pipeline from its first pass, as
+    // `PassBuilder::parsePassPipeline` does
+    fn infer(element: &Element<'_>) -> Option<Level> {
+        if PRESET_NAMES.contains(&element.name) {
+            return Some(Level::Module);
+        }
+
+        [Level::Module, Level::Cgscc, Level::Function, Level::Loop]
+            .into_iter()
+            .find(|&level| level.adaptor_names().contains(&element.name) || element.is_pass_at(level))
+    }
+}
+
+const PRESET_NAMES: [&str; 5] = ["default", "thinlto-pre-link", "thinlto", "lto-pre-link", "lto"];
+
+impl<'a> Element<'a> {
+    // Gets the name along with its parameters, which is how analysis
+    // printers such as `print<domtree>` are named
+    fn head(&self) -> &'a str {
+        match self.params {
+            Some((params, position)) => &self.text[..position - self.position + params.len() + 1],
+            None => self.name,
+        }
+    }
+
+    // Whether LLVM knows this as a pass at `level`, not counting adaptors.
+    // Parameters aren't checked, except that `print` and `verify` only take
+    // the analyses they're registered for.
+    fn is_pass_at(&self, level: Level) -> bool {
+        let names = level.pass_names();
+
+        match (self.name, self.params) {
+            ("require", Some((analysis, _))) => level.analysis_names().contains(&analysis),
+            ("invalidate", Some((analysis, _))) => analysis == "all" || level.analysis_names().contains(&analysis),
+            ("print" | "verify", Some(_)) => names.contains(&self.head()),
+            (name, _) => names.contains(&name),
+        }
+    }
+
+    // Whether LLVM wraps this in an adaptor when it's found at a higher level
+    // than `level`, which it does for passes but not analyses
+    fn is_wrappable_at(&self, level: Level) -> bool {
+        !matches!(self.name, "require" | "invalidate") && self.is_pass_at(level)
+    }
+
+    // Gets the passes of an adaptor such as `repeat<N>(...)`, which needs a count
+    fn expect_counted_children(&self) -> Result<&[Element<'a>], PipelineError> {
+        match self.params {
+            Some((count, _)) if count.parse::<u32>().is_ok() => {},
+            Some((count, position)) => return error(position, format!("Invalid {} count '{}'", self.name, count)),
+            None => {
+                return error(
+                    self.position + self.name.len(),
+                    format!("Adaptor '{}' needs a count", self.name),
+                )
+            },
+        }
+
+        match &self.children {
+            Some(children) => Ok(children),
+            None => error(self.position, format!("Adaptor '{}' needs a list of passes", self.name)),
+        }
+    }
+
+    // Fails for passes which contain others without being an adaptor at `level`
+    fn nesting_error<T>(&self, level: Level) -> Result<T, PipelineError> {
+        let is_adaptor = [Level::Module, Level::Cgscc, Level::Function, Level::Loop]
+            .into_iter()
+            .any(|level| level.adaptor_names().contains(&self.name));
+        let message = if is_adaptor {
+            format!("Adaptor '{}' can't be used in a {} pipeline", self.name, level.as_str())
+        } else {
+            format!("Pass '{}' can't contain other passes", self.name)
+        };
+
+        error(self.position, message)
+    }
+}
+
+// Gets the variant of a pass known at its level, or `Other` for passes which
+// have no variant or have parameters the variant can't hold
+fn known_pass<T: Clone>(passes: &[(&'static str, T)], element: &Element<'_>, other: fn(String) -> T) -> T {
+    match find_pass(passes, element.name) {
+        Some(pass) if element.params.is_none() => pass,
+        _ => other(element.text.to_string()),
+    }
+}
+
+fn parse_preset_level(element: &Element<'_>) -> Result<PresetLevel, PipelineError> {
+    element.expect_no_children()?;
+
+    match element.params {
+        Some((level, position)) => PresetLevel::from_str(level).map_or_else(
+            || error(position, format!("Unknown optimization level '{}'", level)),
+            Ok,
+        ),
+        None => error(
+            element.position + element.name.len(),
+            format!("Preset '{}' needs an optimization level", element.name),
+        ),
+    }
+}
+
+fn pipeline_passes(elements: &[Element<'_>]) -> Result<Vec<ModulePass>, PipelineError> {
+    let first = &elements[0];
+
+    Ok(match Level::infer(first) {
+        Some(Level::Module) => module_passes(elements)?,
+        Some(Level::Cgscc) => vec![ModulePass::Cgscc(cgscc_passes(elements)?)],
+        Some(Level::Function) => vec![ModulePass::Function(function_passes(elements)?)],
+        // LLVM only makes MemorySSA available to a loop pipeline if it
+        // starts with a pass which needs it
+        Some(Level::Loop) => vec![ModulePass::Function(vec![FunctionPass::Loop {
+            passes: loop_passes(elements)?,
+            use_memory_ssa: matches!(first.name, "licm" | "lnicm"),
+        }])],
+        None => return error(first.position, format!("Unknown pass '{}'", first.name)),
+    })
+}
+
+// Checks a list of function and loop passes, such as `FunctionValue::run_passes`
+// wraps in `function(...)`
+#[llvm_versions(13.0..=latest)]
+pub(crate) fn validate_function_passes(text: &str) -> Result<(), PipelineError> {
+    function_passes(&Parser::parse(text)?).map(|_| ())
+}
+
+fn module_passes(elements: &[Element<'_>]) -> Result<Vec<ModulePass>, PipelineError> {
+    let mut passes = Vec::new();
+
+    for element in elements {
+        let pass = match element.name {
+            "module" => {
+                passes.extend(module_passes(element.expect_children()?)?);
+                continue;
+            },
+            "cgscc" => ModulePass::Cgscc(cgscc_passes(element.expect_children()?)?),
+            "function" => ModulePass::Function(function_passes(element.expect_children()?)?),
+            "repeat" => {
+                module_passes(element.expect_counted_children()?)?;
+                ModulePass::Other(element.text.to_string())
+            },
+            "default" => ModulePass::Default(parse_preset_level(element)?),
+            "thinlto-pre-link" => ModulePass::ThinLtoPreLink(parse_preset_level(element)?),
+            "thinlto" => ModulePass::ThinLto(parse_preset_level(element)?),
+            "lto-pre-link" => ModulePass::LtoPreLink(parse_preset_level(element)?),
+            "lto" => ModulePass::Lto(parse_preset_level(element)?),
+            _ if element.children.is_some() => return element.nesting_error(Level::Module),
+            _ if element.is_pass_at(Level::Module) => known_pass(&MODULE_PASSES, element, ModulePass::Other),
+            // Each pass of another level gets an adaptor of its own
+            _ if element.is_wrappable_at(Level::Cgscc) => {
+                ModulePass::Cgscc(cgscc_passes(std::slice::from_ref(element))?)
+            },
+            _ if element.is_wrappable_at(Level::Function) || element.is_wrappable_at(Level::Loop) => {
+                ModulePass::Function(function_passes(std::slice::from_ref(element))?)
+            },
+            _ => return error(element.position, format!("Unknown module pass '{}'", element.head())),
+        };
+
+        passes.push(pass);
+    }
+
+    Ok(passes)
+}
+
+fn cgscc_passes(elements: &[Element<'_>]) -> Result<Vec<CgsccPass>, PipelineError> {
+    let mut passes = Vec::new();
+
+    for element in elements {
+        let pass = match element.name {
+            "function" => CgsccPass::Function(function_passes(element.expect_children()?)?),
+            "cgscc" => {
+                cgscc_passes(element.expect_children()?)?;
+                CgsccPass::Other(element.text.to_string())
+            },
+            "repeat" | "devirt" => {
+                cgscc_passes(element.expect_counted_children()?)?;
+                CgsccPass::Other(element.text.to_string())
+            },
+            _ if element.children.is_some() => return element.nesting_error(Level::Cgscc),
+            _ if element.is_pass_at(Level::Cgscc) => known_pass(&CGSCC_PASSES, element, CgsccPass::Other),
+            _ if element.is_wrappable_at(Level::Function) || element.is_wrappable_at(Level::Loop) => {
+                CgsccPass::Function(function_passes(std::slice::from_ref(element))?)
+            },
+            _ => return error(element.position, format!("Unknown CGSCC pass '{}'", element.head())),
+        };
+
+        passes.push(pass);
+    }
+
+    Ok(passes)
+}
+
+fn function_passes(elements: &[Element<'_>]) -> Result<Vec<FunctionPass>, PipelineError> {
+    let mut passes = Vec::new();
+
+    for element in elements {
+        let pass = match element.name {
+            "loop" | "loop-mssa" => FunctionPass::Loop {
+                passes: loop_passes(element.expect_children()?)?,
+                use_memory_ssa: element.name == "loop-mssa",
+            },
+            "function" => {
+                function_passes(element.expect_children()?)?;
+                FunctionPass::Other(element.text.to_string())
+            },
+            "repeat" => {
+                function_passes(element.expect_counted_children()?)?;
+                FunctionPass::Other(element.text.to_string())
+            },
+            "simplifycfg" => match parse_simplify_cfg_options(element)? {
+                Some(options) => FunctionPass::SimplifyCfg(options),
+                None => FunctionPass::Other(element.text.to_string()),
+            },
+            "loop-unroll" => parse_loop_unroll(element)?,
+            _ if element.children.is_some() => return element.nesting_error(Level::Function),
+            _ if element.is_pass_at(Level::Function) => known_pass(&FUNCTION_PASSES, element, FunctionPass::Other),
+            _ if element.is_wrappable_at(Level::Loop) => FunctionPass::Loop {
+                passes: loop_passes(std::slice::from_ref(element))?,
+                use_memory_ssa: false,
+            },
+            _ => return error(element.position, format!("Unknown function pass '{}'", element.head())),
+        };
+
+        passes.push(pass);
+    }
+
+    Ok(passes)
+}
+
+fn loop_passes(elements: &[Element<'_>]) -> Result<Vec<LoopPass>, PipelineError> {
+    let mut passes = Vec::new();
+
+    for element in elements {
+        let pass = match element.name {
+            "loop" => {
+                loop_passes(element.expect_children()?)?;
+                LoopPass::Other(element.text.to_string())
+            },
+            "repeat" => {
+                loop_passes(element.expect_counted_children()?)?;
+                LoopPass::Other(element.text.to_string())
+            },
+            _ if element.children.is_some() => return element.nesting_error(Level::Loop),
+            _ if element.is_pass_at(Level::Loop) => known_pass(&LOOP_PASSES, element, LoopPass::Other),
+            _ => return error(element.position, format!("Unknown loop pass '{}'", element.head())),
+        };
+
+        passes.push(pass);
+    }
+
+    Ok(passes)
+}
+
+// Parses the options of `simplifycfg`, returning `None` if some aren't
+// modeled by `SimplifyCfgOptions`
+fn parse_simplify_cfg_options(element: &Element<'_>) -> Result<Option<SimplifyCfgOptions>, PipelineError> {
+    element.expect_no_children()?;
+
+    let mut options = SimplifyCfgOptions::default();
+
+    for (param, position) in element.param_list() {
+        if let Some(threshold) = param.strip_prefix("bonus-inst-threshold=") {
+            match threshold.parse() {
+                Ok(threshold) => options.bonus_inst_threshold = Some(threshold),
+                Err(_) => return error(position, format!("Invalid bonus instruction threshold '{}'", threshold)),
+            }
+
+            continue;
+        }
+
+        let (name, value) = match param.strip_prefix("no-") {
+            Some(name) => (name, false),
+            None => (param, true),
+        };
+
+        match options.flag_mut(name) {
+            Some(flag) => *flag = Some(value),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(options))
+}
+
+// Parses `loop-unroll`, falling back to `FunctionPass::Other` for options
+// which `FunctionPass::LoopUnroll` doesn't model
+fn parse_loop_unroll(element: &Element<'_>) -> Result<FunctionPass, PipelineError> {
+    element.expect_no_children()?;
+
+    let mut opt_level = OptimizationLevel::Default;
+    let mut full_unroll_max = None;
+
+    for (param, position) in element.param_list() {
+        if let Some(max) = param.strip_prefix("full-unroll-max=") {
+            match max.parse() {
+                Ok(max) => full_unroll_max = Some(max),
+                Err(_) => return error(position, format!("Invalid full unroll maximum '{}'", max)),
+            }
+
+            continue;
+        }
+
+        opt_level = match param {
+            "O0" => OptimizationLevel::None,
+            "O1" => OptimizationLevel::Less,
+            "O2" => OptimizationLevel::Default,
+            "O3" => OptimizationLevel::Aggressive,
+            _ => return Ok(FunctionPass::Other(element.text.to_string())),
+        };
+    }
+
+    Ok(FunctionPass::LoopUnroll {
+        opt_level,
+        full_unroll_max,
+    })
+}
+
+/// A pass written in Rust which runs on a whole module, for use with
+/// `CustomPasses`. Closures taking a `&Module` are custom module passes.
+#[llvm_versions(13.0..=latest)]
+pub trait CustomModulePass<'ctx> {
+    /// Runs this pass over `module`, returning whether it changed the IR.
+    fn run(&mut self, module: &Module<'ctx>) -> bool;
+}
+
+#[llvm_versions(13.0..=latest)]
+impl<'ctx, F: FnMut(&Module<'ctx>) -> bool> CustomModulePass<'ctx> for F {
+    fn run(&mut self, module: &Module<'ctx>) -> bool {
+        self(module)
+    }
+}
+
+/// A pass written in Rust which runs on a function, for use with
+/// `CustomPasses`. Closures taking a `FunctionValue` are custom function
+/// passes.
+#[llvm_versions(13.0..=latest)]
+pub trait CustomFunctionPass<'ctx> {
+    /// Runs this pass over `function`, returning whether it changed the IR.
+    fn run(&mut self, function: FunctionValue<'ctx>) -> bool;
+}
+
+#[llvm_versions(13.0..=latest)]
+impl<'ctx, F: FnMut(FunctionValue<'ctx>) -> bool> CustomFunctionPass<'ctx> for F {
+    fn run(&mut self, function: FunctionValue<'ctx>) -> bool {
+        self(function)
+    }
+}
+
+/// Named passes written in Rust, which can be used alongside LLVM's passes in
+/// a textual pipeline.
+///
+/// LLVM can't call back into Rust from its pass manager, so `run_passes`
+/// splits the pipeline around custom passes and runs the LLVM passes between
+/// them with `Module::run_passes`. This breaks up the pass manager nesting
+/// the pipeline text describes:
+///
+/// * Custom module passes may only appear at the top level or in a
+///   `module(...)` adaptor.
+/// * Custom function passes may also appear in a `function(...)` adaptor
+///   which isn't nested in another adaptor. The adaptor is split around them,
+///   so each pass of it runs over every function before the next pass starts,
+///   rather than all of them running on one function at a time.
+/// * Custom passes can't appear in any other adaptor, such as `cgscc(...)`,
+///   `loop(...)` or a `function(...)` nested in those, nor take parameters.
+///   Pipelines which name them there are rejected with an error.
+///
+/// LLVM's analyses are also recomputed after each custom pass, since they
+/// can't be kept across separate `Module::run_passes` calls.
+///
+/// ```no_run
+/// use inkwell::context::Context;
+/// use inkwell::passes::PassBuilderOptions;
+/// use inkwell::pipeline::CustomPasses;
+/// use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+/// use inkwell::values::FunctionValue;
+/// use inkwell::OptimizationLevel;
+///
+/// Target::initialize_native(&InitializationConfig::default()).unwrap();
+///
+/// let context = Context::create();
+/// let module = context.create_module("my_module");
+/// let triple = TargetMachine::get_default_triple();
+/// let target = Target::from_triple(&triple).unwrap();
+/// let machine = target
+///     .create_target_machine(
+///         &triple,
+///         "generic",
+///         "",
+///         OptimizationLevel::Default,
+///         RelocMode::Default,
+///         CodeModel::Default,
+///     )
+///     .unwrap();
+/// let mut lowered = 0;
+/// let mut custom_passes = CustomPasses::new();
+///
+/// custom_passes.add_function_pass("my-lowering", |_function: FunctionValue| {
+///     lowered += 1;
+///     false
+/// });
+/// custom_passes
+///     .run_passes(&module, "function(my-lowering),instcombine", &machine, &PassBuilderOptions::create())
+///     .unwrap();
+/// ```
+#[llvm_versions(13.0..=latest)]
+#[derive(Default)]
+pub struct CustomPasses<'a, 'ctx> {
+    module_passes: HashMap<String, Box<dyn CustomModulePass<'ctx> + 'a>>,
+    function_passes: HashMap<String, Box<dyn CustomFunctionPass<'ctx> + 'a>>,
+}
+
+#[llvm_versions(13.0..=latest)]
+impl fmt::Debug for CustomPasses<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomPasses")
+            .field("module_passes", &self.module_passes.keys().collect::<Vec<_>>())
+            .field("function_passes", &self.function_passes.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Sizes of a module's IR, counted by walking the module after each step of
+/// a pipeline recorded by `PipelineRecorder`. These stand in for LLVM's
+/// `-stats` statistic counters, which release builds of LLVM don't keep and
+/// the C API can't print.
+#[llvm_versions(13.0..=latest)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IrSizeCounts {
+    /// The number of function definitions.
+    pub functions: u32,
+    /// The number of basic blocks.
+    pub basic_blocks: u32,
+    /// The number of instructions.
+    pub instructions: u32,
+}
+
+#[llvm_versions(13.0..=latest)]
+impl IrSizeCounts {
+    fn of_module(module: &Module<'_>) -> Self {
+        let mut counts = IrSizeCounts::default();
+
+        for function in module
+            .get_functions()
+            .filter(|function| function.count_basic_blocks() > 0)
+        {
+            counts.functions += 1;
+
+            for basic_block in function.get_basic_blocks() {
+                let mut next_instruction = basic_block.get_first_instruction();
+
+                counts.basic_blocks += 1;
+
+                while let Some(instruction) = next_instruction {
+                    counts.instructions += 1;
+                    next_instruction = instruction.get_next_instruction();
+                }
+            }
+        }
+
+        counts
+    }
+}
+
+/// Records each step of a pipeline run by `CustomPasses::run_passes_recorded`:
+/// how long it took, the sizes of the module's IR after it, and the module's
+/// IR after selected steps. On LLVM 15 and later, it can also record how long
+/// each pass LLVM ran took, from the report of opt's `-time-passes`.
+///
+/// The pipeline text is split into steps, each run by its own
+/// `Module::run_passes` call, and measured from the outside:
+///
+/// * Each top level pass is one step, and so is each pass of a top level
+///   `function(...)` adaptor, which runs over every function before the next
+///   step.
+/// * Presets such as `default<O2>` and other adaptors such as `cgscc(...)`
+///   or `loop(...)` are a single step, however many passes LLVM runs for them.
+/// * Timings are wall clock times of whole steps, including setting up the
+///   pass manager and recomputing analyses.
+#[llvm_versions(13.0..=latest)]
+#[derive(Debug, Default)]
+pub struct PipelineRecorder {
+    record_timings: bool,
+    record_ir_counts: bool,
+    record_pass_timings: bool,
+    snapshot_all: bool,
+    snapshot_passes: Vec<String>,
+    step_timings: Vec<(String, Duration)>,
+    pass_timings: Vec<(String, Duration)>,
+    step_ir_counts: Vec<(String, IrSizeCounts)>,
+    ir_snapshots: Vec<(String, String)>,
+}
+
+#[llvm_versions(13.0..=latest)]
+impl PipelineRecorder {
+    /// Creates a `PipelineRecorder` which records nothing until enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to record how long each step takes.
+    pub fn set_record_timings(&mut self, record_timings: bool) {
+        self.record_timings = record_timings;
+    }
+
+    /// Sets whether to record how long each pass LLVM runs takes, as
+    /// reported by `-time-passes`.
+    ///
+    /// LLVM only takes this from process wide command line options, which
+    /// are set for each step and reset after it. The report is written to a
+    /// file in `std::env::temp_dir()` in between.
+    #[llvm_versions(15.0..=latest)]
+    pub fn set_record_pass_timings(&mut self, record_pass_timings: bool) {
+        self.record_pass_timings = record_pass_timings;
+    }
+
+    /// Sets whether to record `IrSizeCounts` of the module after each step.
+    pub fn set_record_ir_counts(&mut self, record_ir_counts: bool) {
+        self.record_ir_counts = record_ir_counts;
+    }
+
+    /// Sets the steps after which the module's IR is captured. A step
+    /// matches by name, such as `instcombine`, or by its full text, such as
+    /// `simplifycfg<keep-loops>`.
+    pub fn set_ir_snapshots(&mut self, passes: &[&str]) {
+        self.snapshot_passes = passes.iter().map(|pass| pass.to_string()).collect();
+    }
+
+    /// Sets whether to capture the module's IR after every step.
+    pub fn set_ir_snapshots_after_all(&mut self, snapshot_all: bool) {
+        self.snapshot_all = snapshot_all;
+    }
+
+    /// Gets each step run so far, along with how long it took.
+    pub fn get_step_timings(&self) -> &[(String, Duration)] {
+        &self.step_timings
+    }
+
+    /// Gets each pass LLVM ran so far, by the name of its class such as
+    /// `InstCombinePass`, along with the wall clock time spent in all of its
+    /// runs. Analyses are included, as LLVM times them too.
+    #[llvm_versions(15.0..=latest)]
+    pub fn get_pass_timings(&self) -> &[(String, Duration)] {
+        &self.pass_timings
+    }
+
+    /// Gets each step run so far, along with `IrSizeCounts` of the module
+    /// after it.
+    pub fn get_ir_counts(&self) -> &[(String, IrSizeCounts)] {
+        &self.step_ir_counts
+    }
+
+    /// Gets each selected step run so far, along with the module's IR after it.
+    pub fn get_ir_snapshots(&self) -> &[(String, String)] {
+        &self.ir_snapshots
+    }
+
+    /// Discards everything recorded so far, keeping the settings.
+    pub fn clear(&mut self) {
+        self.step_timings.clear();
+        self.pass_timings.clear();
+        self.step_ir_counts.clear();
+        self.ir_snapshots.clear();
+    }
+
+    #[llvm_versions(13.0..=14.0)]
+    fn time_llvm_passes(&mut self, run: impl FnOnce() -> Result<(), LLVMString>) -> Result<(), LLVMString> {
+        run()
+    }
+
+    // Runs LLVM passes, adding the time `-time-passes` reports for each of
+    // them to `pass_timings`
+    #[llvm_versions(15.0..=latest)]
+    fn time_llvm_passes(&mut self, run: impl FnOnce() -> Result<(), LLVMString>) -> Result<(), LLVMString> {
+        let path = std::env::temp_dir().join(format!("inkwell-time-passes-{}.txt", std::process::id()));
+
+        // LLVM appends its report to the file
+        let _ = fs::remove_file(&path);
+
+        parse_command_line_options(&[
+            "-time-passes".to_string(),
+            format!("-info-output-file={}", path.display()),
+        ]);
+
+        let result = run();
+
+        parse_command_line_options(&["-time-passes=false".to_string(), "-info-output-file=-".to_string()]);
+
+        let report = fs::read_to_string(&path).unwrap_or_default();
+        let _ = fs::remove_file(&path);
+
+        for (name, duration) in parse_time_passes_report(&report) {
+            match self.pass_timings.iter_mut().find(|(known, _)| *known == name) {
+                Some((_, total)) => *total += duration,
+                None => self.pass_timings.push((name, duration)),
+            }
+        }
+
+        result
+    }
+
+    fn record(&mut self, element: &Element<'_>, duration: Duration, module: &Module<'_>) {
+        if self.record_timings {
+            self.step_timings.push((element.text.to_string(), duration));
+        }
+
+        if self.record_ir_counts {
+            self.step_ir_counts
+                .push((element.text.to_string(), IrSizeCounts::of_module(module)));
+        }
+
+        let is_selected = self
+            .snapshot_passes
+            .iter()
+            .any(|pass| pass == element.name || pass == element.text);
+
+        if self.snapshot_all || is_selected {
+            self.ir_snapshots
+                .push((element.text.to_string(), module.print_to_string().to_string()));
+        }
+    }
+}
+
+/// The first step of a pipeline after which a module is no longer good, as
+/// found by `CustomPasses::bisect`.
+#[llvm_versions(13.0..=latest)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BadStep {
+    number: usize,
+    text: String,
+    pass_run: Option<usize>,
+}
+
+#[llvm_versions(13.0..=latest)]
+impl BadStep {
+    /// Gets the number of the step, counting from one as
+    /// `CustomPasses::run_passes_limited` does.
+    pub fn get_number(&self) -> usize {
+        self.number
+    }
+
+    /// Gets the text of the step, such as `default<O2>`.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Gets the number of the pass run within the step which made the module
+    /// bad, counting from one as `-opt-bisect-limit` does. Running just the
+    /// step with opt's `-opt-bisect-limit` set to this number names the pass.
+    ///
+    /// This is `None` before LLVM 15, for custom passes, and for steps which
+    /// only went bad in passes LLVM doesn't count, such as `always-inline`.
+    pub fn get_pass_run(&self) -> Option<usize> {
+        self.pass_run
+    }
+}
+
+// How far `CustomPasses::run` gets through a pipeline
+#[llvm_versions(13.0..=latest)]
+#[derive(Clone, Copy)]
+struct StepLimit {
+    // The number of steps to run
+    steps: usize,
+    // How many of the pass runs `-opt-bisect-limit` counts to allow in the
+    // last step, if LLVM runs it
+    last_step_passes: Option<usize>,
+}
+
+#[llvm_versions(13.0..=14.0)]
+fn run_llvm_passes(
+    module: &Module<'_>,
+    passes: &str,
+    machine: &TargetMachine,
+    options: &PassBuilderOptions,
+    _pass_limit: Option<usize>,
+) -> Result<(), LLVMString> {
+    module.run_passes_with_options(passes, machine, options)
+}
+
+// Runs LLVM passes, stopping after `pass_limit` of the pass runs which
+// `-opt-bisect-limit` counts. Only LLVM 15 and later restart the count each
+// time the option is set.
+#[llvm_versions(15.0..=latest)]
+fn run_llvm_passes(
+    module: &Module<'_>,
+    passes: &str,
+    machine: &TargetMachine,
+    options: &PassBuilderOptions,
+    pass_limit: Option<usize>,
+) -> Result<(), LLVMString> {
+    let pass_limit = match pass_limit {
+        Some(pass_limit) => pass_limit.min(i32::MAX as usize - 1),
+        None => return module.run_passes_with_options(passes, machine, options),
+    };
+
+    parse_command_line_options(&[format!("-opt-bisect-limit={}", pass_limit)]);
+
+    let result = module.run_passes_with_options(passes, machine, options);
+
+    // The maximum disables bisection again
+    parse_command_line_options(&[format!("-opt-bisect-limit={}", i32::MAX)]);
+
+    result
+}
+
+#[llvm_versions(13.0..=14.0)]
+fn bisect_llvm_step(
+    _is_good_after: impl FnMut(StepLimit) -> Result<bool, LLVMString>,
+    _step: usize,
+) -> Result<Option<usize>, LLVMString> {
+    Ok(None)
+}
+
+// Finds the first pass run of step number `step` after which the module is
+// bad, given that it's good before the step and bad after it. As the number
+// of pass runs in the step isn't known, the limit is doubled until the module
+// goes bad before bisecting.
+#[llvm_versions(15.0..=latest)]
+fn bisect_llvm_step(
+    mut is_good_after: impl FnMut(StepLimit) -> Result<bool, LLVMString>,
+    step: usize,
+) -> Result<Option<usize>, LLVMString> {
+    let mut is_good_after_passes = |passes| {
+        is_good_after(StepLimit {
+            steps: step,
+            last_step_passes: Some(passes),
+        })
+    };
+
+    if !is_good_after_passes(0)? {
+        return Ok(None);
+    }
+
+    let mut good = 0;
+    let mut bad = 1;
+
+    while is_good_after_passes(bad)? {
+        if bad >= i32::MAX as usize {
+            return Ok(None);
+        }
+
+        good = bad;
+        bad *= 2;
+    }
+
+    while bad - good > 1 {
+        let middle = (good + bad) / 2;
+
+        if is_good_after_passes(middle)? {
+            good = middle;
+        } else {
+            bad = middle;
+        }
+    }
+
+    Ok(Some(bad))
+}
+
+// Reads the wall clock time of each pass from a `-time-passes` report, whose
+// rows look like `0.0003 ( 34.9%)   ...   0.0006 ( 35.0%)  InstCombinePass`
+// with the wall time last
+#[llvm_versions(15.0..=latest)]
+fn parse_time_passes_report(report: &str) -> Vec<(String, Duration)> {
+    let mut timings = Vec::new();
+    let mut in_pass_report = false;
+
+    for line in report.lines() {
+        if line.contains("Pass execution timing report") {
+            in_pass_report = true;
+            continue;
+        }
+
+        let (columns, name) = match line.rfind("%)") {
+            Some(end) if in_pass_report => (&line[..end], line[end + 2..].trim()),
+            _ => continue,
+        };
+
+        // The total ends the report
+        if name == "Total" {
+            in_pass_report = false;
+            continue;
+        }
+
+        let wall_time = columns
+            .rsplit_once('(')
+            .and_then(|(times, _)| times.split_whitespace().last())
+            .and_then(|seconds| seconds.parse::<f64>().ok());
+
+        if let Some(seconds) = wall_time {
+            timings.push((name.to_string(), Duration::from_secs_f64(seconds)));
+        }
+    }
+
+    timings
+}
+
+// Where a pass appears in a pipeline, as far as custom passes are concerned:
+// inside the outermost adaptor which can't contain them, if any
+#[llvm_versions(13.0..=latest)]
+#[derive(Clone, Copy)]
+enum CustomPassScope<'t> {
+    Module,
+    Function,
+    Adaptor(&'t str),
+}
+
+// A part of a pipeline run by either LLVM or a custom pass, along with the
+// pass it was split from
+#[llvm_versions(13.0..=latest)]
+struct Segment<'e, 't> {
+    element: &'e Element<'t>,
+    kind: SegmentKind,
+}
+
+#[llvm_versions(13.0..=latest)]
+enum SegmentKind {
+    Llvm(Vec<String>),
+    Module,
+    Function,
+}
+
+#[llvm_versions(13.0..=latest)]
+impl<'a, 'ctx> CustomPasses<'a, 'ctx> {
+    /// Creates an empty set of custom passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a module pass under `name`, replacing any custom pass of the
+    /// same name. It takes precedence over an LLVM pass of the same name.
+    pub fn add_module_pass(&mut self, name: &str, pass: impl CustomModulePass<'ctx> + 'a) {
+        self.function_passes.remove(name);
+        self.module_passes.insert(name.to_string(), Box::new(pass));
+    }
+
+    /// Registers a function pass under `name`, replacing any custom pass of
+    /// the same name. It takes precedence over an LLVM pass of the same name.
+    pub fn add_function_pass(&mut self, name: &str, pass: impl CustomFunctionPass<'ctx> + 'a) {
+        self.module_passes.remove(name);
+        self.function_passes.insert(name.to_string(), Box::new(pass));
+    }
+
+    /// Runs a textual pipeline, as accepted by `Module::run_passes`, which may
+    /// also name the custom passes registered here. Returns whether any of the
+    /// custom passes changed the IR.
+    pub fn run_passes(
+        &mut self,
+        module: &Module<'ctx>,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+    ) -> Result<bool, LLVMString> {
+        self.run(module, passes, machine, options, None, None)
+            .map(|(changed, _)| changed)
+    }
+
+    /// Like `run_passes`, but runs each step of the pipeline, as described by
+    /// `PipelineRecorder`, on its own and records it in `recorder`. Steps which
+    /// ran before an error are still recorded.
+    pub fn run_passes_recorded(
+        &mut self,
+        module: &Module<'ctx>,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+        recorder: &mut PipelineRecorder,
+    ) -> Result<bool, LLVMString> {
+        self.run(module, passes, machine, options, Some(recorder), None)
+            .map(|(changed, _)| changed)
+    }
+
+    /// Counts the steps of a pipeline, as described by `PipelineRecorder`,
+    /// which `run_passes_limited` can stop after.
+    ///
+    /// These are not the pass runs opt's `-opt-bisect-limit` counts. A preset
+    /// such as `default<O2>` or an adaptor such as `cgscc(...)` or `loop(...)`
+    /// is a single step, however many passes LLVM runs for it. `bisect`
+    /// narrows a bad step down to one of those pass runs where it can.
+    pub fn count_passes(&self, passes: &str) -> Result<usize, LLVMString> {
+        let elements = self.parse_elements(passes)?;
+        let mut segments = Vec::new();
+
+        self.split_module_passes(&elements, &mut segments, true);
+
+        Ok(segments.len())
+    }
+
+    /// Runs only the first `limit` steps of a pipeline, counted as by
+    /// `count_passes`. Returns the text of step number `limit`, counting from
+    /// one, or `None` if the pipeline has fewer steps.
+    ///
+    /// Unlike opt's `-opt-bisect-limit`, this can't stop partway through a
+    /// preset or a nested adaptor.
+    pub fn run_passes_limited(
+        &mut self,
+        module: &Module<'ctx>,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+        limit: usize,
+    ) -> Result<Option<String>, LLVMString> {
+        let step_limit = StepLimit {
+            steps: limit,
+            last_step_passes: None,
+        };
+        let (_, mut passes_run) = self.run(module, passes, machine, options, None, Some(step_limit))?;
+
+        if passes_run.len() < limit {
+            return Ok(None);
+        }
+
+        Ok(passes_run.pop())
+    }
+
+    /// Finds the first step of a pipeline after which `module` is no longer
+    /// good, by running the first steps of the pipeline over clones of
+    /// `module` and bisecting on `is_good`. `module` itself isn't changed.
+    ///
+    /// Returns the first bad step, or `None` if `module` is still good after
+    /// the whole pipeline. Returns an error if `module` is bad before any
+    /// step runs.
+    ///
+    /// Steps are counted as by `count_passes`. On LLVM 15 and later, a bad
+    /// step run by LLVM, such as a preset, is then bisected further with
+    /// `-opt-bisect-limit`, down to the pass run which made `module` bad.
+    /// LLVM prints each pass run to stderr while doing so.
+    pub fn bisect(
+        &mut self,
+        module: &Module<'ctx>,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+        mut is_good: impl FnMut(&Module<'ctx>) -> bool,
+    ) -> Result<Option<BadStep>, LLVMString> {
+        let mut is_good_after = |custom_passes: &mut Self, limit: StepLimit| -> Result<bool, LLVMString> {
+            let trial = module.clone();
+
+            custom_passes.run(&trial, passes, machine, options, None, Some(limit))?;
+
+            Ok(is_good(&trial))
+        };
+        let steps = |steps| StepLimit {
+            steps,
+            last_step_passes: None,
+        };
+
+        if !is_good_after(self, steps(0))? {
+            return Err(LLVMString::create_from_c_str(&to_c_str(
+                "The module is bad before any pass has run.",
+            )));
+        }
+
+        let elements = self.parse_elements(passes)?;
+        let mut segments = Vec::new();
+
+        self.split_module_passes(&elements, &mut segments, true);
+
+        let mut good = 0;
+        let mut bad = segments.len();
+
+        if bad == 0 || is_good_after(self, steps(bad))? {
+            return Ok(None);
+        }
+
+        while bad - good > 1 {
+            let middle = (good + bad) / 2;
+
+            if is_good_after(self, steps(middle))? {
+                good = middle;
+            } else {
+                bad = middle;
+            }
+        }
+
+        let segment = &segments[bad - 1];
+        let pass_run = match segment.kind {
+            SegmentKind::Llvm(_) => bisect_llvm_step(|limit| is_good_after(self, limit), bad)?,
+            _ => None,
+        };
+
+        Ok(Some(BadStep {
+            number: bad,
+            text: segment.element.text.to_string(),
+            pass_run,
+        }))
+    }
+
+    // Returns whether custom passes changed the IR, and the passes run
+    fn run(
+        &mut self,
+        module: &Module<'ctx>,
+        passes: &str,
+        machine: &TargetMachine,
+        options: &PassBuilderOptions,
+        mut recorder: Option<&mut PipelineRecorder>,
+        limit: Option<StepLimit>,
+    ) -> Result<(bool, Vec<String>), LLVMString> {
+        let elements = self.parse_elements(passes)?;
+        let mut segments = Vec::new();
+
+        self.split_module_passes(&elements, &mut segments, recorder.is_some() || limit.is_some());
+
+        let mut changed = false;
+        let mut passes_run = Vec::new();
+
+        let steps = limit.map_or(usize::MAX, |limit| limit.steps);
+
+        for (index, segment) in segments.into_iter().take(steps).enumerate() {
+            let start = Instant::now();
+            let pass_limit = limit
+                .and_then(|limit| limit.last_step_passes)
+                .filter(|_| index + 1 == steps);
+
+            match segment.kind {
+                SegmentKind::Llvm(passes) => {
+                    let run_passes = || run_llvm_passes(module, &passes.join(","), machine, options, pass_limit);
+
+                    match recorder.as_mut() {
+                        Some(recorder) if recorder.record_pass_timings => recorder.time_llvm_passes(run_passes)?,
+                        _ => run_passes()?,
+                    }
+                },
+                SegmentKind::Module => {
+                    changed |= self.module_passes.get_mut(segment.element.name).unwrap().run(module);
+                },
+                SegmentKind::Function => {
+                    let pass = self.function_passes.get_mut(segment.element.name).unwrap();
+
+                    for function in module.get_functions() {
+                        if function.count_basic_blocks() > 0 {
+                            changed |= pass.run(function);
+                        }
+                    }
+                },
+            }
+
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(segment.element, start.elapsed(), module);
+            }
+
+            passes_run.push(segment.element.text.to_string());
+        }
+
+        Ok((changed, passes_run))
+    }
+
+    fn parse_elements<'t>(&self, passes: &'t str) -> Result<Vec<Element<'t>>, LLVMString> {
+        Parser::parse(passes)
+            .and_then(|elements| {
+                self.check_custom_passes(&elements, CustomPassScope::Module)?;
+
+                Ok(elements)
+            })
+            .map_err(|error| LLVMString::create_from_c_str(&to_c_str(&error.to_string())))
+    }
+
+    // Rejects custom passes which `split_module_passes` can't split the
+    // pipeline around
+    fn check_custom_passes<'t>(
+        &self,
+        elements: &[Element<'t>],
+        scope: CustomPassScope<'t>,
+    ) -> Result<(), PipelineError> {
+        for element in elements {
+            let is_module_pass = self.module_passes.contains_key(element.name);
+            let is_function_pass = self.function_passes.contains_key(element.name);
+
+            if is_module_pass || is_function_pass {
+                if element.params.is_some() || element.children.is_some() {
+                    return error(
+                        element.position,
+                        format!("Custom pass '{}' takes no parameters", element.name),
+                    );
+                }
+
+                match scope {
+                    CustomPassScope::Module => {},
+                    CustomPassScope::Function if is_function_pass => {},
+                    CustomPassScope::Function => {
+                        return error(
+                            element.position,
+                            format!("Custom module pass '{}' can't run inside 'function'", element.name),
+                        )
+                    },
+                    CustomPassScope::Adaptor(adaptor) => {
+                        return error(
+                            element.position,
+                            format!("Custom pass '{}' can't run inside '{}'", element.name, adaptor),
+                        )
+                    },
+                }
+            }
+
+            if let Some(children) = &element.children {
+                let child_scope = match (scope, element.name, element.params) {
+                    (CustomPassScope::Module, "module", None) => CustomPassScope::Module,
+                    (CustomPassScope::Module, "function", None) => CustomPassScope::Function,
+                    (CustomPassScope::Adaptor(adaptor), _, _) => CustomPassScope::Adaptor(adaptor),
+                    _ => CustomPassScope::Adaptor(element.name),
+                };
+
+                self.check_custom_passes(children, child_scope)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn split_module_passes<'e, 't>(
+        &self,
+        elements: &'e [Element<'t>],
+        segments: &mut Vec<Segment<'e, 't>>,
+        separate: bool,
+    ) {
+        for element in elements {
+            let is_simple = element.params.is_none() && element.children.is_none();
+
+            match &element.children {
+                Some(children) if element.name == "module" && element.params.is_none() => {
+                    self.split_module_passes(children, segments, separate);
+                },
+                Some(children) if element.name == "function" && element.params.is_none() => {
+                    self.split_function_passes(children, segments, separate);
+                },
+                _ if is_simple && self.module_passes.contains_key(element.name) => {
+                    segments.push(Segment {
+                        element,
+                        kind: SegmentKind::Module,
+                    });
+                },
+                _ if is_simple && self.function_passes.contains_key(element.name) => {
+                    segments.push(Segment {
+                        element,
+                        kind: SegmentKind::Function,
+                    });
+                },
+                _ => push_llvm_pass(segments, element, element.text.to_string(), separate),
+            }
+        }
+    }
+
+    fn split_function_passes<'e, 't>(
+        &self,
+        elements: &'e [Element<'t>],
+        segments: &mut Vec<Segment<'e, 't>>,
+        separate: bool,
+    ) {
+        let mut llvm_passes = Vec::new();
+
+        for element in elements {
+            let is_simple = element.params.is_none() && element.children.is_none();
+
+            if is_simple && self.function_passes.contains_key(element.name) {
+                push_llvm_function_passes(segments, &mut llvm_passes);
+                segments.push(Segment {
+                    element,
+                    kind: SegmentKind::Function,
+                });
+            } else if separate {
+                push_llvm_pass(segments, element, format!("function({})", element.text), true);
+            } else {
+                llvm_passes.push(element);
+            }
+        }
+
+        push_llvm_function_passes(segments, &mut llvm_passes);
+    }
+}
+
+// Appends LLVM passes to the last segment, unless they must run on their own
+#[llvm_versions(13.0..=latest)]
+fn push_llvm_pass<'e, 't>(segments: &mut Vec<Segment<'e, 't>>, element: &'e Element<'t>, pass: String, separate: bool) {
+    match segments.last_mut() {
+        Some(Segment {
+            kind: SegmentKind::Llvm(passes),
+            ..
+        }) if !separate => passes.push(pass),
+        _ => segments.push(Segment {
+            element,
+            kind: SegmentKind::Llvm(vec![pass]),
+        }),
+    }
+}
+
+#[llvm_versions(13.0..=latest)]
+fn push_llvm_function_passes<'e, 't>(segments: &mut Vec<Segment<'e, 't>>, passes: &mut Vec<&'e Element<'t>>) {
+    if let Some(&first) = passes.first() {
+        let texts: Vec<&str> = passes.iter().map(|pass| pass.text).collect();
+
+        push_llvm_pass(segments, first, format!("function({})", texts.join(",")), false);
+        passes.clear();
+    }
+}
@@ -0,0 +1,325 @@
+//! Optimization remarks, which explain what passes did or failed to do, such
+//! as why a call wasn't inlined or a loop wasn't vectorized.
+//!
+//! Remarks are serialized by tools such as `opt -pass-remarks-output` or
+//! `clang -fsave-optimization-record`, in either YAML or LLVM's bitstream
+//! format, and can be parsed from either.
+//!
+//! ```no_run
+//! use inkwell::remarks::{Remark, RemarkKind};
+//!
+//! let yaml = b"--- !Missed
+//! Pass:            inline
+//! Name:            NoDefinition
+//! Function:        main
+//! Args:
+//!   - Callee:          foo
+//!   - String:          ' will not be inlined into '
+//!   - Caller:          main
+//! ...
+//! ";
+//! let remarks = Remark::parse_yaml(yaml).unwrap();
+//!
+//! assert_eq!(remarks[0].kind, RemarkKind::Missed);
+//! assert_eq!(remarks[0].get_message(), "foo will not be inlined into main");
+//! assert_eq!(Remark::parse_yaml(remarks[0].to_yaml().as_bytes()).unwrap(), remarks);
+//! ```
+
+use llvm_sys::remarks::{
+    LLVMRemarkArgGetDebugLoc, LLVMRemarkArgGetKey, LLVMRemarkArgGetValue, LLVMRemarkDebugLocGetSourceColumn,
+    LLVMRemarkDebugLocGetSourceFilePath, LLVMRemarkDebugLocGetSourceLine, LLVMRemarkDebugLocRef,
+    LLVMRemarkEntryDispose, LLVMRemarkEntryGetDebugLoc, LLVMRemarkEntryGetFirstArg, LLVMRemarkEntryGetFunctionName,
+    LLVMRemarkEntryGetHotness, LLVMRemarkEntryGetNextArg, LLVMRemarkEntryGetPassName, LLVMRemarkEntryGetRemarkName,
+    LLVMRemarkEntryGetType, LLVMRemarkEntryRef, LLVMRemarkParserCreateYAML, LLVMRemarkParserDispose,
+    LLVMRemarkParserGetErrorMessage, LLVMRemarkParserGetNext, LLVMRemarkParserHasError, LLVMRemarkParserRef,
+    LLVMRemarkStringGetData, LLVMRemarkStringGetLen, LLVMRemarkStringRef, LLVMRemarkType,
+};
+
+use std::ffi::CStr;
+use std::fmt::Write;
+
+use crate::support::LLVMString;
+
+/// The kind of an optimization `Remark`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RemarkKind {
+    /// A remark of a kind this version of LLVM doesn't know.
+    Unknown,
+    /// An optimization was applied.
+    Passed,
+    /// An optimization was missed.
+    Missed,
+    /// Information gathered while deciding whether to optimize.
+    Analysis,
+    /// An analysis remark about floating point reassociation being disallowed.
+    AnalysisFpCommute,
+    /// An analysis remark about pointers which may alias.
+    AnalysisAliasing,
+    /// A requested optimization, such as through a loop hint, failed.
+    Failure,
+}
+
+impl RemarkKind {
+    fn yaml_tag(self) -> &'static str {
+        match self {
+            RemarkKind::Unknown => "!Unknown",
+            RemarkKind::Passed => "!Passed",
+            RemarkKind::Missed => "!Missed",
+            RemarkKind::Analysis => "!Analysis",
+            RemarkKind::AnalysisFpCommute => "!AnalysisFPCommute",
+            RemarkKind::AnalysisAliasing => "!AnalysisAliasing",
+            RemarkKind::Failure => "!Failure",
+        }
+    }
+}
+
+impl From<LLVMRemarkType> for RemarkKind {
+    fn from(remark_type: LLVMRemarkType) -> Self {
+        match remark_type {
+            LLVMRemarkType::LLVMRemarkTypeUnknown => RemarkKind::Unknown,
+            LLVMRemarkType::LLVMRemarkTypePassed => RemarkKind::Passed,
+            LLVMRemarkType::LLVMRemarkTypeMissed => RemarkKind::Missed,
+            LLVMRemarkType::LLVMRemarkTypeAnalysis => RemarkKind::Analysis,
+            LLVMRemarkType::LLVMRemarkTypeAnalysisFPCommute => RemarkKind::AnalysisFpCommute,
+            LLVMRemarkType::LLVMRemarkTypeAnalysisAliasing => RemarkKind::AnalysisAliasing,
+            LLVMRemarkType::LLVMRemarkTypeFailure => RemarkKind::Failure,
+        }
+    }
+}
+
+/// A source location a `Remark` or one of its arguments refers to.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RemarkDebugLoc {
+    /// The path of the source file.
+    pub file: String,
+    /// The line in the source file.
+    pub line: u32,
+    /// The column in the source file.
+    pub column: u32,
+}
+
+impl RemarkDebugLoc {
+    unsafe fn new(debug_loc: LLVMRemarkDebugLocRef) -> Option<Self> {
+        if debug_loc.is_null() {
+            return None;
+        }
+
+        Some(RemarkDebugLoc {
+            file: remark_string(LLVMRemarkDebugLocGetSourceFilePath(debug_loc)),
+            line: LLVMRemarkDebugLocGetSourceLine(debug_loc),
+            column: LLVMRemarkDebugLocGetSourceColumn(debug_loc),
+        })
+    }
+
+    fn to_yaml(&self) -> String {
+        format!(
+            "{{ File: {}, Line: {}, Column: {} }}",
+            yaml_string(&self.file),
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// A key value pair making up part of a `Remark`'s message, such as the
+/// callee of a call which wasn't inlined.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RemarkArg {
+    /// What the value is, such as `Callee` or `String` for plain text.
+    pub key: String,
+    /// The text of the value.
+    pub value: String,
+    /// Where the value is defined, if known.
+    pub debug_loc: Option<RemarkDebugLoc>,
+}
+
+/// An optimization remark.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Remark {
+    /// Whether the optimization was applied, missed and so on.
+    pub kind: RemarkKind,
+    /// The name of the pass which emitted this remark, such as `inline`.
+    pub pass: String,
+    /// An identifier for this remark within the pass, such as `NoDefinition`.
+    pub name: String,
+    /// The name of the function this remark is about.
+    pub function: String,
+    /// Where in the source this remark is about, if known.
+    pub debug_loc: Option<RemarkDebugLoc>,
+    /// How hot the code this remark is about is, if profile data was available.
+    pub hotness: Option<u64>,
+    /// The parts of this remark's message.
+    pub args: Vec<RemarkArg>,
+}
+
+impl Remark {
+    unsafe fn new(entry: LLVMRemarkEntryRef) -> Self {
+        let mut args = Vec::new();
+        let mut arg = LLVMRemarkEntryGetFirstArg(entry);
+
+        while !arg.is_null() {
+            args.push(RemarkArg {
+                key: remark_string(LLVMRemarkArgGetKey(arg)),
+                value: remark_string(LLVMRemarkArgGetValue(arg)),
+                debug_loc: RemarkDebugLoc::new(LLVMRemarkArgGetDebugLoc(arg)),
+            });
+
+            arg = LLVMRemarkEntryGetNextArg(arg, entry);
+        }
+
+        let hotness = LLVMRemarkEntryGetHotness(entry);
+
+        Remark {
+            kind: LLVMRemarkEntryGetType(entry).into(),
+            pass: remark_string(LLVMRemarkEntryGetPassName(entry)),
+            name: remark_string(LLVMRemarkEntryGetRemarkName(entry)),
+            function: remark_string(LLVMRemarkEntryGetFunctionName(entry)),
+            debug_loc: RemarkDebugLoc::new(LLVMRemarkEntryGetDebugLoc(entry)),
+            // The C API reports missing hotness as zero
+            hotness: if hotness == 0 { None } else { Some(hotness) },
+            args,
+        }
+    }
+
+    /// Parses remarks serialized as YAML, as written by
+    /// `opt -pass-remarks-output`.
+    pub fn parse_yaml(buffer: &[u8]) -> Result<Vec<Self>, LLVMString> {
+        // Files without remarks are empty, which LLVM's parser rejects
+        if buffer.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Vec::new());
+        }
+
+        let mut remarks =
+            unsafe { parse_remarks(LLVMRemarkParserCreateYAML(buffer.as_ptr().cast(), buffer.len() as u64))? };
+
+        for remark in &mut remarks {
+            remark.unescape_yaml_quotes();
+        }
+
+        Ok(remarks)
+    }
+
+    /// Parses remarks serialized in LLVM's bitstream format, as written by
+    /// `opt -pass-remarks-output -pass-remarks-format=bitstream`.
+    #[llvm_versions(10.0..=latest)]
+    pub fn parse_bitstream(buffer: &[u8]) -> Result<Vec<Self>, LLVMString> {
+        use llvm_sys::remarks::LLVMRemarkParserCreateBitstream;
+
+        unsafe {
+            parse_remarks(LLVMRemarkParserCreateBitstream(
+                buffer.as_ptr().cast(),
+                buffer.len() as u64,
+            ))
+        }
+    }
+
+    // LLVM's YAML parser strips the quotes around single quoted strings, but
+    // leaves the quotes escaped inside them doubled
+    fn unescape_yaml_quotes(&mut self) {
+        let unescape = |string: &mut String| *string = string.replace("''", "'");
+        let debug_locs = self
+            .debug_loc
+            .iter_mut()
+            .chain(self.args.iter_mut().filter_map(|arg| arg.debug_loc.as_mut()));
+
+        for debug_loc in debug_locs {
+            unescape(&mut debug_loc.file);
+        }
+
+        unescape(&mut self.pass);
+        unescape(&mut self.name);
+        unescape(&mut self.function);
+
+        for arg in &mut self.args {
+            unescape(&mut arg.key);
+            unescape(&mut arg.value);
+        }
+    }
+
+    /// Gets the message of this remark, which is made up of the values of its
+    /// arguments.
+    pub fn get_message(&self) -> String {
+        self.args.iter().map(|arg| arg.value.as_str()).collect()
+    }
+
+    /// Serializes this remark as a YAML document, in the format written by
+    /// `opt -pass-remarks-output`. Concatenated documents make up a remarks
+    /// file. Remarks of an `Unknown` kind can't be parsed back.
+    pub fn to_yaml(&self) -> String {
+        let mut yaml = format!("--- {}\n", self.kind.yaml_tag());
+
+        // Writing to a String can't fail
+        let _ = writeln!(yaml, "{:<17}{}", "Pass:", yaml_string(&self.pass));
+        let _ = writeln!(yaml, "{:<17}{}", "Name:", yaml_string(&self.name));
+
+        if let Some(debug_loc) = &self.debug_loc {
+            let _ = writeln!(yaml, "{:<17}{}", "DebugLoc:", debug_loc.to_yaml());
+        }
+
+        let _ = writeln!(yaml, "{:<17}{}", "Function:", yaml_string(&self.function));
+
+        if let Some(hotness) = self.hotness {
+            let _ = writeln!(yaml, "{:<17}{}", "Hotness:", hotness);
+        }
+
+        if !self.args.is_empty() {
+            yaml.push_str("Args:\n");
+
+            for arg in &self.args {
+                let _ = writeln!(yaml, "  - {:<17}{}", format!("{}:", arg.key), yaml_string(&arg.value));
+
+                if let Some(debug_loc) = &arg.debug_loc {
+                    let _ = writeln!(yaml, "    {:<17}{}", "DebugLoc:", debug_loc.to_yaml());
+                }
+            }
+        }
+
+        yaml.push_str("...\n");
+        yaml
+    }
+}
+
+unsafe fn parse_remarks(parser: LLVMRemarkParserRef) -> Result<Vec<Remark>, LLVMString> {
+    let mut remarks = Vec::new();
+
+    loop {
+        let entry = LLVMRemarkParserGetNext(parser);
+
+        if entry.is_null() {
+            break;
+        }
+
+        remarks.push(Remark::new(entry));
+
+        LLVMRemarkEntryDispose(entry);
+    }
+
+    let result = if LLVMRemarkParserHasError(parser) == 1 {
+        let message = CStr::from_ptr(LLVMRemarkParserGetErrorMessage(parser));
+
+        Err(LLVMString::create_from_c_str(message))
+    } else {
+        Ok(remarks)
+    };
+
+    LLVMRemarkParserDispose(parser);
+
+    result
+}
+
+// Remark strings aren't null terminated
+unsafe fn remark_string(string: LLVMRemarkStringRef) -> String {
+    if string.is_null() {
+        return String::new();
+    }
+
+    let data = LLVMRemarkStringGetData(string) as *const u8;
+    let bytes = std::slice::from_raw_parts(data, LLVMRemarkStringGetLen(string) as usize);
+
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// Single quoted YAML strings only need quotes escaped, by doubling them
+fn yaml_string(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "''"))
+}
@@ -0,0 +1,214 @@
+//! Sanitizer instrumentation, which checks memory accesses of generated code
+//! at run time to catch bugs such as buffer overflows, uses of uninitialized
+//! memory and data races.
+//!
+//! The address, memory and thread sanitizers report bugs through their
+//! runtimes from compiler-rt, which must be linked into the final binary, or
+//! already be loaded into the process when running sanitized code through an
+//! `ExecutionEngine`. For example, a Rust host built with
+//! `-Zsanitizer=address` provides the address sanitizer's runtime. The
+//! symbols a sanitized module needs are listed by `get_runtime_symbols`. The
+//! sanitizers also add module constructors which initialize their runtimes,
+//! so `ExecutionEngine::run_static_constructors` needs to be called before
+//! running sanitized code.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::passes::PassBuilderOptions;
+//! use inkwell::sanitizers::Sanitizers;
+//! use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+//! use inkwell::OptimizationLevel;
+//!
+//! Target::initialize_native(&InitializationConfig::default()).unwrap();
+//!
+//! let triple = TargetMachine::get_default_triple();
+//! let machine = Target::from_triple(&triple)
+//!     .unwrap()
+//!     .create_target_machine(&triple, "generic", "", OptimizationLevel::Default, RelocMode::Default, CodeModel::JITDefault)
+//!     .unwrap();
+//! let context = Context::create();
+//! let module = context.create_module("my_module");
+//!
+//! module.set_triple(&triple);
+//!
+//! let sanitizers = Sanitizers {
+//!     address: true,
+//!     ..Sanitizers::default()
+//! };
+//!
+//! sanitizers.run_passes(&module, &machine, PassBuilderOptions::create()).unwrap();
+//!
+//! for symbol in sanitizers.get_runtime_symbols(&module) {
+//!     println!("{}", symbol);
+//! }
+//! ```
+
+use llvm_sys::core::LLVMGetFirstUse;
+
+use crate::attributes::{Attribute, AttributeLoc};
+use crate::module::Module;
+use crate::passes::PassBuilderOptions;
+use crate::support::{to_c_str, LLVMString};
+use crate::targets::TargetMachine;
+use crate::values::{AsValueRef, FunctionValue};
+
+/// The sanitizers to instrument a module with.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Sanitizers {
+    /// Checks for out of bounds accesses and uses after free with the
+    /// address sanitizer (`sanitize_address`).
+    pub address: bool,
+    /// Checks for uses of uninitialized memory with the memory sanitizer
+    /// (`sanitize_memory`).
+    pub memory: bool,
+    /// Checks for data races with the thread sanitizer (`sanitize_thread`).
+    pub thread: bool,
+    /// Traps on accesses outside of objects whose size is known, without
+    /// needing a runtime (`bounds-checking`).
+    pub bounds_checking: bool,
+}
+
+impl Sanitizers {
+    /// Checks that the enabled sanitizers can be used together. The address,
+    /// memory and thread sanitizers each need their own runtime, so only one
+    /// of them can be enabled.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let runtimes = [self.address, self.memory, self.thread];
+
+        if runtimes.iter().filter(|enabled| **enabled).count() > 1 {
+            return Err("Only one of the address, memory and thread sanitizers can be enabled.");
+        }
+
+        Ok(())
+    }
+
+    fn get_attribute_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        if self.address {
+            names.push("sanitize_address");
+        }
+
+        if self.memory {
+            names.push("sanitize_memory");
+        }
+
+        if self.thread {
+            names.push("sanitize_thread");
+        }
+
+        names
+    }
+
+    /// Marks a function to be instrumented by the enabled sanitizers.
+    pub fn add_function_attributes(&self, function: FunctionValue) {
+        let context = function.get_type().get_context();
+
+        for name in self.get_attribute_names() {
+            let kind_id = Attribute::get_named_enum_kind_id(name);
+
+            function.add_attribute(AttributeLoc::Function, context.create_enum_attribute(kind_id, 0));
+        }
+    }
+
+    /// Marks every function defined in a module to be instrumented by the
+    /// enabled sanitizers, except those with the
+    /// `disable_sanitizer_instrumentation` attribute.
+    pub fn add_attributes(&self, module: &Module) {
+        let disable_kind_id = Attribute::get_named_enum_kind_id("disable_sanitizer_instrumentation");
+
+        for function in module.get_functions() {
+            let is_declaration = function.count_basic_blocks() == 0;
+            let is_disabled = function
+                .get_enum_attribute(AttributeLoc::Function, disable_kind_id)
+                .is_some();
+
+            if !is_declaration && !is_disabled {
+                self.add_function_attributes(function);
+            }
+        }
+    }
+
+    /// Gets the passes which instrument a module for the enabled sanitizers,
+    /// in the textual pipeline format. Only functions with the sanitizers'
+    /// attributes are instrumented.
+    pub fn get_passes(&self) -> String {
+        let mut passes = Vec::new();
+
+        if self.memory {
+            #[cfg(any(feature = "llvm14-0", feature = "llvm15-0"))]
+            passes.push("msan-module,function(msan)");
+            #[cfg(not(any(feature = "llvm14-0", feature = "llvm15-0")))]
+            passes.push("msan");
+        }
+
+        if self.thread {
+            passes.push("tsan-module,function(tsan)");
+        }
+
+        if self.address {
+            // Before LLVM 15, the module pass also instruments functions
+            #[cfg(feature = "llvm14-0")]
+            passes.push("require<asan-globals-md>,asan-module");
+            #[cfg(not(feature = "llvm14-0"))]
+            passes.push("asan");
+        }
+
+        if self.bounds_checking {
+            passes.push("function(bounds-checking)");
+        }
+
+        passes.join(",")
+    }
+
+    /// Adds the sanitizers' attributes to every function defined in a module
+    /// and runs their instrumentation passes over it.
+    pub fn run_passes(
+        &self,
+        module: &Module,
+        machine: &TargetMachine,
+        options: PassBuilderOptions,
+    ) -> Result<(), LLVMString> {
+        if let Err(message) = self.validate() {
+            return Err(LLVMString::create_from_c_str(&to_c_str(message)));
+        }
+
+        let passes = self.get_passes();
+
+        if passes.is_empty() {
+            return Ok(());
+        }
+
+        self.add_attributes(module);
+
+        module.run_passes(&passes, machine, options)
+    }
+
+    /// Gets the names of the runtime functions and variables an instrumented
+    /// module uses, which must be linked in or mapped into an
+    /// `ExecutionEngine`.
+    pub fn get_runtime_symbols(&self, module: &Module) -> Vec<String> {
+        let mut prefixes = Vec::new();
+
+        if self.address {
+            prefixes.push("__asan_");
+        }
+
+        if self.memory {
+            prefixes.push("__msan_");
+        }
+
+        if self.thread {
+            prefixes.push("__tsan_");
+        }
+
+        let functions = module.get_functions().map(FunctionValue::as_global_value);
+        let globals = functions.chain(module.get_globals());
+
+        globals
+            .filter(|global| global.is_declaration() && unsafe { !LLVMGetFirstUse(global.as_value_ref()).is_null() })
+            .map(|global| global.get_name().to_string_lossy().into_owned())
+            .filter(|name| prefixes.iter().any(|prefix| name.starts_with(prefix)))
+            .collect()
+    }
+}
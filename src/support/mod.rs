@@ -7,6 +7,8 @@ use llvm_sys::core::LLVMGetVersion;
 use llvm_sys::core::{LLVMCreateMessage, LLVMDisposeMessage};
 use llvm_sys::error_handling::LLVMEnablePrettyStackTrace;
 use llvm_sys::support::LLVMLoadLibraryPermanently;
+#[llvm_versions(13.0..=latest)]
+use llvm_sys::support::LLVMParseCommandLineOptions;
 
 use std::borrow::Cow;
 use std::error::Error;
@@ -155,6 +157,18 @@ pub fn enable_llvm_pretty_stack_trace() {
     unsafe { LLVMEnablePrettyStackTrace() }
 }
 
+// Sets LLVM's process wide command line options, such as `-time-passes`
+#[llvm_versions(13.0..=latest)]
+pub(crate) fn parse_command_line_options(options: &[String]) {
+    let program = to_c_str("inkwell");
+    let options: Vec<_> = options.iter().map(|option| to_c_str(option)).collect();
+    let args: Vec<*const c_char> = std::iter::once(program.as_ptr())
+        .chain(options.iter().map(|option| option.as_ptr()))
+        .collect();
+
+    unsafe { LLVMParseCommandLineOptions(args.len() as i32, args.as_ptr(), std::ptr::null()) }
+}
+
 /// This function takes in a Rust string and either:
 ///
 /// A) Finds a terminating null byte in the Rust string and can reference it directly like a C string.
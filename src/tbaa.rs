@@ -0,0 +1,148 @@
+//! Type-based alias analysis (TBAA) metadata, which tells LLVM that memory
+//! accesses through unrelated types never alias.
+//!
+//! TBAA type nodes form a tree under a root: two accesses may alias only if
+//! one's type is an ancestor of (or the same as) the other's. Each load or
+//! store is tagged with an access tag describing the type it accesses and,
+//! for struct fields, the enclosing struct type and the field's offset.
+//!
+//! ```no_run
+//! use inkwell::context::Context;
+//! use inkwell::tbaa::TbaaBuilder;
+//! use inkwell::AddressSpace;
+//!
+//! let context = Context::create();
+//! let module = context.create_module("tbaa");
+//! let builder = context.create_builder();
+//! let tbaa = TbaaBuilder::new(&context, "Simple C/C++ TBAA");
+//! let char_type = tbaa.create_scalar_type("omnipotent char", None);
+//! let int_type = tbaa.create_scalar_type("int", Some(char_type));
+//! let float_type = tbaa.create_scalar_type("float", Some(char_type));
+//!
+//! let i32_type = context.i32_type();
+//! let f32_type = context.f32_type();
+//! let fn_type = context.void_type().fn_type(
+//!     &[i32_type.ptr_type(AddressSpace::default()).into(), f32_type.ptr_type(AddressSpace::default()).into()],
+//!     false,
+//! );
+//! let function = module.add_function("store_both", fn_type, None);
+//! let entry = context.append_basic_block(function, "entry");
+//!
+//! builder.position_at_end(entry);
+//!
+//! let int_store = builder.build_store(function.get_nth_param(0).unwrap().into_pointer_value(), i32_type.const_zero());
+//! let float_store = builder.build_store(function.get_nth_param(1).unwrap().into_pointer_value(), f32_type.const_zero());
+//!
+//! builder.build_return(None);
+//!
+//! tbaa.set_access_tag(int_store, tbaa.create_scalar_access_tag(int_type)).unwrap();
+//! tbaa.set_access_tag(float_store, tbaa.create_scalar_access_tag(float_type)).unwrap();
+//! ```
+
+use crate::context::{AsContextRef, ContextRef};
+use crate::values::{BasicMetadataValueEnum, InstructionOpcode, InstructionValue, MetadataValue};
+
+/// Creates TBAA type nodes and access tags under a single root, and attaches
+/// access tags to memory instructions.
+#[derive(Debug)]
+pub struct TbaaBuilder<'ctx> {
+    context: ContextRef<'ctx>,
+    root: MetadataValue<'ctx>,
+    kind_id: u32,
+}
+
+impl<'ctx> TbaaBuilder<'ctx> {
+    /// Creates a `TbaaBuilder` with a new root named `root_name`. Type trees
+    /// with different roots are unrelated, so accesses tagged under one root
+    /// are assumed to possibly alias those tagged under another.
+    pub fn new(context: impl AsContextRef<'ctx>, root_name: &str) -> Self {
+        let context = unsafe { ContextRef::new(context.as_ctx_ref()) };
+        let root = context.metadata_node(&[context.metadata_string(root_name).into()]);
+        let kind_id = context.get_kind_id("tbaa");
+
+        TbaaBuilder { context, root, kind_id }
+    }
+
+    /// Gets the root node of this `TbaaBuilder`'s type tree.
+    pub fn get_root(&self) -> MetadataValue<'ctx> {
+        self.root
+    }
+
+    /// Gets the metadata kind id of `!tbaa` attachments.
+    pub fn get_kind_id(&self) -> u32 {
+        self.kind_id
+    }
+
+    /// Creates a scalar type node named `name`, such as `"int"` or `"any
+    /// pointer"`. Its parent is `parent`, or the root if `None`; accesses to
+    /// the parent type may alias accesses to this type.
+    pub fn create_scalar_type(&self, name: &str, parent: Option<MetadataValue<'ctx>>) -> MetadataValue<'ctx> {
+        let parent = parent.unwrap_or(self.root);
+
+        self.context
+            .metadata_node(&[self.context.metadata_string(name).into(), parent.into(), self.offset(0)])
+    }
+
+    /// Creates a struct type node named `name`, made up of the given
+    /// `(field type, byte offset)` fields. Field types may be scalar or struct
+    /// type nodes, and should be ordered by offset.
+    pub fn create_struct_type(&self, name: &str, fields: &[(MetadataValue<'ctx>, u64)]) -> MetadataValue<'ctx> {
+        let mut operands = Vec::with_capacity(1 + 2 * fields.len());
+
+        operands.push(self.context.metadata_string(name).into());
+
+        for &(field_type, offset) in fields {
+            operands.push(field_type.into());
+            operands.push(self.offset(offset));
+        }
+
+        self.context.metadata_node(&operands)
+    }
+
+    /// Creates an access tag for an access of type `access_type` at byte
+    /// `offset` within `base_type`, such as a struct's field. If `is_constant`
+    /// is set, the accessed memory is known to never change.
+    pub fn create_access_tag(
+        &self,
+        base_type: MetadataValue<'ctx>,
+        access_type: MetadataValue<'ctx>,
+        offset: u64,
+        is_constant: bool,
+    ) -> MetadataValue<'ctx> {
+        let mut operands = vec![base_type.into(), access_type.into(), self.offset(offset)];
+
+        if is_constant {
+            operands.push(self.offset(1));
+        }
+
+        self.context.metadata_node(&operands)
+    }
+
+    /// Creates an access tag for a direct (non field) access of `scalar_type`.
+    pub fn create_scalar_access_tag(&self, scalar_type: MetadataValue<'ctx>) -> MetadataValue<'ctx> {
+        self.create_access_tag(scalar_type, scalar_type, 0, false)
+    }
+
+    /// Attaches the access tag `tag` to `instruction` as `!tbaa` metadata.
+    /// `instruction` should be a load, store, atomic or call to a memory
+    /// intrinsic like `llvm.memcpy`. Returns an error if it is something else
+    /// or `tag` is not a node.
+    pub fn set_access_tag(
+        &self,
+        instruction: InstructionValue<'ctx>,
+        tag: MetadataValue<'ctx>,
+    ) -> Result<(), &'static str> {
+        match instruction.get_opcode() {
+            InstructionOpcode::Load
+            | InstructionOpcode::Store
+            | InstructionOpcode::AtomicRMW
+            | InstructionOpcode::AtomicCmpXchg
+            | InstructionOpcode::Call => instruction.set_metadata(tag, self.kind_id),
+            _ => Err("TBAA access tags can only be attached to memory accesses."),
+        }
+    }
+
+    fn offset(&self, offset: u64) -> BasicMetadataValueEnum<'ctx> {
+        self.context.i64_type().const_int(offset, false).into()
+    }
+}
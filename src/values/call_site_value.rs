@@ -2,14 +2,16 @@ use std::fmt::{self, Display};
 
 use either::Either;
 use llvm_sys::core::{
-    LLVMGetInstructionCallConv, LLVMGetTypeKind, LLVMIsTailCall, LLVMSetInstrParamAlignment,
-    LLVMSetInstructionCallConv, LLVMSetTailCall, LLVMTypeOf,
+    LLVMGetCalledValue, LLVMGetInstructionCallConv, LLVMGetTypeContext, LLVMGetTypeKind, LLVMIsAFunction,
+    LLVMIsTailCall, LLVMSetInstrParamAlignment, LLVMSetInstructionCallConv, LLVMSetTailCall, LLVMTypeOf,
 };
 use llvm_sys::prelude::LLVMValueRef;
 use llvm_sys::LLVMTypeKind;
 
 use crate::attributes::{Attribute, AttributeLoc};
-use crate::values::{AsValueRef, BasicValueEnum, FunctionValue, InstructionValue, Value};
+use crate::context::ContextRef;
+use crate::values::{AsValueRef, BasicMetadataValueEnum, BasicValueEnum, FunctionValue, InstructionValue, Value};
+use crate::CallingConvention;
 
 use super::AnyValue;
 
@@ -161,8 +163,6 @@ impl<'ctx> CallSiteValue<'ctx> {
     /// assert_eq!(call_site_value.get_called_fn_value(), fn_value);
     /// ```
     pub fn get_called_fn_value(self) -> FunctionValue<'ctx> {
-        use llvm_sys::core::LLVMGetCalledValue;
-
         unsafe { FunctionValue::new(LLVMGetCalledValue(self.as_value_ref())).expect("This should never be null?") }
     }
 
@@ -501,6 +501,26 @@ impl<'ctx> CallSiteValue<'ctx> {
         unsafe { LLVMSetInstructionCallConv(self.as_value_ref(), conv) }
     }
 
+    /// Gets the `CallingConvention` of this `CallSiteValue`.
+    pub fn get_calling_convention(self) -> CallingConvention {
+        self.get_call_convention().into()
+    }
+
+    /// Sets the `CallingConvention` of this `CallSiteValue`. It must match
+    /// the calling convention of the called function, which `Builder` takes
+    /// care of for direct calls.
+    pub fn set_calling_convention(self, calling_convention: CallingConvention) {
+        self.set_call_convention(calling_convention.into())
+    }
+
+    // Calls with a different calling convention than their callee are UB,
+    // which the verifier doesn't catch.
+    pub(crate) fn inherit_calling_convention(self) {
+        if self.is_direct() {
+            self.set_call_convention(self.get_called_fn_value().get_call_conventions());
+        }
+    }
+
     /// Shortcut for setting the alignment `Attribute` for this `CallSiteValue`.
     ///
     /// # Panics
@@ -532,6 +552,68 @@ impl<'ctx> CallSiteValue<'ctx> {
 
         unsafe { LLVMSetInstrParamAlignment(self.as_value_ref(), loc.get_index(), alignment) }
     }
+
+    /// Attaches `!callees` metadata to this indirect call, promising that it
+    /// only ever calls one of `callees`. Returns an error if this is a direct
+    /// call or `callees` is empty.
+    pub fn set_callees(self, callees: &[FunctionValue<'ctx>]) -> Result<(), &'static str> {
+        if self.is_direct() {
+            return Err("Callees metadata can only be attached to indirect calls.");
+        }
+
+        if callees.is_empty() {
+            return Err("At least one callee is required.");
+        }
+
+        let context = self.get_context();
+        let callees: Vec<BasicMetadataValueEnum> = callees
+            .iter()
+            .map(|callee| callee.as_global_value().as_pointer_value().into())
+            .collect();
+
+        self.as_instruction()
+            .set_metadata(context.metadata_node(&callees), context.get_kind_id("callees"))
+    }
+
+    /// Attaches indirect call value profile metadata (`!prof !{!"VP", ...}`)
+    /// to this indirect call. `total_count` is the number of times the call
+    /// was made, and `targets` holds `(target hash, count)` pairs for its most
+    /// frequent targets, where a target's hash is the lower 64 bits of the MD5
+    /// hash of its PGO function name. Returns an error if this is a direct call.
+    pub fn set_value_profile(self, total_count: u64, targets: &[(u64, u64)]) -> Result<(), &'static str> {
+        if self.is_direct() {
+            return Err("Value profiles can only be attached to indirect calls.");
+        }
+
+        let context = self.get_context();
+        let i64_type = context.i64_type();
+        // The zero identifies this as an indirect call target profile.
+        let mut operands = vec![
+            context.metadata_string("VP").into(),
+            context.i32_type().const_zero().into(),
+            i64_type.const_int(total_count, false).into(),
+        ];
+
+        for &(hash, count) in targets {
+            operands.push(i64_type.const_int(hash, false).into());
+            operands.push(i64_type.const_int(count, false).into());
+        }
+
+        self.as_instruction()
+            .set_metadata(context.metadata_node(&operands), context.get_kind_id("prof"))
+    }
+
+    fn is_direct(self) -> bool {
+        unsafe { !LLVMIsAFunction(LLVMGetCalledValue(self.as_value_ref())).is_null() }
+    }
+
+    fn get_context(self) -> ContextRef<'ctx> {
+        unsafe { ContextRef::new(LLVMGetTypeContext(LLVMTypeOf(self.as_value_ref()))) }
+    }
+
+    fn as_instruction(self) -> InstructionValue<'ctx> {
+        unsafe { InstructionValue::new(self.as_value_ref()) }
+    }
 }
 
 unsafe impl AsValueRef for CallSiteValue<'_> {
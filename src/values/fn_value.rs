@@ -1,19 +1,45 @@
 use llvm_sys::analysis::{LLVMVerifierFailureAction, LLVMVerifyFunction, LLVMViewFunctionCFG, LLVMViewFunctionCFGOnly};
+#[llvm_versions(13.0..=latest)]
+use llvm_sys::core::LLVMGetFirstFunction;
 use llvm_sys::core::{
     LLVMAddAttributeAtIndex, LLVMGetAttributeCountAtIndex, LLVMGetEnumAttributeAtIndex, LLVMGetStringAttributeAtIndex,
     LLVMRemoveEnumAttributeAtIndex, LLVMRemoveStringAttributeAtIndex,
 };
+use llvm_sys::core::{
+    LLVMAddFunction, LLVMAddGlobalInAddressSpace, LLVMBasicBlockAsValue, LLVMGetGlobalParent, LLVMGetMDNodeNumOperands,
+    LLVMGetMDNodeOperands, LLVMGetNamedFunction, LLVMGetNamedGlobal, LLVMGetOperand, LLVMGetPointerAddressSpace,
+    LLVMGetTypeContext, LLVMIsAArgument, LLVMIsAGlobalValue, LLVMIsAGlobalVariable, LLVMIsAInstruction, LLVMIsAMDNode,
+    LLVMIsGlobalConstant, LLVMIsThreadLocal, LLVMSetGlobalConstant, LLVMSetOperand, LLVMSetThreadLocal, LLVMTypeOf,
+    LLVMValueAsBasicBlock,
+};
+use llvm_sys::core::{
+    LLVMConstAdd, LLVMConstAddrSpaceCast, LLVMConstArray, LLVMConstBitCast, LLVMConstIntToPtr, LLVMConstNSWAdd,
+    LLVMConstNSWSub, LLVMConstNUWAdd, LLVMConstNUWSub, LLVMConstNamedStruct, LLVMConstPtrToInt, LLVMConstSExt,
+    LLVMConstStructInContext, LLVMConstSub, LLVMConstTrunc, LLVMConstVector, LLVMConstZExt, LLVMGetConstOpcode,
+    LLVMGetElementType, LLVMGetNumOperands, LLVMGetStructName, LLVMGetTypeKind, LLVMIsAConstantArray,
+    LLVMIsAConstantExpr, LLVMIsAConstantStruct, LLVMIsAConstantVector, LLVMIsPackedStruct,
+};
 use llvm_sys::core::{
     LLVMCountBasicBlocks, LLVMCountParams, LLVMDeleteFunction, LLVMGetBasicBlocks, LLVMGetFirstBasicBlock,
     LLVMGetFirstParam, LLVMGetFunctionCallConv, LLVMGetGC, LLVMGetIntrinsicID, LLVMGetLastBasicBlock, LLVMGetLastParam,
     LLVMGetLinkage, LLVMGetNextFunction, LLVMGetNextParam, LLVMGetParam, LLVMGetParams, LLVMGetPreviousFunction,
     LLVMIsAFunction, LLVMIsConstant, LLVMSetFunctionCallConv, LLVMSetGC, LLVMSetLinkage, LLVMSetParamAlignment,
 };
-use llvm_sys::core::{LLVMGetPersonalityFn, LLVMSetPersonalityFn};
+use llvm_sys::core::{
+    LLVMGetCalledValue, LLVMGetFirstUse, LLVMGetNextUse, LLVMGetPersonalityFn, LLVMGetUser, LLVMIsACallInst,
+    LLVMIsAInvokeInst, LLVMSetInstructionCallConv, LLVMSetPersonalityFn,
+};
 #[llvm_versions(7.0..=latest)]
 use llvm_sys::debuginfo::{LLVMGetSubprogram, LLVMSetSubprogram};
-use llvm_sys::prelude::{LLVMBasicBlockRef, LLVMValueRef};
-
+#[llvm_versions(13.0..=latest)]
+use llvm_sys::error::LLVMGetErrorMessage;
+use llvm_sys::prelude::{LLVMBasicBlockRef, LLVMModuleRef, LLVMValueRef};
+#[llvm_versions(13.0..=latest)]
+use llvm_sys::transforms::pass_builder::LLVMRunPasses;
+use llvm_sys::{LLVMOpcode, LLVMTypeKind};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::CStr;
 use std::fmt::{self, Display};
 use std::marker::PhantomData;
@@ -23,11 +49,20 @@ use crate::attributes::{Attribute, AttributeLoc};
 use crate::basic_block::BasicBlock;
 #[llvm_versions(7.0..=latest)]
 use crate::debug_info::DISubprogram;
-use crate::module::Linkage;
+use crate::module::{Linkage, Module};
+#[llvm_versions(13.0..=latest)]
+use crate::passes::PassBuilderOptions;
+#[llvm_versions(13.0..=latest)]
+use crate::pipeline::validate_function_passes;
 use crate::support::to_c_str;
-use crate::types::FunctionType;
+#[llvm_versions(13.0..=latest)]
+use crate::support::LLVMString;
+#[llvm_versions(13.0..=latest)]
+use crate::targets::TargetMachine;
+use crate::types::{AsTypeRef, BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType};
 use crate::values::traits::{AnyValue, AsValueRef};
-use crate::values::{BasicValueEnum, GlobalValue, Value};
+use crate::values::{BasicValue, BasicValueEnum, GlobalValue, PhiValue, Value};
+use crate::CallingConvention;
 
 #[derive(PartialEq, Eq, Clone, Copy, Hash)]
 pub struct FunctionValue<'ctx> {
@@ -80,6 +115,120 @@ impl<'ctx> FunctionValue<'ctx> {
         code != 1
     }
 
+    /// Construct and run a set of function passes over only this function, optimizing it in place.
+    /// The format of `passes` is the same as opt's -passes argument, as for `Module::run_passes`,
+    /// except that it may only contain function and loop passes since it is run as `function(passes)`.
+    /// `passes` is checked as by `Pipeline::validate` first, so that it can't close the wrapping
+    /// adaptor to run passes over the whole module.
+    ///
+    /// Every other function defined in the module is temporarily marked `optnone` so that the
+    /// pass manager skips it, leaving already optimized functions alone.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::passes::PassBuilderOptions;
+    /// use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+    /// use inkwell::OptimizationLevel;
+    ///
+    /// Target::initialize_native(&InitializationConfig::default()).unwrap();
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("hot_fn", fn_type, None);
+    /// let triple = TargetMachine::get_default_triple();
+    /// let target = Target::from_triple(&triple).unwrap();
+    /// let machine = target
+    ///     .create_target_machine(
+    ///         &triple,
+    ///         "generic",
+    ///         "",
+    ///         OptimizationLevel::Aggressive,
+    ///         RelocMode::Default,
+    ///         CodeModel::JITDefault,
+    ///     )
+    ///     .unwrap();
+    ///
+    /// fn_value
+    ///     .run_passes("instcombine,simplifycfg,loop-mssa(licm)", &machine, PassBuilderOptions::create())
+    ///     .unwrap();
+    /// ```
+    #[llvm_versions(13.0..=latest)]
+    pub fn run_passes(
+        self,
+        passes: &str,
+        machine: &TargetMachine,
+        options: PassBuilderOptions,
+    ) -> Result<(), LLVMString> {
+        if let Err(error) = validate_function_passes(passes) {
+            return Err(LLVMString::create_from_c_str(&to_c_str(&error.to_string())));
+        }
+
+        let context = self.get_type().get_context();
+        let optnone_kind_id = Attribute::get_named_enum_kind_id("optnone");
+        let noinline_kind_id = Attribute::get_named_enum_kind_id("noinline");
+        let module = unsafe { LLVMGetGlobalParent(self.as_value_ref()) };
+        let mut next_function = unsafe { FunctionValue::new(LLVMGetFirstFunction(module)) };
+        let mut skipped_functions = Vec::new();
+
+        while let Some(function) = next_function {
+            next_function = function.get_next_function();
+
+            let is_declaration = function.count_basic_blocks() == 0;
+            let is_optnone = function
+                .get_enum_attribute(AttributeLoc::Function, optnone_kind_id)
+                .is_some();
+
+            if function == self || is_declaration || is_optnone {
+                continue;
+            }
+
+            // The verifier requires optnone functions to also be noinline
+            let add_noinline = function
+                .get_enum_attribute(AttributeLoc::Function, noinline_kind_id)
+                .is_none();
+
+            function.add_attribute(
+                AttributeLoc::Function,
+                context.create_enum_attribute(optnone_kind_id, 0),
+            );
+
+            if add_noinline {
+                function.add_attribute(
+                    AttributeLoc::Function,
+                    context.create_enum_attribute(noinline_kind_id, 0),
+                );
+            }
+
+            skipped_functions.push((function, add_noinline));
+        }
+
+        let error = unsafe {
+            LLVMRunPasses(
+                module,
+                to_c_str(&format!("function({})", passes)).as_ptr(),
+                machine.target_machine,
+                options.options_ref,
+            )
+        };
+
+        for (function, added_noinline) in skipped_functions {
+            function.remove_enum_attribute(AttributeLoc::Function, optnone_kind_id);
+
+            if added_noinline {
+                function.remove_enum_attribute(AttributeLoc::Function, noinline_kind_id);
+            }
+        }
+
+        if error.is_null() {
+            Ok(())
+        } else {
+            unsafe { Err(LLVMString::new(LLVMGetErrorMessage(error) as *const libc::c_char)) }
+        }
+    }
+
     // REVIEW: If there's a demand, could easily create a module.get_functions() -> Iterator
     pub fn get_next_function(self) -> Option<Self> {
         unsafe { FunctionValue::new(LLVMGetNextFunction(self.as_value_ref())) }
@@ -198,6 +347,58 @@ impl<'ctx> FunctionValue<'ctx> {
         LLVMDeleteFunction(self.as_value_ref())
     }
 
+    /// Deletes this `FunctionValue` from its module, as long as nothing uses
+    /// it anymore. Returns an error and leaves the module unchanged if it
+    /// is still called or otherwise referenced.
+    ///
+    /// # Safety
+    /// Like `delete`, this frees the function, so `self` and any copies of it,
+    /// such as its parameters or basic blocks, must not be used after a
+    /// successful deletion.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    ///
+    /// assert!(unsafe { fn_value.try_delete() }.is_ok());
+    /// assert!(module.get_function("my_fn").is_none());
+    /// ```
+    pub unsafe fn try_delete(self) -> Result<(), &'static str> {
+        if self.fn_value.get_first_use().is_some() {
+            return Err("Function still has uses.");
+        }
+
+        LLVMDeleteFunction(self.as_value_ref());
+
+        Ok(())
+    }
+
+    /// Replaces all uses of this `FunctionValue` with `other`, such as calls
+    /// to it, then deletes it as with `try_delete`. Returns an error and leaves
+    /// the module unchanged if `other` is this function or has a different type.
+    ///
+    /// # Safety
+    /// As with `try_delete`, `self` and any copies of it must not be used after
+    /// a successful deletion.
+    pub unsafe fn replace_all_uses_and_delete(self, other: FunctionValue<'ctx>) -> Result<(), &'static str> {
+        if self == other {
+            return Err("Cannot replace a function with itself.");
+        }
+
+        if self.get_type() != other.get_type() {
+            return Err("Replacement function has a different type.");
+        }
+
+        self.replace_all_uses_with(other);
+        self.try_delete()
+    }
+
     #[llvm_versions(4.0..=7.0)]
     pub fn get_type(self) -> FunctionType<'ctx> {
         use crate::types::PointerType;
@@ -244,6 +445,57 @@ impl<'ctx> FunctionValue<'ctx> {
         unsafe { LLVMSetFunctionCallConv(self.as_value_ref(), call_conventions) }
     }
 
+    /// Gets the `CallingConvention` of this function.
+    pub fn get_calling_convention(self) -> CallingConvention {
+        self.get_call_conventions().into()
+    }
+
+    /// Sets the `CallingConvention` of this function, and of every existing
+    /// direct call to it so that they keep matching.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::CallingConvention;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let builder = context.create_builder();
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    /// let entry = context.append_basic_block(fn_value, "entry");
+    ///
+    /// builder.position_at_end(entry);
+    ///
+    /// let call_site_value = builder.build_call(fn_value, &[], "call");
+    ///
+    /// fn_value.set_calling_convention(CallingConvention::Fast);
+    ///
+    /// assert_eq!(call_site_value.get_calling_convention(), CallingConvention::Fast);
+    /// ```
+    pub fn set_calling_convention(self, calling_convention: CallingConvention) {
+        let call_conventions = calling_convention.into();
+
+        self.set_call_conventions(call_conventions);
+
+        let mut next_use = unsafe { LLVMGetFirstUse(self.as_value_ref()) };
+
+        while !next_use.is_null() {
+            unsafe {
+                let user = LLVMGetUser(next_use);
+                let is_call = !LLVMIsACallInst(user).is_null() || !LLVMIsAInvokeInst(user).is_null();
+
+                // Only update calls to this function, not calls passing it as an argument
+                if is_call && LLVMGetCalledValue(user) == self.as_value_ref() {
+                    LLVMSetInstructionCallConv(user, call_conventions);
+                }
+
+                next_use = LLVMGetNextUse(next_use);
+            }
+        }
+    }
+
     pub fn get_gc(&self) -> &CStr {
         unsafe { CStr::from_ptr(LLVMGetGC(self.as_value_ref())) }
     }
@@ -258,6 +510,377 @@ impl<'ctx> FunctionValue<'ctx> {
         self.fn_value.replace_all_uses_with(other.as_value_ref())
     }
 
+    /// Creates a deep copy of this `FunctionValue` named `name` in `module`,
+    /// which may be the module this function belongs to or another one in
+    /// the same `Context`.
+    ///
+    /// Basic blocks, instructions (including their metadata), phis,
+    /// attributes and function level metadata are copied, and every use of
+    /// the original's parameters, blocks and instructions is remapped to
+    /// their copies. Recursive calls call the copy. When cloning into a
+    /// different module, referenced functions and global variables are
+    /// redeclared there by name, and aliases and ifuncs are redeclared as the
+    /// function or global variable they stand for. Constant expressions and
+    /// aggregates referring to them, such as a GEP of a global, are rebuilt
+    /// over the redeclarations.
+    ///
+    /// The original's `DISubprogram` is not attached to the copy, since a
+    /// subprogram may only describe one function.
+    ///
+    /// Returns an error when cloning into a different module if a constant
+    /// expression other than a GEP, cast, `add` or `sub` refers to a global
+    /// value, or if such an `add` or `sub` is both `nsw` and `nuw`, which the
+    /// LLVM C API cannot rebuild. The partial copy is deleted, but
+    /// redeclarations already added to `module` are kept.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let builder = context.create_builder();
+    /// let i32_type = context.i32_type();
+    /// let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+    /// let fn_value = module.add_function("double", fn_type, None);
+    /// let entry = context.append_basic_block(fn_value, "entry");
+    /// let param = fn_value.get_first_param().unwrap().into_int_value();
+    ///
+    /// builder.position_at_end(entry);
+    /// builder.build_return(Some(&builder.build_int_add(param, param, "sum")));
+    ///
+    /// let other_module = context.create_module("other_mod");
+    /// let clone = fn_value.clone_into(&other_module, "double_clone").unwrap();
+    ///
+    /// assert_eq!(clone.count_basic_blocks(), 1);
+    /// assert_eq!(other_module.get_function("double_clone"), Some(clone));
+    /// assert!(other_module.verify().is_ok());
+    /// ```
+    pub fn clone_into(self, module: &Module<'ctx>, name: &str) -> Result<FunctionValue<'ctx>, &'static str> {
+        let clone = module.add_function(name, self.get_type(), Some(self.get_linkage()));
+        let param_indices: Vec<_> = (0..self.count_params()).map(Some).collect();
+
+        self.copy_properties_into(clone, &param_indices);
+
+        if let Err(err) = self.clone_body_into(clone, &clone.get_params(), true) {
+            unsafe { clone.delete() }
+
+            return Err(err);
+        }
+
+        Ok(clone)
+    }
+
+    /// Creates a copy of this `FunctionValue` named `name` in the same module,
+    /// with each `(param_index, constant)` parameter replaced by its constant.
+    /// The specialized parameters are removed from the copy's signature, and
+    /// the copy has internal linkage.
+    ///
+    /// The body is copied as with `clone_into`, except that recursive calls
+    /// still call the original function since its signature differs. Returns
+    /// an error if this function is a declaration, as there is no body to
+    /// specialize.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let builder = context.create_builder();
+    /// let i32_type = context.i32_type();
+    /// let fn_type = i32_type.fn_type(&[i32_type.into(), i32_type.into()], false);
+    /// let fn_value = module.add_function("add", fn_type, None);
+    /// let entry = context.append_basic_block(fn_value, "entry");
+    /// let lhs = fn_value.get_first_param().unwrap().into_int_value();
+    /// let rhs = fn_value.get_last_param().unwrap().into_int_value();
+    ///
+    /// builder.position_at_end(entry);
+    /// builder.build_return(Some(&builder.build_int_add(lhs, rhs, "sum")));
+    ///
+    /// let forty_two = i32_type.const_int(42, false);
+    /// let add_42 = fn_value.specialize("add_42", &[(1, forty_two.into())]).unwrap();
+    ///
+    /// assert_eq!(add_42.count_params(), 1);
+    /// assert!(module.verify().is_ok());
+    /// ```
+    pub fn specialize(
+        self,
+        name: &str,
+        constants: &[(u32, BasicValueEnum<'ctx>)],
+    ) -> Result<FunctionValue<'ctx>, &'static str> {
+        if self.count_basic_blocks() == 0 {
+            return Err("Cannot specialize a function declaration.");
+        }
+
+        let fn_type = self.get_type();
+        let param_types = fn_type.get_param_types();
+        let mut specialized: Vec<Option<BasicValueEnum<'ctx>>> = vec![None; param_types.len()];
+
+        for &(index, constant) in constants {
+            let slot = specialized
+                .get_mut(index as usize)
+                .ok_or("Parameter index is out of bounds.")?;
+
+            if slot.is_some() {
+                return Err("Parameter is specialized more than once.");
+            }
+            if !unsafe { Value::new(constant.as_value_ref()) }.is_const() {
+                return Err("Specialized parameter value must be a constant.");
+            }
+            if constant.get_type() != param_types[index as usize] {
+                return Err("Constant type does not match the parameter type.");
+            }
+
+            *slot = Some(constant);
+        }
+
+        let remaining_types: Vec<BasicMetadataTypeEnum> = param_types
+            .iter()
+            .zip(&specialized)
+            .filter(|(_, constant)| constant.is_none())
+            .map(|(ty, _)| (*ty).into())
+            .collect();
+        let specialized_type = match fn_type.get_return_type() {
+            Some(return_type) => return_type.fn_type(&remaining_types, fn_type.is_var_arg()),
+            None => fn_type
+                .get_context()
+                .void_type()
+                .fn_type(&remaining_types, fn_type.is_var_arg()),
+        };
+        let c_string = to_c_str(name);
+        let clone = unsafe {
+            let module = LLVMGetGlobalParent(self.as_value_ref());
+
+            FunctionValue::new(LLVMAddFunction(
+                module,
+                c_string.as_ptr(),
+                specialized_type.as_type_ref(),
+            ))
+            .expect("Function should always be created")
+        };
+
+        clone.set_linkage(Linkage::Internal);
+
+        let mut clone_params = clone.get_param_iter();
+        let mut next_index = 0;
+        let mut param_indices = Vec::with_capacity(specialized.len());
+        let mut replacements = Vec::with_capacity(specialized.len());
+
+        for constant in specialized {
+            match constant {
+                Some(constant) => {
+                    param_indices.push(None);
+                    replacements.push(constant);
+                },
+                None => {
+                    param_indices.push(Some(next_index));
+                    replacements.push(clone_params.next().expect("Clone should have a param left"));
+                    next_index += 1;
+                },
+            }
+        }
+
+        self.copy_properties_into(clone, &param_indices);
+
+        if let Err(err) = self.clone_body_into(clone, &replacements, false) {
+            unsafe { clone.delete() }
+
+            return Err(err);
+        }
+
+        Ok(clone)
+    }
+
+    // Copies attributes, metadata and other function properties. The param
+    // attributes of the original's nth param go to the clone's
+    // `param_indices[n]`th param, if any.
+    fn copy_properties_into(self, clone: FunctionValue<'ctx>, param_indices: &[Option<u32>]) {
+        for loc in [AttributeLoc::Function, AttributeLoc::Return] {
+            for attribute in self.attributes(loc) {
+                clone.add_attribute(loc, attribute);
+            }
+        }
+
+        for (index, clone_index) in param_indices.iter().enumerate() {
+            if let Some(clone_index) = *clone_index {
+                for attribute in self.attributes(AttributeLoc::Param(index as u32)) {
+                    clone.add_attribute(AttributeLoc::Param(clone_index), attribute);
+                }
+            }
+        }
+
+        for (param, clone_index) in self.get_param_iter().zip(param_indices) {
+            if let (Some(clone_index), Some(name)) = (clone_index, get_basic_value_name(param)) {
+                if let Some(clone_param) = clone.get_nth_param(*clone_index) {
+                    clone_param.set_name(&name);
+                }
+            }
+        }
+
+        clone.set_call_conventions(self.get_call_conventions());
+
+        if let Some(personality_fn) = self.get_personality_function() {
+            unsafe {
+                let module = LLVMGetGlobalParent(clone.as_value_ref());
+                let mut personality_fn = personality_fn.as_value_ref();
+
+                if LLVMGetGlobalParent(personality_fn) != module {
+                    personality_fn = redeclare_global(personality_fn, module);
+                }
+
+                LLVMSetPersonalityFn(clone.as_value_ref(), personality_fn);
+            }
+        }
+
+        if !unsafe { LLVMGetGC(self.as_value_ref()) }.is_null() {
+            clone.set_gc(&self.get_gc().to_string_lossy());
+        }
+
+        if let Some(section) = self.get_section() {
+            clone.set_section(Some(&section.to_string_lossy()));
+        }
+
+        let global = self.as_global_value();
+        let clone_global = clone.as_global_value();
+
+        clone_global.set_alignment(global.get_alignment());
+        clone_global.set_visibility(global.get_visibility());
+
+        self.copy_metadata_into(clone);
+    }
+
+    #[llvm_versions(4.0..=7.0)]
+    fn copy_metadata_into(self, _clone: FunctionValue<'ctx>) {
+        // Function level metadata can't be enumerated before LLVM 8
+    }
+
+    #[llvm_versions(8.0..=latest)]
+    fn copy_metadata_into(self, clone: FunctionValue<'ctx>) {
+        // LLVM always assigns kind id 0 to !dbg
+        const DBG_KIND_ID: u32 = 0;
+
+        for (kind_id, metadata) in self.as_global_value().all_metadata_by_kind_id() {
+            // A DISubprogram may only be attached to a single function
+            if kind_id != DBG_KIND_ID {
+                clone.as_global_value().set_metadata(metadata, kind_id);
+            }
+        }
+    }
+
+    // Copies every basic block into `clone`, using `replacements[n]` in place
+    // of the original's nth param. Recursive calls are redirected to `clone`
+    // if `recurse_into_clone` is set.
+    fn clone_body_into(
+        self,
+        clone: FunctionValue<'ctx>,
+        replacements: &[BasicValueEnum<'ctx>],
+        recurse_into_clone: bool,
+    ) -> Result<(), &'static str> {
+        let mut value_map: HashMap<LLVMValueRef, LLVMValueRef> = self
+            .get_param_iter()
+            .zip(replacements)
+            .map(|(param, replacement)| (param.as_value_ref(), replacement.as_value_ref()))
+            .collect();
+
+        if recurse_into_clone {
+            value_map.insert(self.as_value_ref(), clone.as_value_ref());
+        }
+
+        let context = clone.get_type().get_context();
+        let builder = context.create_builder();
+        let module = unsafe { LLVMGetGlobalParent(clone.as_value_ref()) };
+        let basic_blocks = self.get_basic_blocks();
+        let mut instructions = Vec::new();
+        let mut phis = Vec::new();
+
+        for basic_block in &basic_blocks {
+            let clone_block = context.append_basic_block(clone, &basic_block.get_name().to_string_lossy());
+
+            unsafe {
+                value_map.insert(
+                    LLVMBasicBlockAsValue(basic_block.basic_block),
+                    LLVMBasicBlockAsValue(clone_block.basic_block),
+                );
+            }
+        }
+
+        // First copy every instruction, so that operands defined later on
+        // (ie by a loop's back edge) already have a copy to be remapped to.
+        for (basic_block, clone_block) in basic_blocks.iter().zip(clone.get_basic_blocks()) {
+            builder.position_at_end(clone_block);
+
+            let mut next_instruction = basic_block.get_first_instruction();
+
+            while let Some(instruction) = next_instruction {
+                let name = instruction.get_name().map(|name| name.to_string_lossy().into_owned());
+                let clone_instruction = match PhiValue::try_from(instruction) {
+                    // Phi blocks can't be changed through the C API, so phis are
+                    // rebuilt from scratch once everything has been copied
+                    Ok(phi) => {
+                        let phi_type = unsafe { BasicTypeEnum::new(LLVMTypeOf(phi.as_value_ref())) };
+                        let clone_phi = builder.build_phi(phi_type, name.as_deref().unwrap_or(""));
+
+                        phis.push((phi, clone_phi));
+
+                        clone_phi.as_instruction()
+                    },
+                    Err(()) => {
+                        let clone_instruction = instruction.clone_instruction();
+
+                        builder.insert_instruction(&clone_instruction, name.as_deref());
+                        instructions.push(clone_instruction);
+
+                        clone_instruction
+                    },
+                };
+
+                value_map.insert(instruction.as_value_ref(), clone_instruction.as_value_ref());
+
+                next_instruction = instruction.get_next_instruction();
+            }
+        }
+
+        for instruction in instructions {
+            for index in 0..instruction.get_num_operands() {
+                unsafe {
+                    let operand = LLVMGetOperand(instruction.as_value_ref(), index);
+                    let remapped = remap_value(operand, &mut value_map, module)?;
+
+                    if remapped != operand {
+                        LLVMSetOperand(instruction.as_value_ref(), index, remapped);
+                    }
+                }
+            }
+        }
+
+        for (phi, clone_phi) in phis {
+            let incoming: Vec<_> = phi
+                .incoming()
+                .map(|(value, basic_block)| unsafe {
+                    let value = remap_value(value.as_value_ref(), &mut value_map, module)?;
+                    let basic_block =
+                        remap_value(LLVMBasicBlockAsValue(basic_block.basic_block), &mut value_map, module)?;
+
+                    Ok((
+                        BasicValueEnum::new(value),
+                        BasicBlock::new(LLVMValueAsBasicBlock(basic_block)).expect("Invalid BasicBlock"),
+                    ))
+                })
+                .collect::<Result<_, &'static str>>()?;
+            let incoming: Vec<(&dyn BasicValue<'ctx>, BasicBlock<'ctx>)> = incoming
+                .iter()
+                .map(|(value, basic_block)| (value as &dyn BasicValue<'ctx>, *basic_block))
+                .collect();
+
+            clone_phi.add_incoming(&incoming);
+        }
+
+        Ok(())
+    }
+
     /// Adds an `Attribute` to a particular location in this `FunctionValue`.
     ///
     /// # Example
@@ -489,6 +1112,20 @@ impl<'ctx> FunctionValue<'ctx> {
         unsafe { GlobalValue::new(self.as_value_ref()) }
     }
 
+    /// Attaches `function_entry_count` profile metadata (`!prof`) to this
+    /// function, recording how many times it was entered during profiling.
+    #[llvm_versions(8.0..=latest)]
+    pub fn set_entry_count(self, count: u64) {
+        let context = self.get_type().get_context();
+        let entry_count = context.metadata_node(&[
+            context.metadata_string("function_entry_count").into(),
+            context.i64_type().const_int(count, false).into(),
+        ]);
+
+        self.as_global_value()
+            .set_metadata(entry_count, context.get_kind_id("prof"))
+    }
+
     /// Set the debug info descriptor
     #[llvm_versions(7.0..=latest)]
     pub fn set_subprogram(self, subprogram: DISubprogram<'ctx>) {
@@ -588,3 +1225,229 @@ impl<'ctx> Iterator for ParamValueIter<'ctx> {
         unsafe { Some(Self::Item::new(next_value)) }
     }
 }
+
+fn get_basic_value_name(value: BasicValueEnum) -> Option<String> {
+    let name = unsafe { Value::new(value.as_value_ref()) }
+        .get_name()
+        .to_string_lossy()
+        .into_owned();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+// Finds what a cloned instruction should use in place of `value`, which is
+// either its copy, a redeclaration in `module`, or `value` itself.
+#[allow(deprecated)]
+unsafe fn remap_value(
+    value: LLVMValueRef,
+    value_map: &mut HashMap<LLVMValueRef, LLVMValueRef>,
+    module: LLVMModuleRef,
+) -> Result<LLVMValueRef, &'static str> {
+    if let Some(&mapped) = value_map.get(&value) {
+        return Ok(mapped);
+    }
+
+    let mapped = if !LLVMIsAGlobalValue(value).is_null() && LLVMGetGlobalParent(value) != module {
+        redeclare_global(value, module)
+    } else if is_aggregate_or_expr(value) {
+        remap_constant(value, value_map, module)?
+    } else if !LLVMIsAMDNode(value).is_null() && LLVMGetMDNodeNumOperands(value) == 1 {
+        // Function local metadata, such as the value operand of llvm.dbg.value,
+        // wraps an instruction or argument which must be remapped too
+        let mut operand = std::ptr::null_mut();
+
+        LLVMGetMDNodeOperands(value, &mut operand);
+
+        let is_local =
+            !operand.is_null() && (!LLVMIsAInstruction(operand).is_null() || !LLVMIsAArgument(operand).is_null());
+
+        match value_map.get(&operand) {
+            Some(&mapped) if is_local => {
+                let mut mapped = mapped;
+
+                llvm_sys::core::LLVMMDNodeInContext(LLVMGetTypeContext(LLVMTypeOf(mapped)), &mut mapped, 1)
+            },
+            _ => value,
+        }
+    } else {
+        value
+    };
+
+    value_map.insert(value, mapped);
+
+    Ok(mapped)
+}
+
+fn is_aggregate_or_expr(value: LLVMValueRef) -> bool {
+    unsafe {
+        !LLVMIsAConstantExpr(value).is_null()
+            || !LLVMIsAConstantStruct(value).is_null()
+            || !LLVMIsAConstantArray(value).is_null()
+            || !LLVMIsAConstantVector(value).is_null()
+    }
+}
+
+// Rebuilds a constant expression or aggregate whose operands refer to global
+// values which were remapped, such as a GEP of a global from another module.
+// Constants whose operands are all unchanged are returned as is.
+unsafe fn remap_constant(
+    value: LLVMValueRef,
+    value_map: &mut HashMap<LLVMValueRef, LLVMValueRef>,
+    module: LLVMModuleRef,
+) -> Result<LLVMValueRef, &'static str> {
+    let operands: Vec<_> = (0..LLVMGetNumOperands(value) as u32)
+        .map(|index| LLVMGetOperand(value, index))
+        .collect();
+    let mut mapped_operands: Vec<_> = operands
+        .iter()
+        .map(|&operand| remap_value(operand, value_map, module))
+        .collect::<Result<_, _>>()?;
+
+    if mapped_operands == operands {
+        return Ok(value);
+    }
+
+    let ty = LLVMTypeOf(value);
+    let num_operands = mapped_operands.len() as u32;
+
+    if !LLVMIsAConstantStruct(value).is_null() {
+        if LLVMGetStructName(ty).is_null() {
+            let context = LLVMGetTypeContext(ty);
+
+            return Ok(LLVMConstStructInContext(
+                context,
+                mapped_operands.as_mut_ptr(),
+                num_operands,
+                LLVMIsPackedStruct(ty),
+            ));
+        }
+
+        return Ok(LLVMConstNamedStruct(ty, mapped_operands.as_mut_ptr(), num_operands));
+    }
+
+    if !LLVMIsAConstantArray(value).is_null() {
+        return Ok(LLVMConstArray(
+            LLVMGetElementType(ty),
+            mapped_operands.as_mut_ptr(),
+            num_operands,
+        ));
+    }
+
+    if !LLVMIsAConstantVector(value).is_null() {
+        return Ok(LLVMConstVector(mapped_operands.as_mut_ptr(), num_operands));
+    }
+
+    let first = mapped_operands[0];
+    let remapped = match LLVMGetConstOpcode(value) {
+        LLVMOpcode::LLVMGetElementPtr => remap_gep(value, &mut mapped_operands),
+        LLVMOpcode::LLVMBitCast => LLVMConstBitCast(first, ty),
+        LLVMOpcode::LLVMAddrSpaceCast => LLVMConstAddrSpaceCast(first, ty),
+        LLVMOpcode::LLVMPtrToInt => LLVMConstPtrToInt(first, ty),
+        LLVMOpcode::LLVMIntToPtr => LLVMConstIntToPtr(first, ty),
+        LLVMOpcode::LLVMTrunc => LLVMConstTrunc(first, ty),
+        LLVMOpcode::LLVMZExt => LLVMConstZExt(first, ty),
+        LLVMOpcode::LLVMSExt => LLVMConstSExt(first, ty),
+        LLVMOpcode::LLVMAdd => match get_wrap_flags(value) {
+            (false, false) => LLVMConstAdd(first, mapped_operands[1]),
+            (true, false) => LLVMConstNSWAdd(first, mapped_operands[1]),
+            (false, true) => LLVMConstNUWAdd(first, mapped_operands[1]),
+            (true, true) => return Err("Cannot remap an add constant expression which is both nsw and nuw."),
+        },
+        LLVMOpcode::LLVMSub => match get_wrap_flags(value) {
+            (false, false) => LLVMConstSub(first, mapped_operands[1]),
+            (true, false) => LLVMConstNSWSub(first, mapped_operands[1]),
+            (false, true) => LLVMConstNUWSub(first, mapped_operands[1]),
+            (true, true) => return Err("Cannot remap a sub constant expression which is both nsw and nuw."),
+        },
+        _ => return Err("Cannot rebuild a constant expression over a remapped global value."),
+    };
+
+    Ok(remapped)
+}
+
+// Gets whether a constant `add` or `sub` expression is `nsw` and `nuw`. The C
+// API can't query these flags on constants, so they're read from the printed
+// expression instead, ie `i32 add nsw (i32 ptrtoint (...), i32 1)`.
+unsafe fn get_wrap_flags(value: LLVMValueRef) -> (bool, bool) {
+    let string = Value::new(value).print_to_string();
+    let string = string.to_string_lossy();
+    let opcode_and_flags = string.split('(').next().unwrap_or_default();
+    let has_flag = |flag| opcode_and_flags.split_whitespace().any(|word| word == flag);
+
+    (has_flag("nsw"), has_flag("nuw"))
+}
+
+#[llvm_versions(4.0..=13.0)]
+unsafe fn remap_gep(value: LLVMValueRef, operands: &mut [LLVMValueRef]) -> LLVMValueRef {
+    use llvm_sys::core::{LLVMConstGEP, LLVMConstInBoundsGEP, LLVMIsInBounds};
+
+    let (pointer, indices) = operands.split_first_mut().expect("GEP should have a pointer operand");
+
+    if LLVMIsInBounds(value) == 1 {
+        LLVMConstInBoundsGEP(*pointer, indices.as_mut_ptr(), indices.len() as u32)
+    } else {
+        LLVMConstGEP(*pointer, indices.as_mut_ptr(), indices.len() as u32)
+    }
+}
+
+#[llvm_versions(14.0..=latest)]
+unsafe fn remap_gep(value: LLVMValueRef, operands: &mut [LLVMValueRef]) -> LLVMValueRef {
+    use llvm_sys::core::{LLVMConstGEP2, LLVMConstInBoundsGEP2, LLVMGetGEPSourceElementType, LLVMIsInBounds};
+
+    let source_type = LLVMGetGEPSourceElementType(value);
+    let (pointer, indices) = operands.split_first_mut().expect("GEP should have a pointer operand");
+
+    if LLVMIsInBounds(value) == 1 {
+        LLVMConstInBoundsGEP2(source_type, *pointer, indices.as_mut_ptr(), indices.len() as u32)
+    } else {
+        LLVMConstGEP2(source_type, *pointer, indices.as_mut_ptr(), indices.len() as u32)
+    }
+}
+
+// Declares a global value from another module in `module`, reusing an
+// existing function or global variable with the same name. Aliases and ifuncs
+// are declared as the function or global variable they stand for.
+unsafe fn redeclare_global(value: LLVMValueRef, module: LLVMModuleRef) -> LLVMValueRef {
+    let name = Value::new(value).get_name().to_owned();
+    let value_type = global_value_type(value);
+
+    if LLVMGetTypeKind(value_type) == LLVMTypeKind::LLVMFunctionTypeKind {
+        let existing = LLVMGetNamedFunction(module, name.as_ptr());
+
+        if !existing.is_null() {
+            return existing;
+        }
+
+        return LLVMAddFunction(module, name.as_ptr(), value_type);
+    }
+
+    let existing = LLVMGetNamedGlobal(module, name.as_ptr());
+
+    if !existing.is_null() {
+        return existing;
+    }
+
+    let address_space = LLVMGetPointerAddressSpace(LLVMTypeOf(value));
+    let global = LLVMAddGlobalInAddressSpace(module, value_type, name.as_ptr(), address_space);
+
+    if !LLVMIsAGlobalVariable(value).is_null() {
+        LLVMSetGlobalConstant(global, LLVMIsGlobalConstant(value));
+        LLVMSetThreadLocal(global, LLVMIsThreadLocal(value));
+    }
+
+    global
+}
+
+#[llvm_versions(4.0..=7.0)]
+unsafe fn global_value_type(value: LLVMValueRef) -> llvm_sys::prelude::LLVMTypeRef {
+    llvm_sys::core::LLVMGetElementType(LLVMTypeOf(value))
+}
+
+#[llvm_versions(8.0..=latest)]
+unsafe fn global_value_type(value: LLVMValueRef) -> llvm_sys::prelude::LLVMTypeRef {
+    llvm_sys::core::LLVMGlobalGetValueType(value)
+}
@@ -0,0 +1,102 @@
+#[llvm_versions(7.0..=latest)]
+use llvm_sys::core::{LLVMAliasGetAliasee, LLVMAliasSetAliasee, LLVMGetNextGlobalAlias, LLVMGetPreviousGlobalAlias};
+use llvm_sys::prelude::LLVMValueRef;
+
+use std::ffi::CStr;
+use std::fmt::{self, Display};
+
+use crate::values::traits::AsValueRef;
+use crate::values::{GlobalValue, PointerValue, Value};
+
+use super::AnyValue;
+
+/// A global alias, which is a new symbol for an existing global variable,
+/// function or other alias. Created with `Module::add_alias`.
+///
+/// Properties shared with other global values, such as linkage and
+/// visibility, can be accessed through `as_global_value`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct GlobalAliasValue<'ctx> {
+    alias_value: Value<'ctx>,
+}
+
+impl<'ctx> GlobalAliasValue<'ctx> {
+    pub(crate) unsafe fn new(value: LLVMValueRef) -> Self {
+        assert!(!value.is_null());
+
+        GlobalAliasValue {
+            alias_value: Value::new(value),
+        }
+    }
+
+    /// Gets the name of this `GlobalAliasValue`.
+    pub fn get_name(&self) -> &CStr {
+        self.alias_value.get_name()
+    }
+
+    /// Sets the name of this `GlobalAliasValue`.
+    pub fn set_name(self, name: &str) {
+        self.alias_value.set_name(name)
+    }
+
+    /// Gets the value this `GlobalAliasValue` is an alias for. This is usually
+    /// a global value, but may be a constant expression on one.
+    #[llvm_versions(7.0..=latest)]
+    pub fn get_aliasee(self) -> PointerValue<'ctx> {
+        unsafe { PointerValue::new(LLVMAliasGetAliasee(self.as_value_ref())) }
+    }
+
+    /// Makes this `GlobalAliasValue` an alias for `aliasee` instead.
+    #[llvm_versions(7.0..=latest)]
+    pub fn set_aliasee(self, aliasee: GlobalValue<'ctx>) {
+        unsafe { LLVMAliasSetAliasee(self.as_value_ref(), aliasee.as_value_ref()) }
+    }
+
+    /// Gets the previous `GlobalAliasValue` in its module, if any.
+    #[llvm_versions(7.0..=latest)]
+    pub fn get_previous_alias(self) -> Option<GlobalAliasValue<'ctx>> {
+        let value = unsafe { LLVMGetPreviousGlobalAlias(self.as_value_ref()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(GlobalAliasValue::new(value)) }
+    }
+
+    /// Gets the next `GlobalAliasValue` in its module, if any.
+    #[llvm_versions(7.0..=latest)]
+    pub fn get_next_alias(self) -> Option<GlobalAliasValue<'ctx>> {
+        let value = unsafe { LLVMGetNextGlobalAlias(self.as_value_ref()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(GlobalAliasValue::new(value)) }
+    }
+
+    /// Gets this `GlobalAliasValue` as a `GlobalValue`, to access its linkage,
+    /// visibility and so on.
+    pub fn as_global_value(self) -> GlobalValue<'ctx> {
+        unsafe { GlobalValue::new(self.as_value_ref()) }
+    }
+
+    /// Gets this `GlobalAliasValue` as a `PointerValue`, to be used like the
+    /// value it aliases.
+    pub fn as_pointer_value(self) -> PointerValue<'ctx> {
+        unsafe { PointerValue::new(self.as_value_ref()) }
+    }
+}
+
+unsafe impl AsValueRef for GlobalAliasValue<'_> {
+    fn as_value_ref(&self) -> LLVMValueRef {
+        self.alias_value.value
+    }
+}
+
+impl Display for GlobalAliasValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_to_string())
+    }
+}
@@ -1,5 +1,4 @@
-#[llvm_versions(8.0..=latest)]
-use llvm_sys::core::LLVMGlobalSetMetadata;
+use llvm_sys::core::{LLVMDeleteFunction, LLVMIsAFunction, LLVMIsAGlobalVariable, LLVMTypeOf};
 #[llvm_versions(4.0..=7.0)]
 use llvm_sys::core::{
     LLVMDeleteGlobal, LLVMGetAlignment, LLVMGetDLLStorageClass, LLVMGetInitializer, LLVMGetLinkage, LLVMGetNextGlobal,
@@ -18,6 +17,10 @@ use llvm_sys::core::{
 };
 #[llvm_versions(7.0..=latest)]
 use llvm_sys::core::{LLVMGetUnnamedAddress, LLVMSetUnnamedAddress};
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::core::{
+    LLVMGlobalClearMetadata, LLVMGlobalCopyAllMetadata, LLVMGlobalEraseMetadata, LLVMGlobalSetMetadata,
+};
 #[llvm_versions(4.0..=6.0)]
 use llvm_sys::core::{LLVMHasUnnamedAddr, LLVMSetUnnamedAddr};
 use llvm_sys::prelude::LLVMValueRef;
@@ -31,9 +34,11 @@ use std::fmt::{self, Display};
 #[llvm_versions(7.0..=latest)]
 use crate::comdat::Comdat;
 use crate::module::Linkage;
+#[llvm_versions(8.0..=latest)]
+use crate::values::metadata_value::name_metadata_kinds;
 use crate::values::traits::AsValueRef;
 #[llvm_versions(8.0..=latest)]
-use crate::values::MetadataValue;
+use crate::values::{take_metadata_entries, MetadataValue};
 use crate::values::{BasicValue, BasicValueEnum, PointerValue, Value};
 use crate::{DLLStorageClass, GlobalVisibility, ThreadLocalMode};
 
@@ -227,6 +232,76 @@ impl<'ctx> GlobalValue<'ctx> {
         LLVMDeleteGlobal(self.as_value_ref())
     }
 
+    /// Deletes this global variable or function from its module, as long as
+    /// nothing uses it anymore. Returns an error and leaves the module
+    /// unchanged if it still has uses or is some other kind of global value,
+    /// such as an alias.
+    ///
+    /// # Safety
+    /// Like `delete`, this frees the global value, so `self` and any copies of
+    /// it, such as `FunctionValue`s or `PointerValue`s of it, must not be used
+    /// after a successful deletion.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let global = module.add_global(context.i8_type(), None, "my_global");
+    ///
+    /// assert!(unsafe { global.try_delete() }.is_ok());
+    /// assert!(module.get_global("my_global").is_none());
+    /// ```
+    pub unsafe fn try_delete(self) -> Result<(), &'static str> {
+        if self.global_value.get_first_use().is_some() {
+            return Err("Global value still has uses.");
+        }
+
+        if self.is_variable() {
+            LLVMDeleteGlobal(self.as_value_ref());
+        } else if self.is_function() {
+            LLVMDeleteFunction(self.as_value_ref());
+        } else {
+            return Err("Only global variables and functions can be deleted.");
+        }
+
+        Ok(())
+    }
+
+    /// Replaces all uses of this global value with `other`, then deletes it as
+    /// with `try_delete`. Returns an error and leaves the module unchanged if
+    /// `other` is this global value or has a different type.
+    ///
+    /// # Safety
+    /// As with `try_delete`, `self` and any copies of it must not be used after
+    /// a successful deletion.
+    pub unsafe fn replace_all_uses_and_delete(self, other: GlobalValue<'ctx>) -> Result<(), &'static str> {
+        if self == other {
+            return Err("Cannot replace a global value with itself.");
+        }
+
+        if LLVMTypeOf(self.as_value_ref()) != LLVMTypeOf(other.as_value_ref()) {
+            return Err("Replacement global value has a different type.");
+        }
+
+        if !self.is_variable() && !self.is_function() {
+            return Err("Only global variables and functions can be deleted.");
+        }
+
+        self.global_value.replace_all_uses_with(other.as_value_ref());
+        self.try_delete()
+    }
+
+    fn is_variable(self) -> bool {
+        unsafe { !LLVMIsAGlobalVariable(self.as_value_ref()).is_null() }
+    }
+
+    fn is_function(self) -> bool {
+        unsafe { !LLVMIsAFunction(self.as_value_ref()).is_null() }
+    }
+
     pub fn as_pointer_value(self) -> PointerValue<'ctx> {
         unsafe { PointerValue::new(self.as_value_ref()) }
     }
@@ -245,6 +320,40 @@ impl<'ctx> GlobalValue<'ctx> {
         unsafe { LLVMGlobalSetMetadata(self.as_value_ref(), kind_id, metadata.as_metadata_ref()) }
     }
 
+    /// Gets every metadata attachment of this `GlobalValue` as `(kind name,
+    /// metadata)` pairs.
+    #[llvm_versions(8.0..=latest)]
+    pub fn all_metadata(self) -> Vec<(String, MetadataValue<'ctx>)> {
+        let printed = self.global_value.print_to_string();
+
+        name_metadata_kinds(&printed.to_string_lossy(), self.all_metadata_by_kind_id())
+    }
+
+    // Gets every metadata attachment as `(kind id, metadata)` pairs, ordered
+    // by kind id.
+    #[llvm_versions(8.0..=latest)]
+    pub(crate) fn all_metadata_by_kind_id(self) -> Vec<(u32, MetadataValue<'ctx>)> {
+        unsafe {
+            let mut count = 0;
+            let entries = LLVMGlobalCopyAllMetadata(self.as_value_ref(), &mut count);
+
+            take_metadata_entries(self.as_value_ref(), entries, count)
+        }
+    }
+
+    /// Removes the metadata attachment of the given kind from this
+    /// `GlobalValue`, if any.
+    #[llvm_versions(8.0..=latest)]
+    pub fn remove_metadata(self, kind_id: u32) {
+        unsafe { LLVMGlobalEraseMetadata(self.as_value_ref(), kind_id) }
+    }
+
+    /// Removes every metadata attachment from this `GlobalValue`.
+    #[llvm_versions(8.0..=latest)]
+    pub fn clear_metadata(self) {
+        unsafe { LLVMGlobalClearMetadata(self.as_value_ref()) }
+    }
+
     /// Gets a `Comdat` assigned to this `GlobalValue`, if any.
     #[llvm_versions(7.0..=latest)]
     pub fn get_comdat(self) -> Option<Comdat> {
@@ -0,0 +1,116 @@
+use llvm_sys::core::{
+    LLVMGetConstOpcode, LLVMGetGlobalIFuncResolver, LLVMGetNextGlobalIFunc, LLVMGetOperand, LLVMGetPreviousGlobalIFunc,
+    LLVMIsAConstantExpr, LLVMIsAFunction, LLVMSetGlobalIFuncResolver,
+};
+use llvm_sys::prelude::LLVMValueRef;
+use llvm_sys::LLVMOpcode;
+
+use std::ffi::CStr;
+use std::fmt::{self, Display};
+
+use crate::values::traits::AsValueRef;
+use crate::values::{FunctionValue, GlobalValue, PointerValue, Value};
+
+use super::AnyValue;
+
+/// An indirect function, whose address is picked at load time by calling its
+/// resolver function. Created with `Module::add_ifunc`.
+///
+/// Properties shared with other global values, such as linkage and
+/// visibility, can be accessed through `as_global_value`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct IFuncValue<'ctx> {
+    ifunc_value: Value<'ctx>,
+}
+
+impl<'ctx> IFuncValue<'ctx> {
+    pub(crate) unsafe fn new(value: LLVMValueRef) -> Self {
+        assert!(!value.is_null());
+
+        IFuncValue {
+            ifunc_value: Value::new(value),
+        }
+    }
+
+    /// Gets the name of this `IFuncValue`.
+    pub fn get_name(&self) -> &CStr {
+        self.ifunc_value.get_name()
+    }
+
+    /// Sets the name of this `IFuncValue`.
+    pub fn set_name(self, name: &str) {
+        self.ifunc_value.set_name(name)
+    }
+
+    /// Gets the resolver function of this `IFuncValue`, looking through any
+    /// pointer casts of it. Returns `None` if the resolver is not a function,
+    /// such as when it is an alias.
+    pub fn get_resolver(self) -> Option<FunctionValue<'ctx>> {
+        let mut resolver = unsafe { LLVMGetGlobalIFuncResolver(self.as_value_ref()) };
+
+        unsafe {
+            while !LLVMIsAConstantExpr(resolver).is_null()
+                && matches!(
+                    LLVMGetConstOpcode(resolver),
+                    LLVMOpcode::LLVMBitCast | LLVMOpcode::LLVMAddrSpaceCast
+                )
+            {
+                resolver = LLVMGetOperand(resolver, 0);
+            }
+
+            FunctionValue::new(LLVMIsAFunction(resolver))
+        }
+    }
+
+    /// Sets the resolver function of this `IFuncValue`. It should take no
+    /// arguments and return a pointer to the implementation to use.
+    pub fn set_resolver(self, resolver: FunctionValue<'ctx>) {
+        unsafe { LLVMSetGlobalIFuncResolver(self.as_value_ref(), resolver.as_value_ref()) }
+    }
+
+    /// Gets the previous `IFuncValue` in its module, if any.
+    pub fn get_previous_ifunc(self) -> Option<IFuncValue<'ctx>> {
+        let value = unsafe { LLVMGetPreviousGlobalIFunc(self.as_value_ref()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(IFuncValue::new(value)) }
+    }
+
+    /// Gets the next `IFuncValue` in its module, if any.
+    pub fn get_next_ifunc(self) -> Option<IFuncValue<'ctx>> {
+        let value = unsafe { LLVMGetNextGlobalIFunc(self.as_value_ref()) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        unsafe { Some(IFuncValue::new(value)) }
+    }
+
+    /// Gets this `IFuncValue` as a `GlobalValue`, to access its linkage,
+    /// visibility and so on.
+    pub fn as_global_value(self) -> GlobalValue<'ctx> {
+        unsafe { GlobalValue::new(self.as_value_ref()) }
+    }
+
+    /// Gets this `IFuncValue` as a `PointerValue`, which can be called like
+    /// a function pointer.
+    pub fn as_pointer_value(self) -> PointerValue<'ctx> {
+        unsafe { PointerValue::new(self.as_value_ref()) }
+    }
+}
+
+unsafe impl AsValueRef for IFuncValue<'_> {
+    fn as_value_ref(&self) -> LLVMValueRef {
+        self.ifunc_value.value
+    }
+}
+
+impl Display for IFuncValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_to_string())
+    }
+}
@@ -2,12 +2,15 @@ use either::{
     Either,
     Either::{Left, Right},
 };
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::core::LLVMInstructionGetAllMetadataOtherThanDebugLoc;
 use llvm_sys::core::{
     LLVMGetAlignment, LLVMGetFCmpPredicate, LLVMGetICmpPredicate, LLVMGetInstructionOpcode, LLVMGetInstructionParent,
-    LLVMGetMetadata, LLVMGetNextInstruction, LLVMGetNumOperands, LLVMGetOperand, LLVMGetOperandUse,
-    LLVMGetPreviousInstruction, LLVMGetVolatile, LLVMHasMetadata, LLVMInstructionClone, LLVMInstructionEraseFromParent,
-    LLVMInstructionRemoveFromParent, LLVMIsAAllocaInst, LLVMIsABasicBlock, LLVMIsALoadInst, LLVMIsAStoreInst,
-    LLVMIsTailCall, LLVMSetAlignment, LLVMSetMetadata, LLVMSetOperand, LLVMSetVolatile, LLVMValueAsBasicBlock,
+    LLVMGetMetadata, LLVMGetNextInstruction, LLVMGetNumOperands, LLVMGetNumSuccessors, LLVMGetOperand,
+    LLVMGetOperandUse, LLVMGetPreviousInstruction, LLVMGetTypeContext, LLVMGetVolatile, LLVMHasMetadata,
+    LLVMInstructionClone, LLVMInstructionEraseFromParent, LLVMInstructionRemoveFromParent, LLVMIsAAllocaInst,
+    LLVMIsABasicBlock, LLVMIsALoadInst, LLVMIsAStoreInst, LLVMIsConditional, LLVMIsTailCall, LLVMSetAlignment,
+    LLVMSetMetadata, LLVMSetOperand, LLVMSetVolatile, LLVMTypeOf, LLVMValueAsBasicBlock,
 };
 use llvm_sys::core::{LLVMGetOrdering, LLVMSetOrdering};
 #[llvm_versions(10.0..=latest)]
@@ -17,8 +20,17 @@ use llvm_sys::LLVMOpcode;
 
 use std::{ffi::CStr, fmt, fmt::Display};
 
+use crate::context::ContextRef;
+#[llvm_versions(8.0..=latest)]
+use crate::values::metadata_value::name_metadata_kinds;
+#[llvm_versions(8.0..=latest)]
+use crate::values::metadata_value::LLVM_MD_DBG;
+#[llvm_versions(8.0..=latest)]
+use crate::values::take_metadata_entries;
 use crate::values::traits::AsValueRef;
-use crate::values::{BasicValue, BasicValueEnum, BasicValueUse, MetadataValue, Value};
+use crate::values::{
+    BasicMetadataValueEnum, BasicValue, BasicValueEnum, BasicValueUse, IntValue, MetadataValue, Value,
+};
 use crate::{basic_block::BasicBlock, types::AnyTypeEnum};
 use crate::{AtomicOrdering, FloatPredicate, IntPredicate};
 
@@ -213,6 +225,129 @@ impl<'ctx> InstructionValue<'ctx> {
         unsafe { LLVMInstructionRemoveFromParent(self.as_value_ref()) }
     }
 
+    /// Creates a copy of this `InstructionValue` with the same operands. The
+    /// copy has no parent and no name until it is inserted somewhere, for
+    /// example with `insert_into` or `Builder::insert_instruction`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("ivs");
+    /// let builder = context.create_builder();
+    /// let i32_type = context.i32_type();
+    /// let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+    /// let function = module.add_function("double", fn_type, None);
+    /// let basic_block = context.append_basic_block(function, "entry");
+    ///
+    /// builder.position_at_end(basic_block);
+    ///
+    /// let arg = function.get_first_param().unwrap().into_int_value();
+    /// let sum = builder.build_int_add(arg, arg, "sum");
+    /// let ret = builder.build_return(Some(&sum));
+    /// let sum_instruction = sum.as_instruction().unwrap();
+    /// let sum_clone = sum_instruction.clone_instruction();
+    ///
+    /// assert!(sum_clone.get_parent().is_none());
+    ///
+    /// sum_clone.insert_into(basic_block, 1).unwrap();
+    ///
+    /// assert_eq!(sum_instruction.get_next_instruction(), Some(sum_clone));
+    /// assert_eq!(sum_clone.get_next_instruction(), Some(ret));
+    /// ```
+    pub fn clone_instruction(self) -> Self {
+        unsafe { InstructionValue::new(LLVMInstructionClone(self.as_value_ref())) }
+    }
+
+    /// Moves this `InstructionValue` so it comes right before `other`, which
+    /// may be in a different basic block. The instruction keeps its name.
+    pub fn move_before(self, other: InstructionValue<'ctx>) -> Result<(), &'static str> {
+        if self == other {
+            return Err("Cannot move an instruction relative to itself.");
+        }
+        let basic_block = match other.get_parent() {
+            Some(basic_block) => basic_block,
+            None => return Err("Target instruction does not belong to a basic block."),
+        };
+
+        let name = self.take_name_and_remove();
+        let builder = basic_block.get_context().create_builder();
+
+        builder.position_before(&other);
+        builder.insert_instruction(&self, name.as_deref());
+
+        Ok(())
+    }
+
+    /// Moves this `InstructionValue` so it comes right after `other`, which
+    /// may be in a different basic block. The instruction keeps its name.
+    pub fn move_after(self, other: InstructionValue<'ctx>) -> Result<(), &'static str> {
+        if self == other {
+            return Err("Cannot move an instruction relative to itself.");
+        }
+
+        let basic_block = match other.get_parent() {
+            Some(basic_block) => basic_block,
+            None => return Err("Target instruction does not belong to a basic block."),
+        };
+
+        let name = self.take_name_and_remove();
+        let builder = basic_block.get_context().create_builder();
+
+        match other.get_next_instruction() {
+            Some(next) => builder.position_before(&next),
+            None => builder.position_at_end(basic_block),
+        }
+
+        builder.insert_instruction(&self, name.as_deref());
+
+        Ok(())
+    }
+
+    /// Inserts this `InstructionValue`, which must not already belong to a
+    /// basic block, into `basic_block` so that it becomes the instruction at
+    /// index `position`. A `position` equal to the number of instructions in
+    /// the block appends it to the end.
+    pub fn insert_into(self, basic_block: BasicBlock<'ctx>, position: u32) -> Result<(), &'static str> {
+        if self.get_parent().is_some() {
+            return Err("Instruction already belongs to a basic block.");
+        }
+
+        let mut instruction = basic_block.get_first_instruction();
+
+        for _ in 0..position {
+            instruction = match instruction {
+                Some(instruction) => instruction.get_next_instruction(),
+                None => return Err("Position is out of bounds for the basic block."),
+            };
+        }
+
+        let builder = basic_block.get_context().create_builder();
+
+        match instruction {
+            Some(instruction) => builder.position_before(&instruction),
+            None => builder.position_at_end(basic_block),
+        }
+
+        builder.insert_instruction(&self, None);
+
+        Ok(())
+    }
+
+    // Inserting into a builder overwrites the instruction's name, so it needs
+    // to be saved beforehand. Also detaches the instruction if it has a parent.
+    fn take_name_and_remove(self) -> Option<String> {
+        let name = self.get_name().map(|name| name.to_string_lossy().into_owned());
+
+        if self.get_parent().is_some() {
+            unsafe { LLVMInstructionRemoveFromParent(self.as_value_ref()) }
+        }
+
+        name
+    }
+
     // REVIEW: Potentially unsafe is parent BB or grandparent fn was deleted
     // REVIEW: Should this *not* be an option? Parent should always exist,
     // but I doubt LLVM returns null if the parent BB (or grandparent FN)
@@ -677,6 +812,217 @@ impl<'ctx> InstructionValue<'ctx> {
 
         Ok(())
     }
+
+    /// Gets every metadata attachment of this `Instruction`, including its
+    /// debug location (`!dbg`), as `(kind name, metadata)` pairs.
+    #[llvm_versions(8.0..=latest)]
+    pub fn all_metadata(self) -> Vec<(String, MetadataValue<'ctx>)> {
+        name_metadata_kinds(
+            &self.print_to_string().to_string_lossy(),
+            self.all_metadata_by_kind_id(),
+        )
+    }
+
+    // Gets every metadata attachment as `(kind id, metadata)` pairs, with the
+    // debug location first and the others ordered by kind id.
+    #[llvm_versions(8.0..=latest)]
+    pub(crate) fn all_metadata_by_kind_id(self) -> Vec<(u32, MetadataValue<'ctx>)> {
+        let mut attachments = Vec::new();
+
+        if let Some(debug_location) = self.get_metadata(LLVM_MD_DBG) {
+            attachments.push((LLVM_MD_DBG, debug_location));
+        }
+
+        // Despite its name, some versions of this function do report the
+        // debug location, so it is skipped here to only appear once
+        let other_metadata = unsafe {
+            let mut count = 0;
+            let entries = LLVMInstructionGetAllMetadataOtherThanDebugLoc(self.as_value_ref(), &mut count);
+
+            take_metadata_entries(self.as_value_ref(), entries, count)
+        };
+
+        attachments.extend(
+            other_metadata
+                .into_iter()
+                .filter(|&(kind_id, _)| kind_id != LLVM_MD_DBG),
+        );
+
+        attachments
+    }
+
+    /// Removes the metadata attachment of the given kind from this
+    /// `Instruction`, if any.
+    pub fn remove_metadata(self, kind_id: u32) {
+        unsafe { LLVMSetMetadata(self.as_value_ref(), kind_id, std::ptr::null_mut()) }
+    }
+
+    /// Attaches `branch_weights` profile metadata (`!prof`) to this conditional
+    /// branch, switch or indirect branch, with one weight per successor in
+    /// order. Returns an error if this is another kind of instruction or the
+    /// number of weights does not match the number of successors.
+    pub fn set_branch_weights(self, weights: &[u32]) -> Result<(), &'static str> {
+        let num_successors = match self.get_opcode() {
+            InstructionOpcode::Br if unsafe { LLVMIsConditional(self.as_value_ref()) } == 1 => 2,
+            InstructionOpcode::Switch | InstructionOpcode::IndirectBr => unsafe {
+                LLVMGetNumSuccessors(self.as_value_ref())
+            },
+            _ => return Err("Branch weights can only be attached to a conditional branch, switch or indirectbr."),
+        };
+
+        if weights.len() != num_successors as usize {
+            return Err("Expected exactly one branch weight per successor.");
+        }
+
+        let context = self.get_context();
+        let i32_type = context.i32_type();
+        let mut operands = vec![context.metadata_string("branch_weights").into()];
+
+        operands.extend(
+            weights
+                .iter()
+                .map(|&weight| BasicMetadataValueEnum::from(i32_type.const_int(weight as u64, false))),
+        );
+
+        self.set_metadata(context.metadata_node(&operands), context.get_kind_id("prof"))
+    }
+
+    /// Attaches `!range` metadata to this integer load, promising that the
+    /// loaded value lies within one of the half open `[low, high)` intervals
+    /// in `ranges`. Intervals may wrap around, but must be ordered, disjoint
+    /// and non-adjacent. Returns an error if this is not an integer load or the
+    /// bounds are not constants of the loaded type.
+    pub fn set_range_metadata(self, ranges: &[(IntValue<'ctx>, IntValue<'ctx>)]) -> Result<(), &'static str> {
+        let loaded_type = self.get_loaded_type()?;
+
+        if !loaded_type.is_int_type() {
+            return Err("Range metadata can only be attached to integer loads.");
+        }
+
+        if ranges.is_empty() {
+            return Err("At least one range is required.");
+        }
+
+        let mut operands = Vec::with_capacity(2 * ranges.len());
+
+        for &(low, high) in ranges {
+            if AnyTypeEnum::from(low.get_type()) != loaded_type || AnyTypeEnum::from(high.get_type()) != loaded_type {
+                return Err("Range bounds must have the loaded type.");
+            }
+
+            if !low.is_const() || !high.is_const() {
+                return Err("Range bounds must be constants.");
+            }
+
+            if low == high {
+                return Err("Ranges must not be empty.");
+            }
+
+            operands.push(low.into());
+            operands.push(high.into());
+        }
+
+        self.set_metadata_node("range", &operands)
+    }
+
+    /// Attaches `!nonnull` metadata to this pointer load, promising that the
+    /// loaded pointer is never null.
+    pub fn set_nonnull_metadata(self) -> Result<(), &'static str> {
+        self.get_loaded_pointer_type()?;
+        self.set_metadata_node("nonnull", &[])
+    }
+
+    /// Attaches `!align` metadata to this pointer load, promising that the
+    /// loaded pointer is aligned to `alignment` bytes, which must be a power
+    /// of two.
+    pub fn set_align_metadata(self, alignment: u64) -> Result<(), &'static str> {
+        self.get_loaded_pointer_type()?;
+
+        if !alignment.is_power_of_two() {
+            return Err("Alignment must be a power of two.");
+        }
+
+        let alignment = self.get_context().i64_type().const_int(alignment, false);
+
+        self.set_metadata_node("align", &[alignment.into()])
+    }
+
+    /// Attaches `!dereferenceable` metadata to this pointer load, promising
+    /// that the loaded pointer can be dereferenced for `bytes` bytes.
+    pub fn set_dereferenceable_metadata(self, bytes: u64) -> Result<(), &'static str> {
+        self.get_loaded_pointer_type()?;
+
+        let bytes = self.get_context().i64_type().const_int(bytes, false);
+
+        self.set_metadata_node("dereferenceable", &[bytes.into()])
+    }
+
+    /// Attaches `!dereferenceable_or_null` metadata to this pointer load,
+    /// promising that the loaded pointer is either null or can be dereferenced
+    /// for `bytes` bytes.
+    pub fn set_dereferenceable_or_null_metadata(self, bytes: u64) -> Result<(), &'static str> {
+        self.get_loaded_pointer_type()?;
+
+        let bytes = self.get_context().i64_type().const_int(bytes, false);
+
+        self.set_metadata_node("dereferenceable_or_null", &[bytes.into()])
+    }
+
+    /// Attaches `!noundef` metadata to this load, promising that the loaded
+    /// value is neither undef nor poison.
+    pub fn set_noundef_metadata(self) -> Result<(), &'static str> {
+        self.get_loaded_type()?;
+        self.set_metadata_node("noundef", &[])
+    }
+
+    /// Attaches `!invariant.load` metadata to this load, promising that the
+    /// memory it reads never changes while it is dereferenceable.
+    pub fn set_invariant_load_metadata(self) -> Result<(), &'static str> {
+        self.get_loaded_type()?;
+        self.set_metadata_node("invariant.load", &[])
+    }
+
+    /// Attaches `!nontemporal` metadata to this load or store, hinting that
+    /// the memory it accesses is unlikely to be reused soon, so it need not be
+    /// kept in the cache.
+    pub fn set_nontemporal_metadata(self) -> Result<(), &'static str> {
+        if self.get_opcode() != InstructionOpcode::Store {
+            self.get_loaded_type()
+                .map_err(|_| "Nontemporal metadata can only be attached to loads and stores.")?;
+        }
+
+        let one = self.get_context().i32_type().const_int(1, false);
+
+        self.set_metadata_node("nontemporal", &[one.into()])
+    }
+
+    fn get_loaded_type(self) -> Result<AnyTypeEnum<'ctx>, &'static str> {
+        if self.get_opcode() != InstructionOpcode::Load {
+            return Err("This metadata can only be attached to loads.");
+        }
+
+        Ok(self.get_type())
+    }
+
+    fn get_loaded_pointer_type(self) -> Result<AnyTypeEnum<'ctx>, &'static str> {
+        let loaded_type = self.get_loaded_type()?;
+
+        if !loaded_type.is_pointer_type() {
+            return Err("This metadata can only be attached to pointer loads.");
+        }
+
+        Ok(loaded_type)
+    }
+
+    fn set_metadata_node(self, kind: &str, operands: &[BasicMetadataValueEnum<'ctx>]) -> Result<(), &'static str> {
+        let context = self.get_context();
+
+        self.set_metadata(context.metadata_node(operands), context.get_kind_id(kind))
+    }
+
+    fn get_context(self) -> ContextRef<'ctx> {
+        unsafe { ContextRef::new(LLVMGetTypeContext(LLVMTypeOf(self.as_value_ref()))) }
+    }
 }
 
 impl Clone for InstructionValue<'_> {
@@ -5,8 +5,15 @@ use llvm_sys::prelude::LLVMValueRef;
 
 #[llvm_versions(7.0..=latest)]
 use llvm_sys::core::LLVMValueAsMetadata;
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::core::{
+    LLVMDisposeValueMetadataEntries, LLVMGetTypeContext, LLVMMetadataAsValue, LLVMTypeOf,
+    LLVMValueMetadataEntriesGetKind, LLVMValueMetadataEntriesGetMetadata,
+};
 #[llvm_versions(7.0..=latest)]
 use llvm_sys::prelude::LLVMMetadataRef;
+#[llvm_versions(8.0..=latest)]
+use llvm_sys::prelude::LLVMValueMetadataEntry;
 
 use crate::values::traits::AsValueRef;
 use crate::values::{BasicMetadataValueEnum, Value};
@@ -39,6 +46,167 @@ pub const FIRST_CUSTOM_METADATA_KIND_ID: u32 = 36;
 #[cfg(any(feature = "llvm16-0"))]
 pub const FIRST_CUSTOM_METADATA_KIND_ID: u32 = 39;
 
+// The fixed kind id of `!dbg`, which holds an instruction's debug location
+pub(crate) const LLVM_MD_DBG: u32 = 0;
+
+// Names of the pre-defined metadata kinds, indexed by kind id. LLVM only ever
+// appends to this list, so each version knows a prefix of it.
+const FIXED_METADATA_KIND_NAMES: [&str; 39] = [
+    "dbg",
+    "tbaa",
+    "prof",
+    "fpmath",
+    "range",
+    "tbaa.struct",
+    "invariant.load",
+    "alias.scope",
+    "noalias",
+    "nontemporal",
+    "llvm.mem.parallel_loop_access",
+    "nonnull",
+    "dereferenceable",
+    "dereferenceable_or_null",
+    "make.implicit",
+    "unpredictable",
+    "invariant.group",
+    "align",
+    "llvm.loop",
+    "type",
+    "section_prefix",
+    "absolute_symbol",
+    "associated",
+    "callees",
+    "irr_loop",
+    "llvm.access.group",
+    "callback",
+    "llvm.preserve.access.index",
+    "vcall_visibility",
+    "noundef",
+    "annotation",
+    "nosanitize",
+    "func_sanitize",
+    "exclude",
+    "memprof",
+    "callsite",
+    "kcfi_type",
+    "pcsections",
+    "DIAssignID",
+];
+
+/// Gets the name of a metadata kind pre-defined by LLVM, such as `"tbaa"` for
+/// the id returned by `Context::get_kind_id("tbaa")`. Returns `None` for
+/// custom kinds, whose names the LLVM C API cannot look up.
+pub fn get_fixed_metadata_kind_name(kind_id: u32) -> Option<&'static str> {
+    if kind_id >= FIRST_CUSTOM_METADATA_KIND_ID {
+        return None;
+    }
+
+    FIXED_METADATA_KIND_NAMES.get(kind_id as usize).copied()
+}
+
+// Takes ownership of `count` metadata `entries` attached to `value`, as
+// returned by `LLVMGlobalCopyAllMetadata` and friends.
+#[llvm_versions(8.0..=latest)]
+pub(crate) unsafe fn take_metadata_entries<'ctx>(
+    value: LLVMValueRef,
+    entries: *mut LLVMValueMetadataEntry,
+    count: usize,
+) -> Vec<(u32, MetadataValue<'ctx>)> {
+    if entries.is_null() {
+        return Vec::new();
+    }
+
+    let context = LLVMGetTypeContext(LLVMTypeOf(value));
+    let attachments = (0..count as u32)
+        .map(|index| {
+            let kind_id = LLVMValueMetadataEntriesGetKind(entries, index);
+            let metadata = LLVMValueMetadataEntriesGetMetadata(entries, index);
+
+            (kind_id, MetadataValue::new(LLVMMetadataAsValue(context, metadata)))
+        })
+        .collect();
+
+    LLVMDisposeValueMetadataEntries(entries);
+
+    attachments
+}
+
+// Names the kinds of a value's metadata `attachments`, given in the order
+// LLVM lists them, ie by kind id with `!dbg` first. Custom kind names can't be
+// looked up through the C API, so they're read from `printed`, the value's
+// IR, whose declaring line ends with its attachments as `!kind !node` pairs.
+#[llvm_versions(8.0..=latest)]
+pub(crate) fn name_metadata_kinds<'ctx>(
+    printed: &str,
+    attachments: Vec<(u32, MetadataValue<'ctx>)>,
+) -> Vec<(String, MetadataValue<'ctx>)> {
+    // Skips comments such as a function's attributes
+    let line = printed
+        .lines()
+        .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with(';'))
+        .unwrap_or_default();
+    let printed_names = parse_metadata_kind_names(line);
+    let printed_names = &printed_names[printed_names.len().saturating_sub(attachments.len())..];
+
+    assert_eq!(
+        printed_names.len(),
+        attachments.len(),
+        "Printed IR should name every metadata attachment"
+    );
+
+    attachments
+        .into_iter()
+        .zip(printed_names)
+        .map(|((kind_id, metadata), printed_name)| {
+            let name = get_fixed_metadata_kind_name(kind_id).map_or_else(|| printed_name.clone(), str::to_owned);
+
+            (name, metadata)
+        })
+        .collect()
+}
+
+// Finds every ` !kind !` in `line`, unescaping the `\XX` hex escapes LLVM
+// uses for characters other than `[-a-zA-Z$._0-9]`.
+#[llvm_versions(8.0..=latest)]
+fn parse_metadata_kind_names(line: &str) -> Vec<String> {
+    let is_name_byte = |byte: u8| byte.is_ascii_alphanumeric() || b"-$._\\".contains(&byte);
+    let bytes = line.as_bytes();
+    let mut names = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = line[position..].find(" !") {
+        let start = position + offset + 2;
+        let end = start + bytes[start..].iter().take_while(|&&byte| is_name_byte(byte)).count();
+
+        position = start;
+
+        // `!0` refers to a node rather than naming a kind
+        if end == start || bytes[start].is_ascii_digit() || !line[end..].starts_with(" !") {
+            continue;
+        }
+
+        let mut name = Vec::with_capacity(end - start);
+        let mut index = start;
+
+        while index < end {
+            match line.get(index + 1..index + 3).map(|hex| u8::from_str_radix(hex, 16)) {
+                Some(Ok(byte)) if bytes[index] == b'\\' => {
+                    name.push(byte);
+                    index += 3;
+                },
+                _ => {
+                    name.push(bytes[index]);
+                    index += 1;
+                },
+            }
+        }
+
+        names.push(String::from_utf8_lossy(&name).into_owned());
+    }
+
+    names
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Hash)]
 pub struct MetadataValue<'ctx> {
     metadata_value: Value<'ctx>,
@@ -10,13 +10,26 @@ mod enums;
 mod float_value;
 mod fn_value;
 mod generic_value;
+#[deny(missing_docs)]
+mod global_alias_value;
 mod global_value;
+#[deny(missing_docs)]
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0"
+)))]
+mod ifunc_value;
 mod instruction_value;
 mod int_value;
 mod metadata_value;
 mod phi_value;
 mod ptr_value;
 mod struct_value;
+#[deny(missing_docs)]
+mod switch_value;
 mod traits;
 mod vec_value;
 
@@ -34,15 +47,21 @@ pub use crate::values::enums::{AggregateValueEnum, AnyValueEnum, BasicMetadataVa
 pub use crate::values::float_value::FloatValue;
 pub use crate::values::fn_value::FunctionValue;
 pub use crate::values::generic_value::GenericValue;
+pub use crate::values::global_alias_value::GlobalAliasValue;
 pub use crate::values::global_value::GlobalValue;
 #[llvm_versions(7.0..=latest)]
 pub use crate::values::global_value::UnnamedAddress;
+#[llvm_versions(9.0..=latest)]
+pub use crate::values::ifunc_value::IFuncValue;
 pub use crate::values::instruction_value::{InstructionOpcode, InstructionValue};
 pub use crate::values::int_value::IntValue;
-pub use crate::values::metadata_value::{MetadataValue, FIRST_CUSTOM_METADATA_KIND_ID};
-pub use crate::values::phi_value::PhiValue;
+#[llvm_versions(8.0..=latest)]
+pub(crate) use crate::values::metadata_value::take_metadata_entries;
+pub use crate::values::metadata_value::{get_fixed_metadata_kind_name, MetadataValue, FIRST_CUSTOM_METADATA_KIND_ID};
+pub use crate::values::phi_value::{IncomingIter, PhiValue};
 pub use crate::values::ptr_value::PointerValue;
 pub use crate::values::struct_value::StructValue;
+pub use crate::values::switch_value::{SwitchCaseIter, SwitchValue};
 pub use crate::values::traits::AsValueRef;
 pub use crate::values::traits::{AggregateValue, AnyValue, BasicValue, FloatMathValue, IntMathValue, PointerMathValue};
 pub use crate::values::vec_value::VectorValue;
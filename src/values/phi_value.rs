@@ -1,4 +1,11 @@
-use llvm_sys::core::{LLVMAddIncoming, LLVMCountIncoming, LLVMGetIncomingBlock, LLVMGetIncomingValue};
+#[llvm_versions(4.0..=6.0)]
+use llvm_sys::core::LLVMSetValueName;
+#[llvm_versions(7.0..=latest)]
+use llvm_sys::core::LLVMSetValueName2;
+use llvm_sys::core::{
+    LLVMAddIncoming, LLVMCountIncoming, LLVMGetIncomingBlock, LLVMGetIncomingValue, LLVMGetMetadata,
+    LLVMGetTypeContext, LLVMInstructionEraseFromParent, LLVMSetMetadata, LLVMSetOperand, LLVMTypeOf,
+};
 use llvm_sys::prelude::{LLVMBasicBlockRef, LLVMValueRef};
 use std::convert::TryFrom;
 
@@ -6,6 +13,10 @@ use std::ffi::CStr;
 use std::fmt::{self, Display};
 
 use crate::basic_block::BasicBlock;
+use crate::context::ContextRef;
+use crate::types::BasicTypeEnum;
+#[llvm_versions(4.0..=7.0)]
+use crate::values::metadata_value::LLVM_MD_DBG;
 use crate::values::traits::AsValueRef;
 use crate::values::{BasicValue, BasicValueEnum, InstructionOpcode, InstructionValue, Value};
 
@@ -62,6 +73,199 @@ impl<'ctx> PhiValue<'ctx> {
         Some((value, basic_block))
     }
 
+    /// An iterator over the `(value, basic block)` pairs flowing into this `PhiValue`.
+    pub fn incoming(self) -> IncomingIter<'ctx> {
+        IncomingIter {
+            phi_value: self,
+            index: 0,
+        }
+    }
+
+    /// Replaces the incoming value at `index`, keeping its basic block.
+    /// Returns `false` if there is no incoming value at `index`.
+    pub fn set_incoming_value(self, index: u32, value: &dyn BasicValue<'ctx>) -> bool {
+        if index >= self.count_incoming() {
+            return false;
+        }
+
+        // A phi's operands are exactly its incoming values.
+        unsafe { LLVMSetOperand(self.as_value_ref(), index, value.as_value_ref()) }
+
+        true
+    }
+
+    /// Replaces the incoming basic block at `index`, keeping its value.
+    /// Returns `None` if there is no incoming value at `index`.
+    ///
+    /// The LLVM C API cannot modify a phi's incoming blocks, so this rebuilds
+    /// the phi in place and replaces all uses of the original with it. The
+    /// returned `PhiValue` must be used from then on; `self` is no longer valid.
+    /// The new phi keeps the original's name and metadata attachments, though
+    /// before LLVM 8 only its debug location can be carried over.
+    pub fn set_incoming_block(self, index: u32, basic_block: BasicBlock<'ctx>) -> Option<PhiValue<'ctx>> {
+        if index >= self.count_incoming() {
+            return None;
+        }
+
+        let incoming = self
+            .incoming()
+            .enumerate()
+            .map(|(i, (value, bb))| {
+                if i as u32 == index {
+                    (value, basic_block)
+                } else {
+                    (value, bb)
+                }
+            })
+            .collect();
+
+        Some(self.rebuild(incoming))
+    }
+
+    /// Removes the incoming value and block at `index`.
+    /// Returns `None` if there is no incoming value at `index`.
+    ///
+    /// Like `set_incoming_block`, this rebuilds the phi, so the returned
+    /// `PhiValue` must be used from then on.
+    pub fn remove_incoming(self, index: u32) -> Option<PhiValue<'ctx>> {
+        if index >= self.count_incoming() {
+            return None;
+        }
+
+        let incoming = self
+            .incoming()
+            .enumerate()
+            .filter(|&(i, _)| i as u32 != index)
+            .map(|(_, pair)| pair)
+            .collect();
+
+        Some(self.rebuild(incoming))
+    }
+
+    /// Removes every incoming value coming from `basic_block`, such as when
+    /// that block is deleted or no longer branches to this phi's block.
+    /// Returns `None` if `basic_block` is not an incoming block.
+    ///
+    /// Like `set_incoming_block`, this rebuilds the phi, so the returned
+    /// `PhiValue` must be used from then on.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    ///
+    /// let context = Context::create();
+    /// let builder = context.create_builder();
+    /// let module = context.create_module("my_mod");
+    /// let bool_type = context.bool_type();
+    /// let fn_type = context.void_type().fn_type(&[], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    /// let then_block = context.append_basic_block(fn_value, "then");
+    /// let else_block = context.append_basic_block(fn_value, "else");
+    /// let merge_block = context.append_basic_block(fn_value, "merge");
+    /// let false_val = bool_type.const_zero();
+    /// let true_val = bool_type.const_all_ones();
+    ///
+    /// builder.position_at_end(merge_block);
+    ///
+    /// let phi = builder.build_phi(bool_type, "phi");
+    ///
+    /// phi.add_incoming(&[(&false_val, then_block), (&true_val, else_block)]);
+    ///
+    /// let phi = phi.remove_incoming_block(then_block).unwrap();
+    ///
+    /// assert_eq!(phi.count_incoming(), 1);
+    /// assert_eq!(phi.get_name().to_str(), Ok("phi"));
+    /// ```
+    pub fn remove_incoming_block(self, basic_block: BasicBlock<'ctx>) -> Option<PhiValue<'ctx>> {
+        let (removed, kept): (Vec<_>, Vec<_>) = self.incoming().partition(|&(_, bb)| bb == basic_block);
+
+        if removed.is_empty() {
+            return None;
+        }
+
+        Some(self.rebuild(kept))
+    }
+
+    /// Gets the single value this `PhiValue` always merges together, if any.
+    /// Incoming values which are this phi itself are ignored.
+    ///
+    /// Returns `None` if the incoming values differ, or there are none.
+    pub fn has_constant_value(self) -> Option<BasicValueEnum<'ctx>> {
+        let mut values = self
+            .incoming()
+            .map(|(value, _)| value)
+            .filter(|value| value.as_value_ref() != self.as_value_ref());
+        let first = values.next()?;
+
+        if values.all(|value| value == first) {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    // Creates a new phi right before this one with the given incoming values,
+    // then replaces and erases this one. The name and metadata attachments,
+    // including the debug location, are carried over.
+    fn rebuild(self, incoming: Vec<(BasicValueEnum<'ctx>, BasicBlock<'ctx>)>) -> PhiValue<'ctx> {
+        let name = self.get_name().to_owned();
+        let context = unsafe { ContextRef::new(LLVMGetTypeContext(LLVMTypeOf(self.as_value_ref()))) };
+        let builder = context.create_builder();
+        let type_ = unsafe { BasicTypeEnum::new(LLVMTypeOf(self.as_value_ref())) };
+
+        builder.position_before(&self.as_instruction());
+
+        let new_phi = builder.build_phi(type_, "");
+        let incoming: Vec<(&dyn BasicValue<'ctx>, BasicBlock<'ctx>)> = incoming
+            .iter()
+            .map(|(value, bb)| (value as &dyn BasicValue<'ctx>, *bb))
+            .collect();
+
+        new_phi.add_incoming(&incoming);
+        self.copy_metadata_to(new_phi);
+        self.replace_all_uses_with(&new_phi);
+
+        unsafe { LLVMInstructionEraseFromParent(self.as_value_ref()) }
+
+        // Set after erasing so LLVM doesn't uniquify the name with a suffix.
+        // The raw bytes are used as names need not be valid UTF-8
+        #[cfg(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0"))]
+        unsafe {
+            LLVMSetValueName(new_phi.as_value_ref(), name.as_ptr())
+        }
+        #[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0")))]
+        unsafe {
+            LLVMSetValueName2(new_phi.as_value_ref(), name.as_ptr(), name.as_bytes().len())
+        }
+
+        new_phi
+    }
+
+    fn copy_metadata_to(self, new_phi: PhiValue<'ctx>) {
+        for kind_id in self.get_metadata_kind_ids() {
+            unsafe {
+                let metadata = LLVMGetMetadata(self.as_value_ref(), kind_id);
+
+                LLVMSetMetadata(new_phi.as_value_ref(), kind_id, metadata);
+            }
+        }
+    }
+
+    #[llvm_versions(4.0..=7.0)]
+    fn get_metadata_kind_ids(self) -> Vec<u32> {
+        // All attachments can't be enumerated before LLVM 8, so only the
+        // debug location is carried over
+        vec![LLVM_MD_DBG]
+    }
+
+    #[llvm_versions(8.0..=latest)]
+    fn get_metadata_kind_ids(self) -> Vec<u32> {
+        let metadata = self.as_instruction().all_metadata_by_kind_id();
+
+        metadata.into_iter().map(|(kind_id, _)| kind_id).collect()
+    }
+
     /// Gets the name of a `ArrayValue`. If the value is a constant, this will
     /// return an empty string.
     pub fn get_name(&self) -> &CStr {
@@ -120,3 +324,22 @@ impl<'ctx> TryFrom<InstructionValue<'ctx>> for PhiValue<'ctx> {
         }
     }
 }
+
+/// Iterate over all the incoming values and blocks of a `PhiValue`
+#[derive(Debug)]
+pub struct IncomingIter<'ctx> {
+    phi_value: PhiValue<'ctx>,
+    index: u32,
+}
+
+impl<'ctx> Iterator for IncomingIter<'ctx> {
+    type Item = (BasicValueEnum<'ctx>, BasicBlock<'ctx>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let incoming = self.phi_value.get_incoming(self.index)?;
+
+        self.index += 1;
+
+        Some(incoming)
+    }
+}
@@ -0,0 +1,287 @@
+use llvm_sys::core::{
+    LLVMAddCase, LLVMGetNumSuccessors, LLVMGetOperand, LLVMGetSuccessor, LLVMGetSwitchDefaultDest, LLVMGetTypeContext,
+    LLVMInstructionEraseFromParent, LLVMSetMetadata, LLVMSetSuccessor, LLVMTypeOf,
+};
+use llvm_sys::prelude::LLVMValueRef;
+
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::fmt::{self, Display};
+
+use crate::basic_block::BasicBlock;
+use crate::context::ContextRef;
+#[llvm_versions(4.0..=7.0)]
+use crate::values::metadata_value::LLVM_MD_DBG;
+use crate::values::traits::AsValueRef;
+use crate::values::{BasicMetadataValueEnum, InstructionOpcode, InstructionValue, IntValue, MetadataValue, Value};
+
+use super::AnyValue;
+
+/// A `switch` instruction, which transfers control flow to one of several
+/// destinations depending on the value of an integer condition.
+///
+/// A `SwitchValue` can be obtained from the `InstructionValue` returned by
+/// `Builder::build_switch` via `TryFrom`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct SwitchValue<'ctx> {
+    switch_value: Value<'ctx>,
+}
+
+impl<'ctx> SwitchValue<'ctx> {
+    pub(crate) unsafe fn new(value: LLVMValueRef) -> Self {
+        assert!(!value.is_null());
+
+        SwitchValue {
+            switch_value: Value::new(value),
+        }
+    }
+
+    /// Gets the integer value this `SwitchValue` is switching on.
+    pub fn get_condition(self) -> IntValue<'ctx> {
+        unsafe { IntValue::new(LLVMGetOperand(self.as_value_ref(), 0)) }
+    }
+
+    /// Adds a case to this `SwitchValue`, branching to `destination` when
+    /// the condition equals `on_value`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::values::SwitchValue;
+    /// use std::convert::TryFrom;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let builder = context.create_builder();
+    /// let i8_type = context.i8_type();
+    /// let fn_type = context.void_type().fn_type(&[i8_type.into()], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    /// let entry = context.append_basic_block(fn_value, "entry");
+    /// let default = context.append_basic_block(fn_value, "default");
+    /// let zero = context.append_basic_block(fn_value, "zero");
+    /// let value = fn_value.get_first_param().unwrap().into_int_value();
+    ///
+    /// builder.position_at_end(entry);
+    ///
+    /// let switch = SwitchValue::try_from(builder.build_switch(value, default, &[])).unwrap();
+    ///
+    /// switch.add_case(i8_type.const_zero(), zero);
+    ///
+    /// assert_eq!(switch.count_cases(), 1);
+    /// assert_eq!(switch.get_case(0), Some((i8_type.const_zero(), zero)));
+    /// ```
+    pub fn add_case(self, on_value: IntValue<'ctx>, destination: BasicBlock<'ctx>) {
+        unsafe { LLVMAddCase(self.as_value_ref(), on_value.as_value_ref(), destination.basic_block) }
+    }
+
+    /// Counts the number of cases in this `SwitchValue`, not including the
+    /// default destination.
+    pub fn count_cases(self) -> u32 {
+        // The default destination is always the first successor.
+        unsafe { LLVMGetNumSuccessors(self.as_value_ref()) - 1 }
+    }
+
+    /// Gets the value and destination of the case at `index`, if it exists.
+    pub fn get_case(self, index: u32) -> Option<(IntValue<'ctx>, BasicBlock<'ctx>)> {
+        if index >= self.count_cases() {
+            return None;
+        }
+
+        // Operands are laid out as [condition, default, value0, dest0, value1, dest1, ...]
+        let value = unsafe { IntValue::new(LLVMGetOperand(self.as_value_ref(), 2 * (index + 1))) };
+        let destination =
+            unsafe { BasicBlock::new(LLVMGetSuccessor(self.as_value_ref(), index + 1)).expect("Invalid BasicBlock") };
+
+        Some((value, destination))
+    }
+
+    /// An iterator over the `(value, destination)` pairs of this `SwitchValue`,
+    /// not including the default destination.
+    pub fn cases(self) -> SwitchCaseIter<'ctx> {
+        SwitchCaseIter {
+            switch_value: self,
+            index: 0,
+        }
+    }
+
+    /// Gets the `BasicBlock` control flow is transferred to when no case matches.
+    pub fn default_dest(self) -> BasicBlock<'ctx> {
+        unsafe { BasicBlock::new(LLVMGetSwitchDefaultDest(self.as_value_ref())).expect("Invalid BasicBlock") }
+    }
+
+    /// Sets the `BasicBlock` control flow is transferred to when no case matches.
+    pub fn set_default_dest(self, destination: BasicBlock<'ctx>) {
+        unsafe { LLVMSetSuccessor(self.as_value_ref(), 0, destination.basic_block) }
+    }
+
+    /// Removes the case at `index`, returning `None` if it does not exist.
+    ///
+    /// The LLVM C API has no way to shrink a switch's operand list, so this
+    /// rebuilds the switch in place without the removed case and erases the
+    /// original. The returned `SwitchValue` must be used from then on; `self`
+    /// is no longer valid. Metadata attached to the original switch, such as
+    /// its debug location, is carried over, and the removed case's weight is
+    /// dropped from its `branch_weights` profile metadata (`!prof`).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use inkwell::context::Context;
+    /// use inkwell::values::SwitchValue;
+    /// use std::convert::TryFrom;
+    ///
+    /// let context = Context::create();
+    /// let module = context.create_module("my_mod");
+    /// let builder = context.create_builder();
+    /// let i8_type = context.i8_type();
+    /// let fn_type = context.void_type().fn_type(&[i8_type.into()], false);
+    /// let fn_value = module.add_function("my_fn", fn_type, None);
+    /// let entry = context.append_basic_block(fn_value, "entry");
+    /// let default = context.append_basic_block(fn_value, "default");
+    /// let zero = context.append_basic_block(fn_value, "zero");
+    /// let one = context.append_basic_block(fn_value, "one");
+    /// let value = fn_value.get_first_param().unwrap().into_int_value();
+    /// let i8_zero = i8_type.const_int(0, false);
+    /// let i8_one = i8_type.const_int(1, false);
+    ///
+    /// builder.position_at_end(entry);
+    ///
+    /// let switch = builder.build_switch(value, default, &[(i8_zero, zero), (i8_one, one)]);
+    /// let switch = SwitchValue::try_from(switch).unwrap();
+    /// let switch = switch.remove_case(0).unwrap();
+    ///
+    /// assert_eq!(switch.cases().collect::<Vec<_>>(), vec![(i8_one, one)]);
+    /// assert_eq!(entry.get_terminator(), Some(switch.as_instruction()));
+    /// ```
+    pub fn remove_case(self, index: u32) -> Option<SwitchValue<'ctx>> {
+        if index >= self.count_cases() {
+            return None;
+        }
+
+        let cases: Vec<_> = self
+            .cases()
+            .enumerate()
+            .filter(|&(i, _)| i as u32 != index)
+            .map(|(_, case)| case)
+            .collect();
+
+        let context = self.get_context();
+        let builder = context.create_builder();
+
+        builder.position_before(&self.as_instruction());
+
+        let new_switch = builder.build_switch(self.get_condition(), self.default_dest(), &cases);
+        let prof_kind_id = context.get_kind_id("prof");
+
+        for kind_id in self.get_metadata_kind_ids() {
+            let metadata = match self.as_instruction().get_metadata(kind_id) {
+                Some(metadata) if kind_id == prof_kind_id => match self.remove_branch_weight(metadata, index) {
+                    Some(metadata) => metadata,
+                    // Other profile metadata no longer matches the successors
+                    None => continue,
+                },
+                Some(metadata) => metadata,
+                None => continue,
+            };
+
+            unsafe { LLVMSetMetadata(new_switch.as_value_ref(), kind_id, metadata.as_value_ref()) }
+        }
+
+        unsafe {
+            LLVMInstructionEraseFromParent(self.as_value_ref());
+
+            Some(SwitchValue::new(new_switch.as_value_ref()))
+        }
+    }
+
+    // Rebuilds `branch_weights` metadata without the weight of the case at
+    // `index`. The weights follow the name, starting with the default
+    // destination's.
+    fn remove_branch_weight(self, metadata: MetadataValue<'ctx>, index: u32) -> Option<MetadataValue<'ctx>> {
+        let mut operands = metadata.get_node_values();
+
+        match operands.first() {
+            Some(BasicMetadataValueEnum::MetadataValue(name))
+                if name.get_string_value().map(CStr::to_bytes) == Some(b"branch_weights") => {},
+            _ => return None,
+        }
+
+        if operands.len() != self.count_cases() as usize + 2 {
+            return None;
+        }
+
+        operands.remove(index as usize + 2);
+
+        Some(self.get_context().metadata_node(&operands))
+    }
+
+    #[llvm_versions(4.0..=7.0)]
+    fn get_metadata_kind_ids(self) -> Vec<u32> {
+        // All attachments can't be enumerated before LLVM 8, so only the
+        // debug location and profile metadata are carried over
+        vec![LLVM_MD_DBG, self.get_context().get_kind_id("prof")]
+    }
+
+    #[llvm_versions(8.0..=latest)]
+    fn get_metadata_kind_ids(self) -> Vec<u32> {
+        let metadata = self.as_instruction().all_metadata_by_kind_id();
+
+        metadata.into_iter().map(|(kind_id, _)| kind_id).collect()
+    }
+
+    fn get_context(self) -> ContextRef<'ctx> {
+        unsafe { ContextRef::new(LLVMGetTypeContext(LLVMTypeOf(self.as_value_ref()))) }
+    }
+
+    // SubType: -> InstructionValue<Switch>
+    /// Gets the underlying `InstructionValue` of this `SwitchValue`.
+    pub fn as_instruction(self) -> InstructionValue<'ctx> {
+        self.switch_value
+            .as_instruction()
+            .expect("SwitchValue should always be a Switch InstructionValue")
+    }
+}
+
+unsafe impl AsValueRef for SwitchValue<'_> {
+    fn as_value_ref(&self) -> LLVMValueRef {
+        self.switch_value.value
+    }
+}
+
+impl Display for SwitchValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_to_string())
+    }
+}
+
+impl<'ctx> TryFrom<InstructionValue<'ctx>> for SwitchValue<'ctx> {
+    type Error = ();
+
+    fn try_from(value: InstructionValue) -> Result<Self, Self::Error> {
+        if value.get_opcode() == InstructionOpcode::Switch {
+            unsafe { Ok(SwitchValue::new(value.as_value_ref())) }
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Iterate over the cases of a `SwitchValue`
+#[derive(Debug)]
+pub struct SwitchCaseIter<'ctx> {
+    switch_value: SwitchValue<'ctx>,
+    index: u32,
+}
+
+impl<'ctx> Iterator for SwitchCaseIter<'ctx> {
+    type Item = (IntValue<'ctx>, BasicBlock<'ctx>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let case = self.switch_value.get_case(self.index)?;
+
+        self.index += 1;
+
+        Some(case)
+    }
+}
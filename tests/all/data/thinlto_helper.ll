@@ -0,0 +1,8 @@
+; Built with `opt -module-summary -module-hash thinlto_helper.ll -o thinlto_helper.bc`
+; from LLVM 14, since the C API can't write ThinLTO summaries
+target datalayout = "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+target triple = "x86_64-unknown-linux-gnu"
+
+define i32 @helper() {
+  ret i32 42
+}
@@ -3,20 +3,73 @@
 #[macro_use]
 extern crate inkwell_internals;
 
+#[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0", feature = "llvm7-0")))]
+mod test_alias_scope;
 mod test_attributes;
 mod test_basic_block;
 mod test_builder;
 mod test_context;
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0"
+)))]
+mod test_coverage;
 #[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0")))]
 mod test_debug_info;
 mod test_execution_engine;
 mod test_instruction_conversion;
 mod test_instruction_values;
 mod test_intrinsics;
+#[cfg(not(any(feature = "llvm4-0", feature = "llvm5-0", feature = "llvm6-0", feature = "llvm7-0")))]
+mod test_loop_hints;
+#[cfg(feature = "lto")]
+mod test_lto;
 mod test_module;
 mod test_object_file;
 mod test_passes;
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0"
+)))]
+mod test_pgo;
+mod test_pipeline;
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0"
+)))]
+mod test_remarks;
+#[cfg(not(any(
+    feature = "llvm4-0",
+    feature = "llvm5-0",
+    feature = "llvm6-0",
+    feature = "llvm7-0",
+    feature = "llvm8-0",
+    feature = "llvm9-0",
+    feature = "llvm10-0",
+    feature = "llvm11-0",
+    feature = "llvm12-0",
+    feature = "llvm13-0"
+)))]
+mod test_sanitizers;
 mod test_targets;
 mod test_tari_example;
+mod test_tbaa;
 mod test_types;
 mod test_values;
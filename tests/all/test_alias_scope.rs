@@ -0,0 +1,59 @@
+use inkwell::alias_scope::AliasScopeBuilder;
+use inkwell::context::Context;
+use inkwell::AddressSpace;
+
+#[test]
+fn test_alias_scope_builder() {
+    let context = Context::create();
+    let module = context.create_module("my_mod");
+    let builder = context.create_builder();
+    let scopes = AliasScopeBuilder::new(&context);
+    let i32_type = context.i32_type();
+    let i32_ptr_type = i32_type.ptr_type(AddressSpace::default());
+    let fn_type = context
+        .void_type()
+        .fn_type(&[i32_ptr_type.into(), i32_ptr_type.into()], false);
+    let function = module.add_function("my_fn", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let dst = function.get_nth_param(0).unwrap().into_pointer_value();
+    let src = function.get_nth_param(1).unwrap().into_pointer_value();
+
+    assert_eq!(scopes.get_alias_scope_kind_id(), context.get_kind_id("alias.scope"));
+    assert_eq!(scopes.get_noalias_kind_id(), context.get_kind_id("noalias"));
+
+    let domain = scopes.create_domain(Some("my_fn"));
+    let other_domain = scopes.create_domain(Some("my_fn"));
+    let unnamed_domain = scopes.create_domain(None);
+    let dst_scope = scopes.create_scope(domain, Some("my_fn: dst"));
+    let src_scope = scopes.create_scope(domain, Some("my_fn: src"));
+
+    assert_ne!(domain, other_domain);
+    assert_eq!(domain.get_node_size(), 2);
+    assert_eq!(domain.get_node_values()[0].into_metadata_value(), domain);
+    assert_eq!(unnamed_domain.get_node_size(), 1);
+    assert_eq!(dst_scope.get_node_size(), 3);
+    assert_eq!(dst_scope.get_node_values()[0].into_metadata_value(), dst_scope);
+    assert_eq!(dst_scope.get_node_values()[1].into_metadata_value(), domain);
+
+    let scope_list = scopes.create_scope_list(&[dst_scope, src_scope]);
+
+    assert_eq!(scope_list.get_node_size(), 2);
+
+    builder.position_at_end(entry);
+
+    let store = builder.build_store(dst, i32_type.const_zero());
+    let other_store = builder.build_store(src, i32_type.const_zero());
+    let ret = builder.build_return(None);
+
+    assert!(scopes.set_alias_scopes(store, &[dst_scope]).is_ok());
+    assert!(scopes.set_noalias_scopes(store, &[src_scope]).is_ok());
+    assert!(scopes.set_alias_scopes(other_store, &[src_scope]).is_ok());
+    assert!(scopes.set_noalias_scopes(other_store, &[dst_scope]).is_ok());
+    assert!(scopes.set_alias_scopes(ret, &[dst_scope]).is_err());
+
+    let alias_scopes = store.get_metadata(scopes.get_alias_scope_kind_id()).unwrap();
+
+    assert_eq!(alias_scopes.get_node_values()[0].into_metadata_value(), dst_scope);
+
+    assert!(module.verify().is_ok());
+}
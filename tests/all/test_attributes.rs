@@ -1,5 +1,6 @@
-use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::attributes::{Attr, Attribute, AttributeLoc};
 use inkwell::context::Context;
+use inkwell::AddressSpace;
 
 #[test]
 fn test_enum_attribute_kinds() {
@@ -208,3 +209,86 @@ fn test_attributes_on_call_site_values() {
         .get_enum_attribute(AttributeLoc::Return, align_attribute)
         .is_some());
 }
+
+#[test]
+fn test_typed_attributes() {
+    let context = Context::create();
+    let module = context.create_module("my_mod");
+    let i8_ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+    let fn_type = context.void_type().fn_type(&[i8_ptr_type.into()], false);
+    let fn_value = module.add_function("my_fn", fn_type, None);
+
+    assert_eq!(
+        Attr::NoAlias.get_kind_id(),
+        Some(Attribute::get_named_enum_kind_id("noalias"))
+    );
+    assert_eq!(Attr::Align(8).to_string(), "align 8");
+    assert_eq!(Attr::Dereferenceable(16).to_string(), "dereferenceable(16)");
+    assert_eq!(Attr::NoUnwind.to_string(), "nounwind");
+
+    assert!(Attr::Align(3).to_attribute(&context).is_err());
+    assert!(Attr::Dereferenceable(0).to_attribute(&context).is_err());
+
+    let attrs = [Attr::NoAlias, Attr::NonNull, Attr::Align(16), Attr::Dereferenceable(32)];
+
+    for attr in attrs {
+        let attribute = attr.to_attribute(&context).unwrap();
+
+        assert_eq!(Attr::from_attribute(attribute), Some(attr));
+
+        fn_value.add_attribute(AttributeLoc::Param(0), attribute);
+    }
+
+    let no_unwind = Attr::NoUnwind.to_attribute(&context).unwrap();
+
+    fn_value.add_attribute(AttributeLoc::Function, no_unwind);
+
+    assert_eq!(fn_value.count_attributes(AttributeLoc::Param(0)), 4);
+    assert_eq!(
+        fn_value.get_enum_attribute(AttributeLoc::Function, Attr::NoUnwind.get_kind_id().unwrap()),
+        Some(no_unwind)
+    );
+
+    let string_attribute = context.create_string_attribute("my_key", "my_val");
+
+    assert_eq!(Attr::from_attribute(string_attribute), None);
+}
+
+#[llvm_versions(12.0..=latest)]
+#[test]
+fn test_typed_type_attributes() {
+    use inkwell::types::AnyType;
+
+    let context = Context::create();
+    let i32_type = context.i32_type().as_any_type_enum();
+    let by_val = Attr::ByVal(i32_type);
+    let attribute = by_val.to_attribute(&context).unwrap();
+
+    assert!(attribute.is_type());
+    assert_eq!(attribute.get_type_value(), i32_type);
+    assert_eq!(Attr::from_attribute(attribute), Some(by_val));
+    assert_eq!(Attr::SRet(i32_type).to_string(), "sret(i32)");
+}
+
+#[llvm_versions(16.0..=latest)]
+#[test]
+fn test_memory_effects_attribute() {
+    use inkwell::attributes::{MemoryAccess, MemoryEffects};
+
+    let context = Context::create();
+    let read_only = Attr::MemoryEffects(MemoryEffects::all(MemoryAccess::Read));
+    let arg_mem_only = Attr::MemoryEffects(MemoryEffects {
+        arg_mem: MemoryAccess::ReadWrite,
+        inaccessible_mem: MemoryAccess::None,
+        other_mem: MemoryAccess::None,
+    });
+
+    assert_eq!(read_only.to_string(), "memory(read)");
+    assert_eq!(arg_mem_only.to_string(), "memory(argmem: readwrite)");
+    assert!(Attr::ArgMemOnly.to_attribute(&context).is_err());
+
+    let attribute = arg_mem_only.to_attribute(&context).unwrap();
+
+    assert_eq!(attribute.get_enum_value(), 3);
+    assert_eq!(Attr::from_attribute(attribute), Some(arg_mem_only));
+}
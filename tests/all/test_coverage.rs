@@ -0,0 +1,269 @@
+use inkwell::context::Context;
+use inkwell::coverage::{Coverage, SourceRegion};
+use inkwell::execution_engine::JitFunction;
+use inkwell::passes::PassBuilderOptions;
+use inkwell::pgo::{FunctionProfile, InstrProfile};
+use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+use inkwell::values::AnyValue;
+use inkwell::{IntPredicate, OptimizationLevel};
+
+fn region(line_start: u32, column_start: u32, line_end: u32, column_end: u32) -> SourceRegion {
+    SourceRegion {
+        file: "max.src".to_string(),
+        line_start,
+        column_start,
+        line_end,
+        column_end,
+    }
+}
+
+#[test]
+fn test_coverage_regions() {
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let fn_type = context.void_type().fn_type(&[], false);
+    let function = module.add_function("main", fn_type, None);
+    let mut coverage = Coverage::new("/home/user/project");
+
+    assert!(coverage.add_function(&module, function, Vec::new()).is_err());
+    assert!(coverage
+        .add_function(&module, function, vec![region(2, 1, 1, 1)])
+        .is_err());
+    assert!(coverage
+        .add_function(&module, function, vec![region(1, 1, 3, 2)])
+        .is_ok());
+    assert!(coverage
+        .add_function(&module, function, vec![region(1, 1, 3, 2)])
+        .is_err());
+    assert!(coverage.get_function_hash(function).is_some());
+
+    builder.position_at_end(context.append_basic_block(function, "entry"));
+
+    assert!(coverage.build_counter_increment(&builder, function, 0).is_ok());
+    assert!(coverage.build_counter_increment(&builder, function, 1).is_err());
+
+    builder.build_return(None);
+
+    assert!(module.verify().is_ok());
+}
+
+#[test]
+fn test_coverage_mapping_records() {
+    Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let fn_type = context.void_type().fn_type(&[], false);
+    let mut coverage = Coverage::new("/home/user");
+    let main_region = SourceRegion {
+        file: "main.c".to_string(),
+        line_start: 3,
+        column_start: 12,
+        line_end: 5,
+        column_end: 2,
+    };
+    let builder = context.create_builder();
+    let add_function = |name| {
+        let function = module.add_function(name, fn_type, None);
+
+        builder.position_at_end(context.append_basic_block(function, "entry"));
+        builder.build_return(None);
+
+        function
+    };
+    let main = add_function("main");
+    let sorted = add_function("sorted");
+
+    coverage.add_function(&module, main, vec![main_region]).unwrap();
+    // Regions are sorted, and counters keep referring to their index
+    coverage
+        .add_function(
+            &module,
+            sorted,
+            vec![region(1, 1, 9, 2), region(6, 5, 7, 6), region(2, 5, 3, 6)],
+        )
+        .unwrap();
+
+    // Records are named after the MD5 hash of their function's name, so these
+    // follow the test suite of RFC 1321, whose empty string can't name a
+    // function
+    let hashes = [
+        ("a", "A8B6F1C0B975C10C"),
+        ("abc", "B04FD23C98500190"),
+        ("message digest", "8D93B77C7D696BF9"),
+        ("abcdefghijklmnopqrstuvwxyz", "E49261D7D3FCC3"),
+        (
+            "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+            "55C9E32BA2F4ED57",
+        ),
+    ];
+
+    for (name, _) in hashes {
+        coverage
+            .add_function(&module, add_function(name), vec![region(1, 1, 1, 2)])
+            .unwrap();
+    }
+
+    let triple = TargetMachine::get_default_triple();
+    let machine = Target::from_triple(&triple)
+        .unwrap()
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+
+    module.set_triple(&triple);
+    coverage.emit(&module, &machine, &PassBuilderOptions::create()).unwrap();
+
+    assert!(module.verify().is_ok());
+
+    for (_, hash) in hashes {
+        assert!(module.get_global(&format!("__covrec_{}", hash)).is_some());
+    }
+
+    // The record clang writes for the same `main`, from LLVM's documentation
+    // of the coverage mapping format
+    let main_record = module
+        .get_global("__covrec_DB956436E78DD5FA")
+        .unwrap()
+        .as_pointer_value()
+        .print_to_string()
+        .to_string();
+
+    assert!(main_record.contains("i64 -2624081020897602054, i32 9,"));
+    assert!(main_record.contains(r#"[9 x i8] c"\01\01\00\01\01\03\0C\02\02""#));
+
+    let sorted_record = module
+        .get_global("__covrec_8F353CD894CCDE26")
+        .unwrap()
+        .as_pointer_value()
+        .print_to_string()
+        .to_string();
+
+    assert!(sorted_record.contains(r#"c"\01\02\00\03\01\01\01\08\02\09\01\05\01\06\05\04\05\01\06""#));
+}
+
+#[test]
+fn test_coverage_instrumentation() {
+    Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i64_type = context.i64_type();
+    let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+    let function = module.add_function("max", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let then_block = context.append_basic_block(function, "then");
+    let else_block = context.append_basic_block(function, "else");
+    let mut coverage = Coverage::new("/home/user/project");
+
+    // fn max(a, b) {
+    //     if a > b {
+    //         a
+    //     } else {
+    //         b
+    //     }
+    // }
+    coverage
+        .add_function(
+            &module,
+            function,
+            vec![region(1, 15, 7, 2), region(2, 14, 4, 6), region(4, 12, 6, 6)],
+        )
+        .unwrap();
+
+    let a = function.get_nth_param(0).unwrap().into_int_value();
+    let b = function.get_nth_param(1).unwrap().into_int_value();
+
+    builder.position_at_end(entry);
+    coverage.build_counter_increment(&builder, function, 0).unwrap();
+
+    let is_greater = builder.build_int_compare(IntPredicate::UGT, a, b, "is_greater");
+
+    builder.build_conditional_branch(is_greater, then_block, else_block);
+    builder.position_at_end(then_block);
+    coverage.build_counter_increment(&builder, function, 1).unwrap();
+    builder.build_return(Some(&a));
+    builder.position_at_end(else_block);
+    coverage.build_counter_increment(&builder, function, 2).unwrap();
+    builder.build_return(Some(&b));
+
+    // Functions without increments have no counters to read
+    let empty_function = module.add_function("empty", context.void_type().fn_type(&[], false), None);
+
+    coverage
+        .add_function(&module, empty_function, vec![region(9, 14, 9, 16)])
+        .unwrap();
+    builder.position_at_end(context.append_basic_block(empty_function, "entry"));
+    builder.build_return(None);
+
+    let triple = TargetMachine::get_default_triple();
+    let machine = Target::from_triple(&triple)
+        .unwrap()
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::JITDefault,
+        )
+        .unwrap();
+    module.set_triple(&triple);
+
+    coverage
+        .emit_for_jit(&module, &machine, &PassBuilderOptions::create())
+        .unwrap();
+
+    assert!(module.verify().is_ok());
+    assert!(module.get_global("__llvm_coverage_mapping").is_some());
+    assert!(module
+        .get_globals()
+        .any(|global| global.get_name().to_bytes().starts_with(b"__covrec_")));
+
+    let execution_engine = module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+
+    unsafe {
+        let max: JitFunction<unsafe extern "C" fn(u64, u64) -> u64> = execution_engine.get_function("max").unwrap();
+
+        assert_eq!(max.call(3, 1), 3);
+        assert_eq!(max.call(5, 2), 5);
+    }
+
+    let report = coverage.read_counters(&execution_engine);
+    let counts: Vec<u64> = report.regions.iter().map(|region| region.count).collect();
+
+    assert_eq!(counts, [2, 2, 0, 0]);
+    assert_eq!(report.get_uncovered_regions().len(), 2);
+    assert_eq!(report.get_uncovered_regions()[0].region, region(4, 12, 6, 6));
+    assert_eq!(report.get_uncovered_regions()[1].function, "empty");
+
+    let mut profile = InstrProfile::new();
+
+    profile.functions.push(FunctionProfile {
+        name: "max".to_string(),
+        hash: coverage.get_function_hash(function).unwrap(),
+        counts: vec![4, 1, 3],
+    });
+
+    let counts: Vec<u64> = coverage
+        .create_report(&profile)
+        .regions
+        .iter()
+        .map(|region| region.count)
+        .collect();
+
+    assert_eq!(counts, [4, 1, 3, 0]);
+
+    // A profile of different regions doesn't apply
+    profile.functions[0].hash += 1;
+
+    assert_eq!(coverage.create_report(&profile).get_uncovered_regions().len(), 4);
+}
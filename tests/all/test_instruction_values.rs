@@ -1,4 +1,5 @@
 use inkwell::context::Context;
+use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::types::AnyTypeEnum;
 use inkwell::values::{BasicValue, InstructionOpcode::*};
 use inkwell::{AddressSpace, AtomicOrdering, AtomicRMWBinOp, FloatPredicate, IntPredicate};
@@ -618,3 +619,366 @@ fn test_find_instruction_with_name() {
     assert!(some_number.is_some());
     assert_eq!(some_number.unwrap().get_name().unwrap().to_str(), Ok("some_number"))
 }
+
+#[test]
+fn test_clone_and_move_instructions() {
+    let context = Context::create();
+    let module = context.create_module("ivs");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into(), i32_type.into()], false);
+    let fn_value = module.add_function("sum", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let exit = context.append_basic_block(fn_value, "exit");
+    let lhs = fn_value.get_first_param().unwrap().into_int_value();
+    let rhs = fn_value.get_last_param().unwrap().into_int_value();
+
+    builder.position_at_end(entry);
+
+    let add = builder.build_int_add(lhs, rhs, "add").as_instruction().unwrap();
+    let mul = builder.build_int_mul(lhs, rhs, "mul").as_instruction().unwrap();
+    let br = builder.build_unconditional_branch(exit);
+
+    builder.position_at_end(exit);
+
+    let ret = builder.build_return(Some(&lhs));
+
+    // Cloning
+    let add_clone = add.clone_instruction();
+
+    assert_eq!(add_clone.get_opcode(), Add);
+    assert!(add_clone.get_parent().is_none());
+    assert_eq!(add_clone.get_operand(0).unwrap().left(), Some(lhs.into()));
+    assert_eq!(add_clone.get_operand(1).unwrap().left(), Some(rhs.into()));
+
+    assert!(add.insert_into(entry, 0).is_err());
+    assert!(add_clone.insert_into(exit, 2).is_err());
+    assert!(add_clone.insert_into(exit, 1).is_ok());
+    assert_eq!(add_clone.get_parent(), Some(exit));
+    assert_eq!(ret.get_next_instruction(), Some(add_clone));
+
+    add_clone.erase_from_basic_block();
+
+    let add_clone = add.clone_instruction();
+
+    assert!(add_clone.insert_into(exit, 0).is_ok());
+    assert_eq!(exit.get_first_instruction(), Some(add_clone));
+    assert_eq!(add_clone.get_next_instruction(), Some(ret));
+
+    // Moving within a block
+    assert!(mul.move_before(mul).is_err());
+    assert!(mul.move_before(add).is_ok());
+    assert_eq!(entry.get_first_instruction(), Some(mul));
+    assert_eq!(mul.get_next_instruction(), Some(add));
+    assert_eq!(mul.get_name().unwrap().to_str(), Ok("mul"));
+
+    assert!(mul.move_after(add).is_ok());
+    assert_eq!(add.get_next_instruction(), Some(mul));
+    assert_eq!(mul.get_next_instruction(), Some(br));
+
+    // Moving across blocks
+    assert!(mul.move_after(ret).is_ok());
+    assert_eq!(mul.get_parent(), Some(exit));
+    assert_eq!(exit.get_last_instruction(), Some(mul));
+
+    assert!(mul.move_before(add).is_ok());
+    assert_eq!(mul.get_parent(), Some(entry));
+    assert_eq!(entry.get_first_instruction(), Some(mul));
+    assert_eq!(mul.get_name().unwrap().to_str(), Ok("mul"));
+
+    assert!(module.verify().is_ok());
+}
+
+#[test]
+fn test_branch_weights() {
+    let context = Context::create();
+    let module = context.create_module("ivs");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = context.void_type().fn_type(&[i32_type.into()], false);
+    let fn_value = module.add_function("branchy", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let middle = context.append_basic_block(fn_value, "middle");
+    let exit = context.append_basic_block(fn_value, "exit");
+    let arg = fn_value.get_first_param().unwrap().into_int_value();
+
+    builder.position_at_end(entry);
+
+    let is_zero = builder.build_int_compare(IntPredicate::EQ, arg, i32_type.const_zero(), "is_zero");
+    let cond_br = builder.build_conditional_branch(is_zero, exit, middle);
+
+    builder.position_at_end(middle);
+
+    let switch = builder.build_switch(arg, exit, &[(i32_type.const_int(1, false), exit)]);
+
+    builder.position_at_end(exit);
+
+    let ret = builder.build_return(None);
+
+    assert!(cond_br.set_branch_weights(&[1, 2, 3]).is_err());
+    assert!(cond_br.set_branch_weights(&[1, 2000]).is_ok());
+    assert!(switch.set_branch_weights(&[5, 10]).is_ok());
+    assert!(ret.set_branch_weights(&[]).is_err());
+
+    let prof_kind_id = context.get_kind_id("prof");
+    let weights = cond_br.get_metadata(prof_kind_id).unwrap();
+
+    assert_eq!(weights.get_node_size(), 3);
+    assert_eq!(
+        weights.get_node_values()[0]
+            .into_metadata_value()
+            .get_string_value()
+            .unwrap()
+            .to_str(),
+        Ok("branch_weights")
+    );
+    assert_eq!(switch.get_metadata(prof_kind_id).unwrap().get_node_size(), 3);
+
+    assert!(module.verify().is_ok());
+}
+
+#[llvm_versions(15.0..=latest)]
+#[test]
+fn test_indirect_call_profile_metadata() {
+    let context = Context::create();
+    let module = context.create_module("ivs");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let callee_type = i32_type.fn_type(&[], false);
+    let fn_type = i32_type.fn_type(&[context.i8_type().ptr_type(AddressSpace::default()).into()], false);
+    let first = module.add_function("first", callee_type, None);
+    let second = module.add_function("second", callee_type, None);
+    let fn_value = module.add_function("call_it", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let fn_ptr = fn_value.get_first_param().unwrap().into_pointer_value();
+
+    fn_value.set_entry_count(42);
+
+    builder.position_at_end(entry);
+
+    let direct_call = builder.build_call(first, &[], "direct");
+    let indirect_call = builder.build_indirect_call(callee_type, fn_ptr, &[], "indirect");
+
+    builder.build_return(Some(&indirect_call.try_as_basic_value().left().unwrap()));
+
+    assert!(direct_call.set_callees(&[first]).is_err());
+    assert!(direct_call.set_value_profile(10, &[]).is_err());
+    assert!(indirect_call.set_callees(&[]).is_err());
+    assert!(indirect_call.set_callees(&[first, second]).is_ok());
+    assert!(indirect_call
+        .set_value_profile(100, &[(0x1234_5678_9abc_def0, 70), (0x0fed_cba9_8765_4321, 30)])
+        .is_ok());
+
+    let module_ir = module.print_to_string().to_string();
+
+    assert!(module_ir.contains("!callees"));
+
+    assert!(module_ir.contains("!{ptr @first, ptr @second}"));
+    assert!(module_ir.contains("!{!\"function_entry_count\", i64 42}"));
+    assert!(module_ir
+        .contains("!{!\"VP\", i32 0, i64 100, i64 1311768467463790320, i64 70, i64 1147797409030816545, i64 30}"));
+
+    assert!(module.verify().is_ok());
+}
+
+#[llvm_versions(15.0..=latest)]
+#[test]
+fn test_load_metadata() {
+    let context = Context::create();
+    let module = context.create_module("ivs");
+    let builder = context.create_builder();
+    let i8_type = context.i8_type();
+    let i32_type = context.i32_type();
+    let ptr_type = i8_type.ptr_type(AddressSpace::default());
+    let fn_type = context.void_type().fn_type(&[ptr_type.into()], false);
+    let fn_value = module.add_function("loads", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let arg = fn_value.get_first_param().unwrap().into_pointer_value();
+
+    builder.position_at_end(entry);
+
+    let int_load = builder.build_load(i32_type, arg, "int").as_instruction_value().unwrap();
+    let ptr_load = builder.build_load(ptr_type, arg, "ptr").as_instruction_value().unwrap();
+    let store = builder.build_store(arg, i32_type.const_zero());
+
+    builder.build_return(None);
+
+    let range = (i32_type.const_int(0, false), i32_type.const_int(10, false));
+    let upper_range = (i32_type.const_int(20, false), i32_type.const_int(30, false));
+    let wrong_type_range = (i8_type.const_int(0, false), i8_type.const_int(10, false));
+
+    assert!(int_load.set_range_metadata(&[]).is_err());
+    assert!(int_load.set_range_metadata(&[wrong_type_range]).is_err());
+    assert!(int_load.set_range_metadata(&[(range.0, range.0)]).is_err());
+    assert!(ptr_load.set_range_metadata(&[range]).is_err());
+    assert!(store.set_range_metadata(&[range]).is_err());
+    assert!(int_load.set_range_metadata(&[range, upper_range]).is_ok());
+    assert_eq!(
+        int_load
+            .get_metadata(context.get_kind_id("range"))
+            .unwrap()
+            .get_node_size(),
+        4
+    );
+
+    assert!(int_load.set_nonnull_metadata().is_err());
+    assert!(int_load.set_align_metadata(8).is_err());
+    assert!(int_load.set_dereferenceable_metadata(8).is_err());
+    assert!(ptr_load.set_nonnull_metadata().is_ok());
+    assert!(ptr_load.set_align_metadata(3).is_err());
+    assert!(ptr_load.set_align_metadata(8).is_ok());
+    assert!(ptr_load.set_dereferenceable_metadata(16).is_ok());
+    assert!(ptr_load.set_dereferenceable_or_null_metadata(16).is_ok());
+
+    assert!(store.set_noundef_metadata().is_err());
+    assert!(store.set_invariant_load_metadata().is_err());
+    assert!(int_load.set_noundef_metadata().is_ok());
+    assert!(int_load.set_invariant_load_metadata().is_ok());
+    assert!(int_load.set_nontemporal_metadata().is_ok());
+    assert!(store.set_nontemporal_metadata().is_ok());
+
+    let module_ir = module.print_to_string().to_string();
+
+    assert!(module_ir.contains("!{i32 0, i32 10, i32 20, i32 30}"));
+    assert!(module_ir.contains("!nonnull"));
+    assert!(module_ir.contains("!dereferenceable_or_null"));
+    assert!(module_ir.contains("!invariant.load"));
+    assert!(module_ir.contains("!{i32 1}"));
+
+    assert!(module.verify().is_ok());
+}
+
+#[llvm_versions(8.0..=latest)]
+#[test]
+fn test_enumerate_and_remove_metadata() {
+    use inkwell::values::get_fixed_metadata_kind_name;
+
+    let context = Context::create();
+    let module = context.create_module("ivs");
+    let builder = context.create_builder();
+    let fn_type = context.void_type().fn_type(&[], false);
+    let fn_value = module.add_function("annotated", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let tbaa_kind_id = context.get_kind_id("tbaa");
+    let custom_kind_id = context.get_kind_id("my.custom.kind");
+    let md_node = context.metadata_node(&[context.metadata_string("hello").into()]);
+
+    assert_eq!(get_fixed_metadata_kind_name(0), Some("dbg"));
+    assert_eq!(get_fixed_metadata_kind_name(tbaa_kind_id), Some("tbaa"));
+    assert_eq!(
+        get_fixed_metadata_kind_name(context.get_kind_id("nonnull")),
+        Some("nonnull")
+    );
+    assert_eq!(get_fixed_metadata_kind_name(custom_kind_id), None);
+
+    builder.position_at_end(entry);
+
+    let ret = builder.build_return(None);
+
+    assert!(ret.all_metadata().is_empty());
+
+    ret.set_metadata(md_node, tbaa_kind_id).unwrap();
+    ret.set_metadata(md_node, custom_kind_id).unwrap();
+
+    assert_eq!(
+        ret.all_metadata(),
+        vec![("tbaa".to_string(), md_node), ("my.custom.kind".to_string(), md_node)]
+    );
+
+    ret.remove_metadata(tbaa_kind_id);
+
+    assert_eq!(ret.all_metadata(), vec![("my.custom.kind".to_string(), md_node)]);
+    assert!(ret.get_metadata(tbaa_kind_id).is_none());
+
+    // Names are printed with escapes for other characters
+    let odd_kind_id = context.get_kind_id("my odd\\kind!");
+
+    ret.set_metadata(md_node, odd_kind_id).unwrap();
+
+    assert_eq!(
+        ret.all_metadata(),
+        vec![
+            ("my.custom.kind".to_string(), md_node),
+            ("my odd\\kind!".to_string(), md_node)
+        ]
+    );
+
+    // Global values
+    let global = fn_value.as_global_value();
+
+    assert!(global.all_metadata().is_empty());
+
+    global.set_metadata(md_node, tbaa_kind_id);
+    global.set_metadata(md_node, custom_kind_id);
+
+    assert_eq!(
+        global.all_metadata(),
+        vec![("tbaa".to_string(), md_node), ("my.custom.kind".to_string(), md_node)]
+    );
+
+    global.remove_metadata(custom_kind_id);
+
+    assert_eq!(global.all_metadata(), vec![("tbaa".to_string(), md_node)]);
+
+    global.clear_metadata();
+
+    assert!(global.all_metadata().is_empty());
+
+    let declaration = module.add_function("declaration", fn_type, None).as_global_value();
+    let variable = module.add_global(context.i8_type(), None, "variable");
+
+    declaration.set_metadata(md_node, custom_kind_id);
+    variable.set_initializer(&context.i8_type().const_zero());
+    variable.set_metadata(md_node, odd_kind_id);
+
+    assert_eq!(
+        declaration.all_metadata(),
+        vec![("my.custom.kind".to_string(), md_node)]
+    );
+    assert_eq!(variable.all_metadata(), vec![("my odd\\kind!".to_string(), md_node)]);
+}
+
+#[llvm_versions(8.0..=latest)]
+#[test]
+fn test_all_metadata_includes_debug_location() {
+    let context = Context::create();
+    let ir = b"
+        define void @located() #0 !dbg !3 {
+            ret void, !dbg !4, !my.kind !5
+        }
+
+        attributes #0 = { nounwind }
+
+        !llvm.dbg.cu = !{!0}
+        !llvm.module.flags = !{!2}
+
+        !0 = distinct !DICompileUnit(language: DW_LANG_C99, file: !1, emissionKind: FullDebug)
+        !1 = !DIFile(filename: \"located.c\", directory: \".\")
+        !2 = !{i32 2, !\"Debug Info Version\", i32 3}
+        !3 = distinct !DISubprogram(name: \"located\", scope: !1, file: !1, unit: !0, spFlags: DISPFlagDefinition)
+        !4 = !DILocation(line: 1, scope: !3)
+        !5 = !{}
+    ";
+    let memory_buffer = MemoryBuffer::create_from_memory_range_copy(ir, "my_ir");
+    let module = context.create_module_from_ir(memory_buffer).unwrap();
+    let function = module.get_function("located").unwrap();
+    let ret = function
+        .get_first_basic_block()
+        .unwrap()
+        .get_first_instruction()
+        .unwrap();
+    let kind_names: Vec<_> = ret.all_metadata().into_iter().map(|(kind_name, _)| kind_name).collect();
+
+    assert_eq!(kind_names, ["dbg", "my.kind"]);
+    assert_eq!(ret.all_metadata()[0].1, ret.get_metadata(0).unwrap());
+
+    let function_kind_names: Vec<_> = function
+        .as_global_value()
+        .all_metadata()
+        .into_iter()
+        .map(|(kind_name, _)| kind_name)
+        .collect();
+
+    // The function's attributes are printed on a comment line before it
+    assert_eq!(function_kind_names, ["dbg"]);
+}
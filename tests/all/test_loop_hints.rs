@@ -0,0 +1,134 @@
+use inkwell::context::Context;
+use inkwell::loop_hints::{LoopHints, LoopUnroll};
+use inkwell::values::MetadataValue;
+
+fn hint_name(hint: MetadataValue) -> String {
+    let name = hint.get_node_values()[0].into_metadata_value();
+
+    name.get_string_value().unwrap().to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_loop_hints() {
+    let context = Context::create();
+    let module = context.create_module("my_mod");
+    let builder = context.create_builder();
+    let fn_type = context.void_type().fn_type(&[context.bool_type().into()], false);
+    let function = module.add_function("my_fn", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let body = context.append_basic_block(function, "body");
+    let exit = context.append_basic_block(function, "exit");
+    let done = function.get_first_param().unwrap().into_int_value();
+
+    builder.position_at_end(entry);
+    builder.build_unconditional_branch(body);
+    builder.position_at_end(body);
+
+    let latch_branch = builder.build_conditional_branch(done, exit, body);
+
+    builder.position_at_end(exit);
+
+    let ret = builder.build_return(None);
+
+    assert_eq!(
+        LoopHints::new().attach(ret),
+        Err("Loop hints can only be attached to a branch.")
+    );
+
+    let loop_id = LoopHints::new()
+        .unroll(LoopUnroll::Count(4))
+        .vectorize_enable(true)
+        .vectorize_width(8)
+        .interleave_count(2)
+        .distribute(false)
+        .must_progress(true)
+        .attach(latch_branch)
+        .unwrap();
+    let operands = loop_id.get_node_values();
+
+    assert_eq!(
+        latch_branch.get_metadata(context.get_kind_id("llvm.loop")),
+        Some(loop_id)
+    );
+    assert_eq!(operands.len(), 7);
+    assert_eq!(operands[0].into_metadata_value(), loop_id);
+
+    let names: Vec<_> = operands[1..]
+        .iter()
+        .map(|operand| hint_name(operand.into_metadata_value()))
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "llvm.loop.unroll.count",
+            "llvm.loop.vectorize.enable",
+            "llvm.loop.vectorize.width",
+            "llvm.loop.interleave.count",
+            "llvm.loop.distribute.enable",
+            "llvm.loop.mustprogress",
+        ]
+    );
+
+    let unroll_count = operands[1].into_metadata_value().get_node_values()[1].into_int_value();
+
+    assert_eq!(unroll_count.get_zero_extended_constant(), Some(4));
+    assert!(module.verify().is_ok());
+
+    // Each loop gets its own distinct id, even with identical hints
+    let other_id = LoopHints::new()
+        .unroll(LoopUnroll::Disable)
+        .attach(latch_branch)
+        .unwrap();
+    let same_hints_id = LoopHints::new()
+        .unroll(LoopUnroll::Disable)
+        .attach(latch_branch)
+        .unwrap();
+
+    assert_ne!(other_id, same_hints_id);
+    assert_eq!(
+        latch_branch.get_metadata(context.get_kind_id("llvm.loop")),
+        Some(same_hints_id)
+    );
+}
+
+#[llvm_versions(15.0..=latest)]
+#[test]
+fn test_loop_hints_parallel_accesses() {
+    use inkwell::memory_buffer::MemoryBuffer;
+
+    let context = Context::create();
+    let ir = b"
+        define void @my_fn(i1 %done, ptr %ptr) {
+        entry:
+            br label %body
+        body:
+            store i32 0, ptr %ptr, !llvm.access.group !0
+            br i1 %done, label %exit, label %body
+        exit:
+            ret void
+        }
+
+        !0 = distinct !{}
+    ";
+    let memory_buffer = MemoryBuffer::create_from_memory_range_copy(ir, "my_ir");
+    let module = context.create_module_from_ir(memory_buffer).unwrap();
+    let entry = module.get_function("my_fn").unwrap().get_first_basic_block().unwrap();
+    let body = entry.get_next_basic_block().unwrap();
+    let store = body.get_first_instruction().unwrap();
+    let latch_branch = store.get_next_instruction().unwrap();
+    let access_group = store.get_metadata(context.get_kind_id("llvm.access.group")).unwrap();
+
+    let loop_id = LoopHints::new()
+        .parallel_accesses(&[access_group])
+        .attach(latch_branch)
+        .unwrap();
+    let parallel_accesses = loop_id.get_node_values()[1].into_metadata_value();
+
+    assert_eq!(hint_name(parallel_accesses), "llvm.loop.parallel_accesses");
+    assert_eq!(
+        parallel_accesses.get_node_values()[1].into_metadata_value(),
+        access_group
+    );
+    assert!(module.verify().is_ok());
+}
@@ -0,0 +1,137 @@
+use inkwell::context::Context;
+use inkwell::lto::{LtoCodeGenerator, LtoModule};
+use inkwell::memory_buffer::MemoryBuffer;
+use inkwell::module::Linkage;
+use inkwell::targets::{RelocMode, TargetMachine};
+
+#[test]
+fn test_lto_code_generator() {
+    let context = Context::create();
+    let builder = context.create_builder();
+    let fn_type = context.i32_type().fn_type(&[], false);
+
+    let module = context.create_module("first");
+    let main = module.add_function("main", fn_type, None);
+
+    module.set_triple(&TargetMachine::get_default_triple());
+    builder.position_at_end(context.append_basic_block(main, "entry"));
+
+    let helper = module.add_function("helper", fn_type, Some(Linkage::External));
+    let call = builder.build_call(helper, &[], "call");
+
+    builder.build_return(Some(&call.try_as_basic_value().left().unwrap()));
+
+    let other_module = context.create_module("second");
+    let helper = other_module.add_function("helper", fn_type, None);
+
+    other_module.set_triple(&TargetMachine::get_default_triple());
+    builder.position_at_end(context.append_basic_block(helper, "entry"));
+    builder.build_return(Some(&context.i32_type().const_int(42, false)));
+
+    let bitcode = module.write_bitcode_to_memory();
+    let lto_module = LtoModule::create_from_memory_buffer(&bitcode).unwrap();
+
+    assert_eq!(
+        lto_module.get_target_triple(),
+        TargetMachine::get_default_triple().as_str()
+    );
+    assert_eq!(lto_module.count_symbols(), 2);
+    assert!(!lto_module.is_thinlto());
+
+    let names: Vec<_> = (0..lto_module.count_symbols())
+        .map(|index| lto_module.get_symbol_name(index).unwrap().to_str().unwrap())
+        .collect();
+
+    assert!(names.contains(&"main"));
+    assert!(names.contains(&"helper"));
+    assert!(lto_module.get_symbol_name(2).is_none());
+
+    let other_bitcode = other_module.write_bitcode_to_memory();
+    let other_lto_module = LtoModule::create_from_memory_buffer_with_path(&other_bitcode, "second.bc").unwrap();
+    let mut codegen = LtoCodeGenerator::create();
+
+    codegen.add_module(lto_module).unwrap();
+    codegen.add_module(other_lto_module).unwrap();
+    codegen.add_must_preserve_symbol("main");
+    codegen.set_pic_model(RelocMode::PIC);
+
+    let object = codegen.compile().unwrap();
+
+    assert!(object.get_size() > 0);
+    assert!(object.create_object_file().is_ok());
+
+    let garbage = MemoryBuffer::create_from_memory_range_copy(b"not bitcode", "garbage");
+
+    assert!(LtoModule::create_from_memory_buffer(&garbage).is_err());
+}
+
+// The C API can't write ThinLTO summaries, so these modules were built by
+// opt from the sources next to them
+#[llvm_versions(14.0..=latest)]
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_thinlto_code_generator() {
+    use inkwell::lto::ThinLtoCodeGenerator;
+
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, read_dir, remove_dir_all, write};
+
+    let main = MemoryBuffer::create_from_memory_range_copy(include_bytes!("data/thinlto_main.bc"), "main");
+    let helper = MemoryBuffer::create_from_memory_range_copy(include_bytes!("data/thinlto_helper.bc"), "helper");
+    let cache_dir = temp_dir().join("inkwell_thinlto_cache");
+
+    assert!(LtoModule::create_from_memory_buffer(&main).unwrap().is_thinlto());
+
+    let _ = remove_dir_all(&cache_dir);
+
+    create_dir_all(&cache_dir).unwrap();
+
+    let run = || {
+        let mut codegen = ThinLtoCodeGenerator::create().unwrap();
+
+        codegen.set_cache_dir(&cache_dir);
+        codegen.set_cache_pruning_interval(-1);
+        codegen.set_cache_entry_expiration(60);
+        codegen.set_pic_model(RelocMode::PIC);
+        codegen.add_must_preserve_symbol("main");
+        codegen.add_module("main.bc", &main).unwrap();
+        codegen.add_module("helper.bc", &helper).unwrap();
+
+        assert!(codegen.get_objects().is_empty());
+
+        codegen.process();
+        codegen.get_objects()
+    };
+    let objects = run();
+
+    assert_eq!(objects.len(), 2);
+
+    for object in objects {
+        assert!(object.create_object_file().is_ok());
+    }
+
+    // Objects are read back from the cache rather than built again
+    let cache_entries: Vec<_> = read_dir(&cache_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_str().unwrap().starts_with("llvmcache-"))
+        .collect();
+
+    assert_eq!(cache_entries.len(), 2);
+
+    for path in &cache_entries {
+        write(path, b"cached object").unwrap();
+    }
+
+    for object in run() {
+        assert_eq!(object.as_slice(), b"cached object");
+    }
+
+    // Bitcode written through the C API has no summary
+    let context = Context::create();
+    let module = context.create_module("no_summary");
+    let bitcode = module.write_bitcode_to_memory();
+    let mut codegen = ThinLtoCodeGenerator::create().unwrap();
+
+    assert!(codegen.add_module("no_summary.bc", &bitcode).is_err());
+}
@@ -511,3 +511,180 @@ fn test_double_ee_from_same_module() {
 
     assert!(module.create_interpreter_execution_engine().is_err());
 }
+
+#[llvm_versions(9.0..=latest)]
+#[test]
+fn test_aliases_and_ifuncs() {
+    use inkwell::module::Linkage;
+    use inkwell::AddressSpace;
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = context.void_type().fn_type(&[], false);
+    let fn_ptr_type = fn_type.ptr_type(AddressSpace::default());
+    let fn_value = module.add_function("my_fn_v2", fn_type, None);
+    let global = module.add_global(i32_type, None, "my_global");
+    let entry = context.append_basic_block(fn_value, "entry");
+
+    builder.position_at_end(entry);
+    builder.build_return(None);
+    global.set_initializer(&i32_type.const_zero());
+
+    assert!(module.get_alias("my_fn").is_none());
+    assert_eq!(module.get_aliases().count(), 0);
+
+    let fn_alias = module.add_alias("my_fn", fn_value.as_global_value());
+    let global_alias = module.add_alias("my_global_alias", global);
+
+    assert_eq!(fn_alias.get_name().to_str(), Ok("my_fn"));
+    assert_eq!(fn_alias.get_aliasee(), fn_value.as_global_value().as_pointer_value());
+    assert_eq!(module.get_alias("my_fn"), Some(fn_alias));
+    assert_eq!(module.get_aliases().collect::<Vec<_>>(), vec![fn_alias, global_alias]);
+    assert_eq!(fn_alias.get_next_alias(), Some(global_alias));
+    assert_eq!(global_alias.get_previous_alias(), Some(fn_alias));
+
+    fn_alias.as_global_value().set_linkage(Linkage::WeakAny);
+    global_alias.set_aliasee(fn_alias.as_global_value());
+
+    assert_eq!(fn_alias.as_global_value().get_linkage(), Linkage::WeakAny);
+    assert_eq!(global_alias.get_aliasee(), fn_alias.as_pointer_value());
+
+    global_alias.set_aliasee(global);
+
+    let resolver = module.add_function("my_fn_resolver", fn_ptr_type.fn_type(&[], false), None);
+    let entry = context.append_basic_block(resolver, "entry");
+
+    builder.position_at_end(entry);
+    builder.build_return(Some(&fn_value.as_global_value().as_pointer_value()));
+
+    assert!(module.get_ifunc("my_ifunc").is_none());
+
+    let ifunc = module.add_ifunc("my_ifunc", fn_type, None, resolver);
+
+    assert_eq!(ifunc.get_name().to_str(), Ok("my_ifunc"));
+    assert_eq!(ifunc.get_resolver(), Some(resolver));
+    assert_eq!(module.get_ifunc("my_ifunc"), Some(ifunc));
+    assert_eq!(module.get_ifuncs().collect::<Vec<_>>(), vec![ifunc]);
+    assert!(ifunc.get_next_ifunc().is_none());
+    assert!(module.verify().is_ok());
+}
+
+// Only typed pointers can make a resolver's type differ from its function's
+#[llvm_versions(9.0..=14.0)]
+#[test]
+fn test_ifunc_cast_resolver() {
+    let context = Context::create();
+    let ir = r#"
+        define i8* @resolver() {
+        entry:
+          ret i8* null
+        }
+
+        @my_ifunc = ifunc void (), void ()* ()* bitcast (i8* ()* @resolver to void ()* ()*)
+    "#;
+    let memory_buffer = MemoryBuffer::create_from_memory_range_copy(ir.as_bytes(), "my_ir");
+    let module = context.create_module_from_ir(memory_buffer).unwrap();
+    let ifunc = module.get_ifunc("my_ifunc").unwrap();
+
+    assert_eq!(ifunc.get_resolver(), module.get_function("resolver"));
+}
+
+#[llvm_versions(9.0..=latest)]
+#[test]
+fn test_multiversion_function() {
+    use inkwell::attributes::AttributeLoc;
+    use inkwell::module::Linkage;
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+    let kernel = module.add_function("kernel", fn_type, None);
+    let caller = module.add_function("caller", fn_type, None);
+    let declaration = module.add_function("declaration", fn_type, None);
+    let param = kernel.get_first_param().unwrap().into_int_value();
+
+    builder.position_at_end(context.append_basic_block(kernel, "entry"));
+    builder.build_return(Some(&builder.build_int_mul(param, param, "square")));
+    builder.position_at_end(context.append_basic_block(caller, "entry"));
+
+    let call = builder.build_call(kernel, &[caller.get_first_param().unwrap().into()], "call");
+
+    builder.build_return(Some(&call.try_as_basic_value().left().unwrap()));
+
+    assert_eq!(
+        module.multiversion_function(declaration, &[("haswell", "+avx2")]),
+        Err("Function must have a body to be multiversioned.")
+    );
+
+    module.set_triple(&TargetTriple::create("aarch64-unknown-linux-gnu"));
+
+    assert_eq!(
+        module.multiversion_function(kernel, &[("neoverse-n1", "+avx2")]),
+        Err("Multiversioning is only supported for x86 targets.")
+    );
+
+    module.set_triple(&TargetTriple::create("x86_64-unknown-linux-gnu"));
+
+    assert_eq!(
+        module.multiversion_function(kernel, &[("haswell", "-avx2")]),
+        Err("Variant has no target features which can be detected at runtime.")
+    );
+    assert_eq!(
+        module.multiversion_function(kernel, &[("haswell", "+avx2,+cx16")]),
+        Err("Variant enables a target feature which can't be detected at runtime.")
+    );
+
+    let variants = [("haswell", "+avx2,+fma,-cx16"), ("znver4", "+avx512f,+gfni")];
+    let ifunc = module.multiversion_function(kernel, &variants).unwrap();
+    let haswell = module.get_function("kernel.haswell").unwrap();
+    let resolver = ifunc.get_resolver().unwrap();
+
+    assert_eq!(ifunc.get_name().to_str(), Ok("kernel"));
+    assert_eq!(ifunc.as_global_value().get_linkage(), Linkage::External);
+    assert_eq!(kernel.get_name().to_str(), Ok("kernel.default"));
+    assert_eq!(kernel.get_linkage(), Linkage::Internal);
+    assert_eq!(haswell.get_linkage(), Linkage::Internal);
+    assert!(module.get_function("kernel.znver4").is_some());
+    assert_eq!(resolver.get_name().to_str(), Ok("kernel.resolver"));
+    assert_eq!(
+        haswell
+            .get_string_attribute(AttributeLoc::Function, "target-features")
+            .unwrap()
+            .get_string_value()
+            .to_str(),
+        Ok("+avx2,+fma,-cx16")
+    );
+    assert!(caller.to_string().contains("@kernel("));
+    assert!(module.get_global("__cpu_model").is_some());
+    assert!(module.verify().is_ok());
+}
+
+#[llvm_versions(7.0..=latest)]
+#[test]
+fn test_named_metadata() {
+    let context = Context::create();
+    let module = context.create_module("my_module");
+
+    assert!(module.named_metadata().next().is_none());
+
+    let first = context.metadata_node(&[context.metadata_string("first").into()]);
+    let second = context.metadata_node(&[context.i32_type().const_int(2, false).into()]);
+
+    module.add_global_metadata("my.first", &first).unwrap();
+    module.add_global_metadata("my.second", &first).unwrap();
+    module.add_global_metadata("my.second", &second).unwrap();
+
+    let named_metadata: Vec<_> = module.named_metadata().collect();
+
+    assert_eq!(
+        named_metadata,
+        vec![
+            ("my.first".to_string(), vec![first]),
+            ("my.second".to_string(), vec![first, second]),
+        ]
+    );
+}
@@ -307,3 +307,87 @@ fn test_run_passes_invalid() {
     assert!(res.is_err());
     assert_eq!(res.unwrap_err().to_str().unwrap(), "unknown pass name 'invalid_pass'");
 }
+
+#[llvm_versions(13.0..=latest)]
+#[test]
+fn test_run_passes_on_function() {
+    use inkwell::attributes::{Attribute, AttributeLoc};
+    use inkwell::values::{FunctionValue, InstructionOpcode};
+
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+    let hot_fn = module.add_function("hot_fn", fn_type, None);
+    let cold_fn = module.add_function("cold_fn", fn_type, None);
+    let noinline_kind_id = Attribute::get_named_enum_kind_id("noinline");
+
+    cold_fn.add_attribute(
+        AttributeLoc::Function,
+        context.create_enum_attribute(noinline_kind_id, 0),
+    );
+
+    for function in [hot_fn, cold_fn] {
+        let entry = context.append_basic_block(function, "entry");
+        let param = function.get_first_param().unwrap().into_int_value();
+
+        builder.position_at_end(entry);
+
+        let sum = builder.build_int_add(param, i32_type.const_zero(), "sum");
+
+        builder.build_return(Some(&sum));
+    }
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+
+    hot_fn
+        .run_passes("instcombine", &machine, PassBuilderOptions::create())
+        .unwrap();
+
+    // Only hot_fn is optimized, and cold_fn keeps just its own attributes
+    let first_opcode = |function: FunctionValue| {
+        let entry = function.get_first_basic_block().unwrap();
+
+        entry.get_first_instruction().unwrap().get_opcode()
+    };
+
+    assert_eq!(first_opcode(hot_fn), InstructionOpcode::Return);
+    assert_eq!(first_opcode(cold_fn), InstructionOpcode::Add);
+    assert_eq!(cold_fn.count_attributes(AttributeLoc::Function), 1);
+    assert!(cold_fn
+        .get_enum_attribute(AttributeLoc::Function, noinline_kind_id)
+        .is_some());
+    assert!(module.verify().is_ok());
+
+    // Module passes can't be run over a single function
+    assert!(hot_fn
+        .run_passes("globaldce", &machine, PassBuilderOptions::create())
+        .is_err());
+    assert_eq!(cold_fn.count_attributes(AttributeLoc::Function), 1);
+
+    // Nor can they be smuggled in by closing the function adaptor
+    let error = hot_fn
+        .run_passes(
+            "instcombine),globaldce,function(sroa",
+            &machine,
+            PassBuilderOptions::create(),
+        )
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "Unexpected ')' at position 11");
+    assert_eq!(cold_fn.count_attributes(AttributeLoc::Function), 1);
+}
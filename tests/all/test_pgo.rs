@@ -0,0 +1,224 @@
+use inkwell::context::Context;
+use inkwell::execution_engine::JitFunction;
+use inkwell::passes::PassBuilderOptions;
+use inkwell::pgo::{set_profile_use_file, FunctionProfile, InstrProfile, PgoInstrumentation};
+use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+use inkwell::{IntPredicate, OptimizationLevel};
+
+use std::env::temp_dir;
+
+const PROFILE_TEXT: &str = "# IR level Instrumentation Flag
+:ir
+main
+# Func Hash:
+1234
+# Num Counters:
+2
+# Counter Values:
+10
+1
+
+helper
+# Func Hash:
+5678
+# Num Counters:
+1
+# Counter Values:
+3
+
+";
+
+#[test]
+fn test_parse_instr_profile() {
+    let profile = InstrProfile::parse_text(PROFILE_TEXT).unwrap();
+
+    assert_eq!(profile.functions.len(), 2);
+    assert_eq!(
+        profile.get_function("main"),
+        Some(&FunctionProfile {
+            name: "main".to_string(),
+            hash: 1234,
+            counts: vec![10, 1],
+        })
+    );
+    assert_eq!(profile.get_function("helper").unwrap().counts, vec![3]);
+    assert!(profile.get_function("missing").is_none());
+    assert_eq!(profile.to_string(), PROFILE_TEXT);
+
+    assert!(profile.ir_level);
+    assert!(InstrProfile::parse_text(":ir\n").unwrap().functions.is_empty());
+    assert!(!InstrProfile::parse_text(":fe\nmain\n1234\n0\n").unwrap().ir_level);
+
+    let frontend_profile = InstrProfile::parse_text("main\n1234\n1\n5\n").unwrap();
+
+    assert!(!frontend_profile.ir_level);
+    assert_eq!(
+        frontend_profile.to_string(),
+        "main\n# Func Hash:\n1234\n# Num Counters:\n1\n# Counter Values:\n5\n\n"
+    );
+    assert!(InstrProfile::parse_text(":csir\nmain\n1234\n0\n").is_err());
+    assert!(InstrProfile::parse_text(":ir\nmain\n1234\n2\n10\n").is_err());
+    assert!(InstrProfile::parse_text(":ir\nmain\nhash\n0\n").is_err());
+    assert!(InstrProfile::parse_text(":ir\nmain\n1234\n1\n10\n1\n0\n1\n").is_err());
+}
+
+#[test]
+fn test_merge_instr_profiles() {
+    let mut profile = InstrProfile::parse_text(PROFILE_TEXT).unwrap();
+    let other = InstrProfile::parse_text(":ir\nmain\n1234\n2\n5\n2\nmain\n9999\n1\n7\n").unwrap();
+
+    profile.merge(&other).unwrap();
+
+    assert_eq!(profile.functions.len(), 3);
+    assert_eq!(profile.functions[0].counts, vec![15, 3]);
+    assert_eq!(profile.functions[1].counts, vec![3]);
+    assert_eq!(profile.functions[2].hash, 9999);
+
+    let mismatched = InstrProfile::parse_text(":ir\nhelper\n5678\n2\n1\n1\n").unwrap();
+
+    assert!(profile.merge(&mismatched).is_err());
+    assert!(profile.merge(&InstrProfile::new()).is_err());
+}
+
+// Written in the layout of the LLVM 14 profile runtime, with a binary id and
+// an unknown function, and checked with `llvm-profdata show`
+#[llvm_versions(14.0..=latest)]
+#[test]
+fn test_parse_raw_profile() {
+    let data = include_bytes!("data/pgo.profraw");
+    let profile = InstrProfile::parse_raw(data, &["sum", "missing"]).unwrap();
+
+    assert!(profile.ir_level);
+    assert_eq!(
+        profile.functions,
+        vec![FunctionProfile {
+            name: "sum".to_string(),
+            hash: 0x1234,
+            counts: vec![5, 2],
+        }]
+    );
+    assert_eq!(
+        InstrProfile::parse_raw(data, &["other", "sum"])
+            .unwrap()
+            .get_function("other"),
+        Some(&FunctionProfile {
+            name: "other".to_string(),
+            hash: 0x5678,
+            counts: vec![7],
+        })
+    );
+    assert!(InstrProfile::parse_raw(data, &[]).unwrap().functions.is_empty());
+
+    let mut old_version = data.to_vec();
+
+    old_version[8] = 5;
+
+    assert_eq!(
+        InstrProfile::parse_raw(&old_version, &["sum"]),
+        Err("Unsupported raw profile version.")
+    );
+    assert!(InstrProfile::parse_raw(&data[..200], &["sum"]).is_err());
+    assert!(InstrProfile::parse_raw(PROFILE_TEXT.as_bytes(), &["sum"]).is_err());
+}
+
+#[test]
+fn test_write_indexed_profile() {
+    let profile = InstrProfile::parse_text(PROFILE_TEXT).unwrap();
+    let indexed = profile.write_indexed();
+    let frontend_indexed = InstrProfile::parse_text("main\n1234\n1\n5\n").unwrap().write_indexed();
+
+    // The magic number and version, flagged as IR level
+    assert_eq!(&indexed[..8], b"\xfflprofi\x81");
+    assert_eq!(&indexed[8..16], &[7, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(&frontend_indexed[8..16], &[7, 0, 0, 0, 0, 0, 0, 0]);
+    // llvm-profdata writes a profile of the same size
+    assert_eq!(indexed.len(), 648);
+    assert_eq!(InstrProfile::new().write_indexed().len() % 8, 0);
+}
+
+#[test]
+fn test_pgo_instrumentation() {
+    Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i64_type = context.i64_type();
+    let fn_type = i64_type.fn_type(&[i64_type.into()], false);
+    let function = module.add_function("sum", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let loop_block = context.append_basic_block(function, "loop");
+    let exit = context.append_basic_block(function, "exit");
+
+    // Sums the numbers below n
+    builder.position_at_end(entry);
+    builder.build_unconditional_branch(loop_block);
+    builder.position_at_end(loop_block);
+
+    let index = builder.build_phi(i64_type, "index");
+    let sum = builder.build_phi(i64_type, "sum");
+    let index_value = index.as_basic_value().into_int_value();
+    let sum_value = sum.as_basic_value().into_int_value();
+    let next_index = builder.build_int_add(index_value, i64_type.const_int(1, false), "next_index");
+    let next_sum = builder.build_int_add(sum_value, index_value, "next_sum");
+    let n = function.get_first_param().unwrap().into_int_value();
+    let done = builder.build_int_compare(IntPredicate::UGE, next_index, n, "done");
+
+    index.add_incoming(&[(&i64_type.const_zero(), entry), (&next_index, loop_block)]);
+    sum.add_incoming(&[(&i64_type.const_zero(), entry), (&next_sum, loop_block)]);
+    builder.build_conditional_branch(done, exit, loop_block);
+    builder.position_at_end(exit);
+    builder.build_return(Some(&next_sum));
+
+    let triple = TargetMachine::get_default_triple();
+    let machine = Target::from_triple(&triple)
+        .unwrap()
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::JITDefault,
+        )
+        .unwrap();
+    module.set_triple(&triple);
+
+    let profiled_module = module.clone();
+    let instrumentation =
+        PgoInstrumentation::instrument_for_jit(&profiled_module, &machine, &PassBuilderOptions::create()).unwrap();
+
+    assert!(profiled_module.verify().is_ok());
+
+    let execution_engine = profiled_module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .unwrap();
+
+    unsafe {
+        let sum: JitFunction<unsafe extern "C" fn(u64) -> u64> = execution_engine.get_function("sum").unwrap();
+
+        assert_eq!(sum.call(10), 45);
+        assert_eq!(sum.call(5), 10);
+    }
+
+    let profile = instrumentation.read_counters(&execution_engine).unwrap();
+    let sum_profile = profile.get_function("sum").unwrap();
+
+    assert_eq!(profile.functions.len(), 1);
+    assert!(sum_profile.counts.iter().sum::<u64>() > 0);
+    assert_eq!(InstrProfile::parse_text(&profile.to_string()).unwrap(), profile);
+
+    let profile_path = temp_dir().join("inkwell_pgo_instrumentation.profdata");
+
+    std::fs::write(&profile_path, profile.write_indexed()).unwrap();
+    set_profile_use_file(profile_path.to_str().unwrap()).unwrap();
+    module
+        .run_passes("pgo-instr-use", &machine, PassBuilderOptions::create())
+        .unwrap();
+
+    let ir = module.print_to_string().to_string();
+
+    assert!(ir.contains("!\"function_entry_count\", i64 2}"));
+    assert!(ir.contains("!\"branch_weights\""));
+    assert!(set_profile_use_file(profile_path.to_str().unwrap()).is_ok());
+}
@@ -0,0 +1,674 @@
+use inkwell::pipeline::{CgsccPass, FunctionPass, LoopPass, ModulePass, Pipeline, PresetLevel, SimplifyCfgOptions};
+use inkwell::OptimizationLevel;
+
+#[llvm_versions(13.0..=latest)]
+use inkwell::context::Context;
+#[llvm_versions(13.0..=latest)]
+use inkwell::module::{Linkage, Module};
+#[llvm_versions(13.0..=latest)]
+use inkwell::passes::PassBuilderOptions;
+#[llvm_versions(13.0..=latest)]
+use inkwell::pipeline::{CustomPasses, IrSizeCounts, PipelineRecorder};
+#[llvm_versions(13.0..=latest)]
+use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+#[llvm_versions(13.0..=latest)]
+use inkwell::values::{FunctionValue, InstructionOpcode};
+
+#[test]
+fn test_render_pipeline() {
+    assert_eq!(Pipeline::new().to_string(), "");
+    assert_eq!(Pipeline::preset(PresetLevel::Oz).to_string(), "default<Oz>");
+    assert_eq!(
+        Pipeline::new()
+            .add_pass(ModulePass::ThinLtoPreLink(PresetLevel::O3))
+            .to_string(),
+        "thinlto-pre-link<O3>"
+    );
+
+    let simplify_cfg = SimplifyCfgOptions {
+        bonus_inst_threshold: Some(2),
+        switch_to_lookup: Some(true),
+        keep_loops: Some(false),
+        ..Default::default()
+    };
+    let pipeline = Pipeline::new()
+        .add_pass(ModulePass::AlwaysInline)
+        .add_cgscc_passes(vec![
+            CgsccPass::Inline,
+            CgsccPass::Function(vec![FunctionPass::EarlyCse]),
+        ])
+        .add_function_passes(vec![
+            FunctionPass::SimplifyCfg(simplify_cfg),
+            FunctionPass::SimplifyCfg(SimplifyCfgOptions::default()),
+            FunctionPass::Loop {
+                passes: vec![LoopPass::IndVars, LoopPass::LoopDeletion],
+                use_memory_ssa: false,
+            },
+            FunctionPass::LoopUnroll {
+                opt_level: OptimizationLevel::Less,
+                full_unroll_max: None,
+            },
+        ])
+        .add_pass(ModulePass::Other("print<module-debuginfo>".to_string()));
+
+    assert_eq!(pipeline.get_passes().len(), 4);
+    assert_eq!(
+        pipeline.to_string(),
+        "always-inline,cgscc(inline,function(early-cse)),\
+         function(simplifycfg<bonus-inst-threshold=2;switch-to-lookup;no-keep-loops>,simplifycfg,\
+         loop(indvars,loop-deletion),loop-unroll<O1>),print<module-debuginfo>"
+    );
+}
+
+#[test]
+fn test_parse_pipeline() {
+    let text = "module(globaldce,default<O1>),cgscc(argpromotion),\
+                function(simplifycfg<no-hoist-common-insts;sink-common-insts>,loop-mssa(licm),loop-unroll<O3;full-unroll-max=8>)";
+    let pipeline = Pipeline::parse(text).unwrap();
+
+    assert_eq!(
+        pipeline,
+        Pipeline::new()
+            .add_pass(ModulePass::GlobalDce)
+            .add_pass(ModulePass::Default(PresetLevel::O1))
+            .add_cgscc_passes(vec![CgsccPass::ArgPromotion])
+            .add_function_passes(vec![
+                FunctionPass::SimplifyCfg(SimplifyCfgOptions {
+                    hoist_common_insts: Some(false),
+                    sink_common_insts: Some(true),
+                    ..Default::default()
+                }),
+                FunctionPass::Loop {
+                    passes: vec![LoopPass::Licm],
+                    use_memory_ssa: true,
+                },
+                FunctionPass::LoopUnroll {
+                    opt_level: OptimizationLevel::Aggressive,
+                    full_unroll_max: Some(8),
+                },
+            ])
+    );
+    assert_eq!(Pipeline::parse(&pipeline.to_string()), Ok(pipeline));
+
+    // Like LLVM, the first pass decides how the whole list is nested
+    assert_eq!(
+        Pipeline::parse("instcombine,licm,gvn").unwrap(),
+        Pipeline::new().add_function_passes(vec![
+            FunctionPass::InstCombine,
+            FunctionPass::Loop {
+                passes: vec![LoopPass::Licm],
+                use_memory_ssa: false,
+            },
+            FunctionPass::Gvn,
+        ])
+    );
+    assert_eq!(
+        Pipeline::parse("globalopt,instcombine,instcombine,inline").unwrap(),
+        Pipeline::new()
+            .add_pass(ModulePass::GlobalOpt)
+            .add_function_passes(vec![FunctionPass::InstCombine])
+            .add_function_passes(vec![FunctionPass::InstCombine])
+            .add_cgscc_passes(vec![CgsccPass::Inline])
+    );
+    assert_eq!(
+        Pipeline::parse("inline,instcombine").unwrap().to_string(),
+        "cgscc(inline,function(instcombine))"
+    );
+    assert_eq!(
+        Pipeline::parse("licm,loop-rotate").unwrap().to_string(),
+        "function(loop-mssa(licm,loop-rotate))"
+    );
+    assert_eq!(
+        Pipeline::parse("loop-unroll").unwrap().to_string(),
+        "function(loop-unroll<O2>)"
+    );
+
+    // Passes without a variant of their own are kept verbatim
+    assert_eq!(
+        Pipeline::parse("memcpyopt,function(gvn<pre>),require<domtree>").unwrap(),
+        Pipeline::new().add_function_passes(vec![
+            FunctionPass::Other("memcpyopt".to_string()),
+            FunctionPass::Other("function(gvn<pre>)".to_string()),
+            FunctionPass::Other("require<domtree>".to_string()),
+        ])
+    );
+    assert_eq!(
+        Pipeline::parse("cgscc(devirt<4>(inline)),loop-sink,instcombine<max-iterations=2>").unwrap(),
+        Pipeline::new()
+            .add_cgscc_passes(vec![CgsccPass::Other("devirt<4>(inline)".to_string())])
+            .add_function_passes(vec![FunctionPass::Other("loop-sink".to_string())])
+            .add_function_passes(vec![FunctionPass::Other("instcombine<max-iterations=2>".to_string())])
+    );
+
+    for text in [
+        "memcpyopt",
+        "function(float2int)",
+        "loop-sink",
+        "function(gvn<pre>)",
+        "instcombine<max-iterations=2>",
+        "require<globals-aa>",
+        "cgscc(devirt<4>(inline))",
+        "function(repeat<2>(loop-unroll<O2;no-partial>,print<domtree>))",
+        "loop-mssa(loop(licm)),require<domtree>,invalidate<all>",
+    ] {
+        assert!(Pipeline::validate(text).is_ok(), "{}", text);
+    }
+    assert!(Pipeline::validate("function(verify),verify").is_ok());
+}
+
+#[test]
+fn test_parse_pipeline_errors() {
+    let check = |text: &str, position: usize, message: &str| {
+        let error = Pipeline::validate(text).unwrap_err();
+
+        assert_eq!(error.get_position(), position, "{}", text);
+        assert_eq!(error.get_message(), message, "{}", text);
+    };
+
+    check("", 0, "Expected a pass name");
+    check("instcombine,", 12, "Expected a pass name");
+    check("instcombin", 0, "Unknown pass 'instcombin'");
+    check("function(gvn,licm2)", 13, "Unknown function pass 'licm2'");
+    check("function(loop(instcombine))", 14, "Unknown loop pass 'instcombine'");
+    check("cgscc(globalopt)", 6, "Unknown CGSCC pass 'globalopt'");
+    check("function(gvn", 12, "Expected ',' or ')'");
+    check("gvn)", 3, "Unexpected ')'");
+    check("default", 7, "Preset 'default' needs an optimization level");
+    check("default<O4>", 8, "Unknown optimization level 'O4'");
+    check("default<O2", 7, "Unterminated '<'");
+    check("instcombine,globalopt", 12, "Unknown function pass 'globalopt'");
+    check("inline,globalopt", 7, "Unknown CGSCC pass 'globalopt'");
+    check(
+        "globalopt,require<domtree>",
+        10,
+        "Unknown module pass 'require<domtree>'",
+    );
+    check(
+        "globalopt,loop-mssa(licm)",
+        10,
+        "Adaptor 'loop-mssa' can't be used in a module pipeline",
+    );
+    check(
+        "function(loop(loop-mssa(licm)))",
+        14,
+        "Adaptor 'loop-mssa' can't be used in a loop pipeline",
+    );
+    check("function(repeat<x>(gvn))", 16, "Invalid repeat count 'x'");
+    check("function(repeat(gvn))", 15, "Adaptor 'repeat' needs a count");
+    check("function(default<O2>)", 9, "Unknown function pass 'default<O2>'");
+    check("gvn(dce)", 0, "Pass 'gvn' can't contain other passes");
+    check("function", 0, "Adaptor 'function' needs a list of passes");
+    check(
+        "simplifycfg<bonus-inst-threshold=x>",
+        12,
+        "Invalid bonus instruction threshold 'x'",
+    );
+    check(
+        "loop-unroll<O2;full-unroll-max=-1>",
+        15,
+        "Invalid full unroll maximum '-1'",
+    );
+
+    assert_eq!(
+        Pipeline::validate("instcombin").unwrap_err().to_string(),
+        "Unknown pass 'instcombin' at position 0"
+    );
+}
+
+#[llvm_versions(13.0..=latest)]
+#[test]
+fn test_run_pipeline() {
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+    let pipeline = Pipeline::new()
+        .add_pass(ModulePass::GlobalDce)
+        .add_function_passes(vec![
+            FunctionPass::InstCombine,
+            FunctionPass::SimplifyCfg(SimplifyCfgOptions::default()),
+            FunctionPass::Loop {
+                passes: vec![LoopPass::Licm],
+                use_memory_ssa: true,
+            },
+        ]);
+
+    module
+        .run_passes(&pipeline.to_string(), &machine, PassBuilderOptions::create())
+        .unwrap();
+}
+
+#[llvm_versions(13.0..=latest)]
+#[test]
+fn test_custom_passes() {
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+
+    for name in ["first", "second"] {
+        let function = module.add_function(name, fn_type, None);
+        let entry = context.append_basic_block(function, "entry");
+
+        builder.position_at_end(entry);
+        builder.build_return(Some(&function.get_first_param().unwrap()));
+    }
+
+    module.add_function("declaration", fn_type, None);
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+    let first_opcode = |function: FunctionValue| {
+        let entry = function.get_first_basic_block().unwrap();
+
+        entry.get_first_instruction().unwrap().get_opcode()
+    };
+    let mut lowered = Vec::new();
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut module_runs = 0;
+    let mut custom_passes = CustomPasses::new();
+
+    // Returns `x + 0` instead of `x`, which instcombine folds back
+    custom_passes.add_function_pass("add-zero", |function: FunctionValue| {
+        let ret = function.get_first_basic_block().unwrap().get_terminator().unwrap();
+        let param = function.get_first_param().unwrap().into_int_value();
+
+        builder.position_before(&ret);
+
+        let sum = builder.build_int_add(param, i32_type.const_zero(), "sum");
+
+        ret.set_operand(0, sum);
+        lowered.push(function.get_name().to_str().unwrap().to_string());
+
+        true
+    });
+    custom_passes.add_function_pass("before", |function| {
+        before.push(first_opcode(function));
+        false
+    });
+    custom_passes.add_function_pass("after", |function| {
+        after.push(first_opcode(function));
+        false
+    });
+    custom_passes.add_module_pass("count-module", |module: &Module| {
+        module_runs += module.get_functions().count();
+        false
+    });
+
+    let options = PassBuilderOptions::create();
+
+    options.set_verify_each(true);
+
+    assert_eq!(
+        custom_passes.run_passes(
+            &module,
+            "function(add-zero,before,instcombine,after),count-module,module(function(verify),count-module)",
+            &machine,
+            &options,
+        ),
+        Ok(true)
+    );
+
+    let error = custom_passes
+        .run_passes(&module, "function(before,)", &machine, &options)
+        .unwrap_err();
+
+    assert_eq!(error.to_str(), Ok("Expected a pass name at position 16"));
+    assert!(custom_passes
+        .run_passes(&module, "before,not-a-pass", &machine, &options)
+        .is_err());
+
+    // Custom passes can't be split out of other adaptors, so nothing runs
+    for (pipeline, message) in [
+        (
+            "function(loop(before))",
+            "Custom pass 'before' can't run inside 'loop' at position 14",
+        ),
+        (
+            "cgscc(function(before))",
+            "Custom pass 'before' can't run inside 'cgscc' at position 15",
+        ),
+        (
+            "function(count-module)",
+            "Custom module pass 'count-module' can't run inside 'function' at position 9",
+        ),
+        (
+            "instcombine,before<x>",
+            "Custom pass 'before' takes no parameters at position 12",
+        ),
+    ] {
+        let error = custom_passes
+            .run_passes(&module, pipeline, &machine, &options)
+            .unwrap_err();
+
+        assert_eq!(error.to_str(), Ok(message));
+    }
+    assert_eq!(
+        custom_passes.run_passes(&module, "function(before)", &machine, &options),
+        Ok(false)
+    );
+
+    drop(custom_passes);
+
+    assert_eq!(lowered, ["first", "second"]);
+    assert_eq!(
+        before,
+        [
+            InstructionOpcode::Add,
+            InstructionOpcode::Add,
+            InstructionOpcode::Return,
+            InstructionOpcode::Return,
+            InstructionOpcode::Return,
+            InstructionOpcode::Return,
+        ]
+    );
+    assert_eq!(after, [InstructionOpcode::Return, InstructionOpcode::Return]);
+    assert_eq!(module_runs, 6);
+}
+
+#[llvm_versions(13.0..=latest)]
+#[test]
+fn test_pass_recorder() {
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+
+    for (name, linkage) in [("used", None), ("unused", Some(Linkage::Internal))] {
+        let function = module.add_function(name, fn_type, linkage);
+        let entry = context.append_basic_block(function, "entry");
+        let param = function.get_first_param().unwrap().into_int_value();
+
+        builder.position_at_end(entry);
+
+        let sum = builder.build_int_add(param, i32_type.const_zero(), "sum");
+
+        builder.build_return(Some(&sum));
+    }
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+    let options = PassBuilderOptions::create();
+    let mut recorder = PipelineRecorder::new();
+
+    recorder.set_record_timings(true);
+    recorder.set_record_ir_counts(true);
+    recorder.set_ir_snapshots(&["instcombine", "simplifycfg<keep-loops>"]);
+
+    CustomPasses::new()
+        .run_passes_recorded(
+            &module,
+            "function(instcombine,simplifycfg<keep-loops>),globaldce",
+            &machine,
+            &options,
+            &mut recorder,
+        )
+        .unwrap();
+
+    let timed_passes: Vec<&str> = recorder
+        .get_step_timings()
+        .iter()
+        .map(|(pass, _)| pass.as_str())
+        .collect();
+
+    assert_eq!(timed_passes, ["instcombine", "simplifycfg<keep-loops>", "globaldce"]);
+    assert_eq!(
+        recorder.get_ir_counts()[0],
+        (
+            "instcombine".to_string(),
+            IrSizeCounts {
+                functions: 2,
+                basic_blocks: 2,
+                instructions: 2,
+            }
+        )
+    );
+    assert_eq!(recorder.get_ir_counts()[2].1.functions, 1);
+
+    let snapshots = recorder.get_ir_snapshots();
+
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].0, "instcombine");
+    assert!(snapshots[0].1.contains("define internal i32 @unused"));
+    assert!(!snapshots[0].1.contains("add i32"));
+
+    recorder.clear();
+    recorder.set_record_timings(false);
+    recorder.set_record_ir_counts(false);
+    recorder.set_ir_snapshots(&[]);
+    recorder.set_ir_snapshots_after_all(true);
+
+    CustomPasses::new()
+        .run_passes_recorded(&module, "globaldce,verify", &machine, &options, &mut recorder)
+        .unwrap();
+
+    assert!(recorder.get_step_timings().is_empty());
+    assert!(recorder.get_ir_counts().is_empty());
+    assert_eq!(recorder.get_ir_snapshots().len(), 2);
+    assert!(!recorder.get_ir_snapshots()[1].1.contains("@unused"));
+}
+
+#[llvm_versions(15.0..=latest)]
+#[test]
+fn test_pass_recorder_pass_timings() {
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let fn_type = context.void_type().fn_type(&[], false);
+    let function = module.add_function("my_fn", fn_type, None);
+    let builder = context.create_builder();
+
+    builder.position_at_end(context.append_basic_block(function, "entry"));
+    builder.build_return(None);
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+    let options = PassBuilderOptions::create();
+    let mut recorder = PipelineRecorder::new();
+
+    recorder.set_record_pass_timings(true);
+
+    for _ in 0..2 {
+        CustomPasses::new()
+            .run_passes_recorded(
+                &module,
+                "function(instcombine),globaldce",
+                &machine,
+                &options,
+                &mut recorder,
+            )
+            .unwrap();
+    }
+
+    let timed_passes: Vec<&str> = recorder
+        .get_pass_timings()
+        .iter()
+        .map(|(pass, _)| pass.as_str())
+        .collect();
+
+    assert!(timed_passes.contains(&"InstCombinePass"));
+    assert!(timed_passes.contains(&"GlobalDCEPass"));
+    assert_eq!(
+        timed_passes.iter().filter(|&&pass| pass == "InstCombinePass").count(),
+        1
+    );
+
+    recorder.clear();
+    recorder.set_record_pass_timings(false);
+
+    CustomPasses::new()
+        .run_passes_recorded(&module, "globaldce", &machine, &options, &mut recorder)
+        .unwrap();
+
+    assert!(recorder.get_pass_timings().is_empty());
+}
+
+#[llvm_versions(13.0..=latest)]
+#[test]
+fn test_bisect_passes() {
+    Target::initialize_all(&InitializationConfig::default());
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into()], false);
+    let function = module.add_function("identity", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let param = function.get_first_param().unwrap().into_int_value();
+
+    builder.position_at_end(entry);
+
+    let sum = builder.build_int_add(param, i32_type.const_zero(), "sum");
+
+    builder.build_return(Some(&sum));
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).unwrap();
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .unwrap();
+    let options = PassBuilderOptions::create();
+    let mut custom_passes = CustomPasses::new();
+
+    // Miscompiles every function to return zero
+    custom_passes.add_function_pass("break-it", |function: FunctionValue| {
+        let ret = function.get_last_basic_block().unwrap().get_terminator().unwrap();
+
+        ret.set_operand(0, i32_type.const_zero())
+    });
+
+    // Good if identity still returns something other than a constant
+    let is_good = |module: &Module| {
+        let function = module.get_function("identity").unwrap();
+        let ret = function.get_last_basic_block().unwrap().get_terminator().unwrap();
+        let value = ret.get_operand(0).unwrap().left().unwrap();
+
+        !value.into_int_value().is_const()
+    };
+    let pipeline = "instcombine,function(simplifycfg,break-it,dce),globaldce";
+
+    assert_eq!(custom_passes.count_passes(pipeline), Ok(5));
+    assert!(custom_passes.count_passes("instcombine,").is_err());
+
+    let trial = module.clone();
+
+    assert_eq!(
+        custom_passes.run_passes_limited(&trial, pipeline, &machine, &options, 2),
+        Ok(Some("simplifycfg".to_string()))
+    );
+    assert!(is_good(&trial));
+    assert_eq!(
+        custom_passes.run_passes_limited(&trial, pipeline, &machine, &options, 0),
+        Ok(None)
+    );
+    assert_eq!(
+        custom_passes.run_passes_limited(&trial, pipeline, &machine, &options, 6),
+        Ok(None)
+    );
+    assert!(!is_good(&trial));
+
+    let bad_step = custom_passes
+        .bisect(&module, pipeline, &machine, &options, is_good)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(bad_step.get_number(), 3);
+    assert_eq!(bad_step.get_text(), "break-it");
+    assert_eq!(bad_step.get_pass_run(), None);
+    assert_eq!(
+        custom_passes.bisect(
+            &module,
+            "instcombine,function(simplifycfg,dce)",
+            &machine,
+            &options,
+            is_good
+        ),
+        Ok(None)
+    );
+    assert!(custom_passes
+        .bisect(&module, pipeline, &machine, &options, |_| false)
+        .is_err());
+
+    // A preset is one step, which LLVM 15 and later narrow down to the pass
+    // run that folded away the add
+    let keeps_add = |module: &Module| {
+        let function = module.get_function("identity").unwrap();
+        let first = function.get_first_basic_block().unwrap().get_first_instruction();
+
+        first.unwrap().get_opcode() == InstructionOpcode::Add
+    };
+    let bad_step = custom_passes
+        .bisect(&module, "globaldce,default<O2>", &machine, &options, keeps_add)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(bad_step.get_number(), 2);
+    assert_eq!(bad_step.get_text(), "default<O2>");
+    #[cfg(any(feature = "llvm13-0", feature = "llvm14-0"))]
+    assert_eq!(bad_step.get_pass_run(), None);
+    #[cfg(any(feature = "llvm15-0", feature = "llvm16-0"))]
+    assert!(bad_step.get_pass_run().is_some());
+
+    // The module itself is never optimized
+    let ret = function.get_last_basic_block().unwrap().get_terminator().unwrap();
+
+    assert_eq!(ret.get_operand(0).unwrap().left(), Some(sum.into()));
+}
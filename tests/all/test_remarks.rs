@@ -0,0 +1,107 @@
+use inkwell::remarks::{Remark, RemarkArg, RemarkDebugLoc, RemarkKind};
+
+const REMARKS_YAML: &str = "--- !Missed
+Pass:            inline
+Name:            NoDefinition
+DebugLoc:        { File: main.c, Line: 7, Column: 12 }
+Function:        main
+Args:
+  - Callee:          foo
+  - String:          ' will not be inlined into '
+  - Caller:          main
+    DebugLoc:        { File: main.c, Line: 5, Column: 0 }
+  - String:          ' because its definition is unavailable'
+...
+--- !Passed
+Pass:            gvn
+Name:            LoadElim
+Function:        'it''s'
+Hotness:         42
+Args:
+  - String:          load eliminated
+...
+--- !Analysis
+Pass:            loop-vectorize
+Name:            CantVectorizeCall
+Function:        main
+...
+";
+
+#[test]
+fn test_parse_yaml_remarks() {
+    let remarks = Remark::parse_yaml(REMARKS_YAML.as_bytes()).unwrap();
+
+    assert_eq!(remarks.len(), 3);
+
+    let inline = &remarks[0];
+
+    assert_eq!(inline.kind, RemarkKind::Missed);
+    assert_eq!(inline.pass, "inline");
+    assert_eq!(inline.name, "NoDefinition");
+    assert_eq!(inline.function, "main");
+    assert_eq!(
+        inline.debug_loc,
+        Some(RemarkDebugLoc {
+            file: "main.c".to_string(),
+            line: 7,
+            column: 12,
+        })
+    );
+    assert_eq!(inline.hotness, None);
+    assert_eq!(inline.args.len(), 4);
+    assert_eq!(
+        inline.args[2],
+        RemarkArg {
+            key: "Caller".to_string(),
+            value: "main".to_string(),
+            debug_loc: Some(RemarkDebugLoc {
+                file: "main.c".to_string(),
+                line: 5,
+                column: 0,
+            }),
+        }
+    );
+    assert_eq!(
+        inline.get_message(),
+        "foo will not be inlined into main because its definition is unavailable"
+    );
+
+    assert_eq!(remarks[1].kind, RemarkKind::Passed);
+    assert_eq!(remarks[1].function, "it's");
+    assert_eq!(remarks[1].hotness, Some(42));
+    assert_eq!(remarks[1].debug_loc, None);
+    assert_eq!(remarks[2].kind, RemarkKind::Analysis);
+    assert!(remarks[2].args.is_empty());
+    assert_eq!(remarks[2].get_message(), "");
+
+    assert!(Remark::parse_yaml(b"").unwrap().is_empty());
+    assert!(Remark::parse_yaml(b"--- !Bogus\nPass: inline\n...\n").is_err());
+}
+
+#[test]
+fn test_write_yaml_remarks() {
+    let remarks = Remark::parse_yaml(REMARKS_YAML.as_bytes()).unwrap();
+
+    assert_eq!(
+        remarks[1].to_yaml(),
+        "--- !Passed
+Pass:            'gvn'
+Name:            'LoadElim'
+Function:        'it''s'
+Hotness:         42
+Args:
+  - String:          'load eliminated'
+...
+"
+    );
+
+    let yaml: String = remarks.iter().map(Remark::to_yaml).collect();
+
+    assert_eq!(Remark::parse_yaml(yaml.as_bytes()).unwrap(), remarks);
+}
+
+#[llvm_versions(10.0..=latest)]
+#[test]
+fn test_parse_bitstream_remarks() {
+    assert!(Remark::parse_bitstream(REMARKS_YAML.as_bytes()).is_err());
+}
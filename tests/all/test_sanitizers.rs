@@ -0,0 +1,178 @@
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::context::Context;
+use inkwell::passes::PassBuilderOptions;
+use inkwell::sanitizers::Sanitizers;
+use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
+use inkwell::{AddressSpace, OptimizationLevel};
+
+#[test]
+fn test_sanitizer_passes() {
+    let address = Sanitizers {
+        address: true,
+        ..Sanitizers::default()
+    };
+    let thread = Sanitizers {
+        thread: true,
+        bounds_checking: true,
+        ..Sanitizers::default()
+    };
+    let conflicting = Sanitizers {
+        address: true,
+        memory: true,
+        ..Sanitizers::default()
+    };
+
+    assert!(address.validate().is_ok());
+    assert!(address.get_passes().contains("asan"));
+    assert_eq!(
+        thread.get_passes(),
+        "tsan-module,function(tsan),function(bounds-checking)"
+    );
+    assert!(conflicting.validate().is_err());
+    assert_eq!(Sanitizers::default().get_passes(), "");
+}
+
+#[test]
+fn test_sanitizer_instrumentation() {
+    Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+    let context = Context::create();
+    let module = context.create_module("my_module");
+    let builder = context.create_builder();
+    let i64_type = context.i64_type();
+    let ptr_type = i64_type.ptr_type(AddressSpace::default());
+    let fn_type = i64_type.fn_type(&[ptr_type.into()], false);
+    let load = module.add_function("load", fn_type, None);
+    let skipped = module.add_function("skipped", fn_type, None);
+    let declaration = module.add_function("declaration", fn_type, None);
+    let triple = TargetMachine::get_default_triple();
+
+    module.set_triple(&triple);
+
+    for function in [load, skipped] {
+        let ptr = function.get_first_param().unwrap().into_pointer_value();
+
+        builder.position_at_end(context.append_basic_block(function, "entry"));
+
+        #[cfg(feature = "llvm14-0")]
+        let value = builder.build_load(ptr, "value");
+        #[cfg(any(feature = "llvm15-0", feature = "llvm16-0"))]
+        let value = builder.build_load(i64_type, ptr, "value");
+
+        // Unaligned loads are checked byte by byte
+        value
+            .into_int_value()
+            .as_instruction()
+            .unwrap()
+            .set_alignment(8)
+            .unwrap();
+        builder.build_return(Some(&value));
+    }
+
+    let disable_kind_id = Attribute::get_named_enum_kind_id("disable_sanitizer_instrumentation");
+
+    skipped.add_attribute(
+        AttributeLoc::Function,
+        context.create_enum_attribute(disable_kind_id, 0),
+    );
+
+    let machine = Target::from_triple(&triple)
+        .unwrap()
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::JITDefault,
+        )
+        .unwrap();
+    let sanitizers = Sanitizers {
+        address: true,
+        ..Sanitizers::default()
+    };
+    let sanitize_kind_id = Attribute::get_named_enum_kind_id("sanitize_address");
+
+    assert!(sanitizers.get_runtime_symbols(&module).is_empty());
+
+    sanitizers
+        .run_passes(&module, &machine, PassBuilderOptions::create())
+        .unwrap();
+
+    assert!(load
+        .get_enum_attribute(AttributeLoc::Function, sanitize_kind_id)
+        .is_some());
+    assert!(skipped
+        .get_enum_attribute(AttributeLoc::Function, sanitize_kind_id)
+        .is_none());
+    assert!(declaration
+        .get_enum_attribute(AttributeLoc::Function, sanitize_kind_id)
+        .is_none());
+    assert!(module.verify().is_ok());
+
+    let symbols = sanitizers.get_runtime_symbols(&module);
+
+    assert!(symbols.iter().any(|symbol| symbol.starts_with("__asan_report_load8")));
+    assert!(symbols.iter().all(|symbol| symbol.starts_with("__asan_")));
+    // Instrumenting functions twice would check the shadow memory loads too
+    assert!(!symbols
+        .iter()
+        .any(|symbol| symbol.starts_with("__asan_report_load1") || symbol.starts_with("__asan_report_load_n")));
+
+    for (sanitizers, prefix) in [
+        (
+            Sanitizers {
+                memory: true,
+                ..Sanitizers::default()
+            },
+            "__msan_",
+        ),
+        (
+            Sanitizers {
+                thread: true,
+                ..Sanitizers::default()
+            },
+            "__tsan_",
+        ),
+    ] {
+        let module = context.create_module("other_module");
+        let function = module.add_function("load", fn_type, None);
+        let ptr = function.get_first_param().unwrap().into_pointer_value();
+
+        module.set_triple(&triple);
+        builder.position_at_end(context.append_basic_block(function, "entry"));
+
+        #[cfg(feature = "llvm14-0")]
+        let value = builder.build_load(ptr, "value");
+        #[cfg(any(feature = "llvm15-0", feature = "llvm16-0"))]
+        let value = builder.build_load(i64_type, ptr, "value");
+
+        // Unaligned loads are checked byte by byte
+        value
+            .into_int_value()
+            .as_instruction()
+            .unwrap()
+            .set_alignment(8)
+            .unwrap();
+        builder.build_return(Some(&value));
+        sanitizers
+            .run_passes(&module, &machine, PassBuilderOptions::create())
+            .unwrap();
+
+        let symbols = sanitizers.get_runtime_symbols(&module);
+
+        assert!(module.verify().is_ok());
+        assert!(!symbols.is_empty());
+        assert!(symbols.iter().all(|symbol| symbol.starts_with(prefix)));
+    }
+
+    let conflicting = Sanitizers {
+        address: true,
+        thread: true,
+        ..Sanitizers::default()
+    };
+
+    assert!(conflicting
+        .run_passes(&module, &machine, PassBuilderOptions::create())
+        .is_err());
+}
@@ -0,0 +1,58 @@
+use inkwell::context::Context;
+use inkwell::tbaa::TbaaBuilder;
+use inkwell::AddressSpace;
+
+#[test]
+fn test_tbaa_builder() {
+    let context = Context::create();
+    let module = context.create_module("my_mod");
+    let builder = context.create_builder();
+    let tbaa = TbaaBuilder::new(&context, "my root");
+    let i32_type = context.i32_type();
+    let f32_type = context.f32_type();
+    let i32_ptr_type = i32_type.ptr_type(AddressSpace::default());
+    let f32_ptr_type = f32_type.ptr_type(AddressSpace::default());
+    let fn_type = context
+        .void_type()
+        .fn_type(&[i32_ptr_type.into(), f32_ptr_type.into()], false);
+    let function = module.add_function("my_fn", fn_type, None);
+    let entry = context.append_basic_block(function, "entry");
+    let int_ptr = function.get_nth_param(0).unwrap().into_pointer_value();
+    let float_ptr = function.get_nth_param(1).unwrap().into_pointer_value();
+
+    assert_eq!(tbaa.get_kind_id(), context.get_kind_id("tbaa"));
+    assert_eq!(tbaa.get_root().get_node_size(), 1);
+
+    let char_type = tbaa.create_scalar_type("omnipotent char", None);
+    let int_type = tbaa.create_scalar_type("int", Some(char_type));
+    let float_type = tbaa.create_scalar_type("float", Some(char_type));
+    let struct_type = tbaa.create_struct_type("pair", &[(int_type, 0), (float_type, 4)]);
+
+    assert_eq!(char_type.get_node_size(), 3);
+    assert_eq!(char_type.get_node_values()[1].into_metadata_value(), tbaa.get_root());
+    assert_eq!(int_type.get_node_values()[1].into_metadata_value(), char_type);
+    assert_eq!(struct_type.get_node_size(), 5);
+
+    let field_tag = tbaa.create_access_tag(struct_type, float_type, 4, false);
+    let const_tag = tbaa.create_access_tag(int_type, int_type, 0, true);
+    let int_tag = tbaa.create_scalar_access_tag(int_type);
+
+    assert_eq!(field_tag.get_node_size(), 3);
+    assert_eq!(const_tag.get_node_size(), 4);
+    assert_eq!(int_tag.get_node_values()[0].into_metadata_value(), int_type);
+
+    builder.position_at_end(entry);
+
+    let int_store = builder.build_store(int_ptr, i32_type.const_zero());
+    let float_store = builder.build_store(float_ptr, f32_type.const_zero());
+    let ret = builder.build_return(None);
+
+    assert!(tbaa.set_access_tag(int_store, int_tag).is_ok());
+    assert!(tbaa.set_access_tag(float_store, field_tag).is_ok());
+    assert_eq!(
+        tbaa.set_access_tag(ret, int_tag),
+        Err("TBAA access tags can only be attached to memory accesses.")
+    );
+    assert_eq!(int_store.get_metadata(tbaa.get_kind_id()), Some(int_tag));
+    assert!(module.verify().is_ok());
+}
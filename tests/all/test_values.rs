@@ -4,8 +4,8 @@ use inkwell::comdat::ComdatSelectionKind;
 use inkwell::context::Context;
 use inkwell::module::Linkage::*;
 use inkwell::types::{StringRadix, VectorType};
-use inkwell::values::{AnyValue, InstructionOpcode::*, FIRST_CUSTOM_METADATA_KIND_ID};
-use inkwell::{AddressSpace, DLLStorageClass, GlobalVisibility, ThreadLocalMode};
+use inkwell::values::{AnyValue, InstructionOpcode::*, SwitchValue, FIRST_CUSTOM_METADATA_KIND_ID};
+use inkwell::{AddressSpace, CallingConvention, DLLStorageClass, GlobalVisibility, ThreadLocalMode};
 
 use std::convert::TryFrom;
 
@@ -60,6 +60,51 @@ fn test_call_site() {
     call_site.set_alignment_attribute(AttributeLoc::Return, 16);
 }
 
+#[test]
+fn test_calling_conventions() {
+    let context = Context::create();
+    let module = context.create_module("testing");
+    let builder = context.create_builder();
+    let fn_type = context.void_type().fn_type(&[], false);
+    let callee = module.add_function("callee", fn_type, None);
+    let caller = module.add_function("caller", fn_type, None);
+    let entry = context.append_basic_block(caller, "entry");
+
+    assert_eq!(CallingConvention::from(0), CallingConvention::C);
+    assert_eq!(CallingConvention::from(78), CallingConvention::X86_64SysV);
+    assert_eq!(CallingConvention::from(1234), CallingConvention::Other(1234));
+    assert_eq!(u32::from(CallingConvention::Fast), 8);
+    assert_eq!(u32::from(CallingConvention::Other(1234)), 1234);
+
+    assert_eq!(callee.get_calling_convention(), CallingConvention::C);
+
+    callee.set_calling_convention(CallingConvention::Cold);
+
+    assert_eq!(callee.get_call_conventions(), 9);
+
+    builder.position_at_end(entry);
+
+    // Calls inherit the callee's calling convention
+    let first_call = builder.build_call(callee, &[], "first");
+
+    assert_eq!(first_call.get_calling_convention(), CallingConvention::Cold);
+
+    let second_call = builder.build_call(callee, &[], "second");
+
+    builder.build_return(None);
+
+    // Changing the callee's calling convention updates its calls
+    callee.set_calling_convention(CallingConvention::Fast);
+
+    assert_eq!(first_call.get_calling_convention(), CallingConvention::Fast);
+    assert_eq!(second_call.get_calling_convention(), CallingConvention::Fast);
+
+    second_call.set_calling_convention(CallingConvention::PreserveMost);
+
+    assert_eq!(second_call.get_call_convention(), 14);
+    assert_eq!(callee.get_calling_convention(), CallingConvention::Fast);
+}
+
 #[test]
 fn test_set_get_name() {
     let context = Context::create();
@@ -909,6 +954,172 @@ fn test_phi_values() {
     assert!(phi.get_incoming(2).is_none());
 }
 
+#[test]
+fn test_phi_value_editing() {
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = context.create_module("my_mod");
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[context.bool_type().into()], false);
+    let fn_value = module.add_function("my_func", fn_type, None);
+    let entry_block = context.append_basic_block(fn_value, "entry");
+    let then_block = context.append_basic_block(fn_value, "then");
+    let else_block = context.append_basic_block(fn_value, "else");
+    let merge_block = context.append_basic_block(fn_value, "merge");
+    let cond = fn_value.get_first_param().unwrap().into_int_value();
+    let one = i32_type.const_int(1, false);
+    let two = i32_type.const_int(2, false);
+    let three = i32_type.const_int(3, false);
+
+    builder.position_at_end(entry_block);
+    builder.build_conditional_branch(cond, then_block, else_block);
+    builder.position_at_end(then_block);
+    builder.build_unconditional_branch(merge_block);
+    builder.position_at_end(else_block);
+    builder.build_unconditional_branch(merge_block);
+    builder.position_at_end(merge_block);
+
+    let phi = builder.build_phi(i32_type, "phi");
+    let ret = builder.build_return(Some(&phi.as_basic_value()));
+    let kind_id = context.get_kind_id("my_kind");
+    let metadata = context.metadata_node(&[context.metadata_string("my_md").into()]);
+
+    phi.add_incoming(&[(&one, then_block), (&one, else_block)]);
+    phi.as_instruction().set_metadata(metadata, kind_id).unwrap();
+
+    assert_eq!(
+        phi.incoming().collect::<Vec<_>>(),
+        vec![(one.into(), then_block), (one.into(), else_block)]
+    );
+    assert_eq!(phi.has_constant_value(), Some(one.into()));
+    assert!(phi.set_incoming_value(1, &two));
+    assert!(!phi.set_incoming_value(2, &two));
+    assert_eq!(phi.get_incoming(1), Some((two.into(), else_block)));
+    assert!(phi.has_constant_value().is_none());
+    assert!(module.verify().is_ok());
+
+    // Swap the incoming blocks around
+    assert!(phi.set_incoming_block(2, entry_block).is_none());
+
+    let phi = phi.set_incoming_block(0, else_block).unwrap();
+    let phi = phi.set_incoming_block(1, then_block).unwrap();
+
+    assert_eq!(phi.get_name().to_str(), Ok("phi"));
+    assert_eq!(
+        phi.incoming().collect::<Vec<_>>(),
+        vec![(one.into(), else_block), (two.into(), then_block)]
+    );
+    assert_eq!(ret.get_operand(0).unwrap().left(), Some(phi.as_basic_value()));
+    assert!(module.verify().is_ok());
+
+    phi.add_incoming(&[(&three, entry_block)]);
+
+    let phi = phi.remove_incoming(2).unwrap();
+
+    assert_eq!(phi.count_incoming(), 2);
+    assert!(phi.remove_incoming(2).is_none());
+    assert!(phi.remove_incoming_block(entry_block).is_none());
+
+    let phi = phi.remove_incoming_block(then_block).unwrap();
+
+    assert_eq!(phi.incoming().collect::<Vec<_>>(), vec![(one.into(), else_block)]);
+    assert_eq!(phi.has_constant_value(), Some(one.into()));
+    assert_eq!(ret.get_operand(0).unwrap().left(), Some(phi.as_basic_value()));
+    assert_eq!(merge_block.get_first_instruction(), Some(phi.as_instruction()));
+    assert_eq!(phi.get_name().to_str(), Ok("phi"));
+    assert_eq!(phi.as_instruction().get_metadata(kind_id), Some(metadata));
+}
+
+#[test]
+fn test_switch_values() {
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = context.create_module("my_mod");
+    let i8_type = context.i8_type();
+    let fn_type = context.void_type().fn_type(&[i8_type.into()], false);
+    let fn_value = module.add_function("my_func", fn_type, None);
+    let entry_block = context.append_basic_block(fn_value, "entry");
+    let default_block = context.append_basic_block(fn_value, "default");
+    let other_block = context.append_basic_block(fn_value, "other");
+    let zero_block = context.append_basic_block(fn_value, "zero");
+    let one_block = context.append_basic_block(fn_value, "one");
+    let i8_zero = i8_type.const_int(0, false);
+    let i8_one = i8_type.const_int(1, false);
+    let i8_two = i8_type.const_int(2, false);
+    let value = fn_value.get_first_param().unwrap().into_int_value();
+
+    builder.position_at_end(entry_block);
+
+    let instruction = builder.build_switch(value, default_block, &[(i8_zero, zero_block)]);
+    let switch = SwitchValue::try_from(instruction).unwrap();
+
+    for block in [default_block, other_block, zero_block, one_block] {
+        builder.position_at_end(block);
+        builder.build_return(None);
+    }
+
+    assert_eq!(switch.as_instruction(), instruction);
+    assert_eq!(switch.get_condition(), value);
+    assert_eq!(switch.count_cases(), 1);
+    assert_eq!(switch.default_dest(), default_block);
+
+    switch.add_case(i8_one, one_block);
+    switch.add_case(i8_two, other_block);
+
+    assert_eq!(switch.count_cases(), 3);
+    assert_eq!(
+        switch.cases().collect::<Vec<_>>(),
+        vec![(i8_zero, zero_block), (i8_one, one_block), (i8_two, other_block)]
+    );
+    assert!(switch.get_case(3).is_none());
+
+    switch.set_default_dest(other_block);
+
+    assert_eq!(switch.default_dest(), other_block);
+    assert!(switch.remove_case(3).is_none());
+
+    let prof_kind_id = context.get_kind_id("prof");
+    let custom_kind_id = context.get_kind_id("my_kind");
+    let custom_metadata = context.metadata_node(&[context.metadata_string("my_value").into()]);
+
+    switch.as_instruction().set_branch_weights(&[1, 2, 3, 4]).unwrap();
+    switch
+        .as_instruction()
+        .set_metadata(custom_metadata, custom_kind_id)
+        .unwrap();
+
+    let switch = switch.remove_case(1).unwrap();
+    let weights: Vec<_> = switch
+        .as_instruction()
+        .get_metadata(prof_kind_id)
+        .unwrap()
+        .get_node_values()
+        .into_iter()
+        .skip(1)
+        .map(|weight| weight.into_int_value().get_zero_extended_constant().unwrap())
+        .collect();
+
+    assert_eq!(weights, [1, 2, 4]);
+    assert_eq!(
+        switch.as_instruction().get_metadata(custom_kind_id),
+        Some(custom_metadata)
+    );
+
+    assert_eq!(switch.count_cases(), 2);
+    assert_eq!(switch.default_dest(), other_block);
+    assert_eq!(
+        switch.cases().collect::<Vec<_>>(),
+        vec![(i8_zero, zero_block), (i8_two, other_block)]
+    );
+    assert_eq!(entry_block.get_terminator(), Some(switch.as_instruction()));
+    assert_eq!(entry_block.get_first_instruction(), Some(switch.as_instruction()));
+    assert!(module.verify().is_ok());
+
+    let ret = default_block.get_terminator().unwrap();
+
+    assert!(SwitchValue::try_from(ret).is_err());
+}
+
 #[test]
 fn test_allocations() {
     let context = Context::create();
@@ -1265,3 +1476,292 @@ fn test_constant_expression() {
     assert!(expr.is_const());
     assert!(!expr.is_constant_int());
 }
+
+#[test]
+fn test_function_clone_and_specialize() {
+    use inkwell::values::PhiValue;
+    use inkwell::IntPredicate;
+
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = context.create_module("my_mod");
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[i32_type.into(), i32_type.into()], false);
+    let tick_fn = module.add_function("tick", context.void_type().fn_type(&[], false), None);
+    let counter = module.add_global(i32_type, None, "counter");
+    let fn_value = module.add_function("count_down", fn_type, None);
+    let entry = context.append_basic_block(fn_value, "entry");
+    let loop_block = context.append_basic_block(fn_value, "loop");
+    let exit = context.append_basic_block(fn_value, "exit");
+    let n = fn_value.get_first_param().unwrap().into_int_value();
+    let step = fn_value.get_last_param().unwrap().into_int_value();
+
+    counter.set_initializer(&i32_type.const_zero());
+    fn_value.add_attribute(AttributeLoc::Param(1), context.create_string_attribute("step", "yes"));
+    n.set_name("n");
+
+    builder.position_at_end(entry);
+    builder.build_unconditional_branch(loop_block);
+    builder.position_at_end(loop_block);
+
+    let phi = builder.build_phi(i32_type, "i");
+    let next = builder.build_int_sub(phi.as_basic_value().into_int_value(), step, "next");
+
+    phi.add_incoming(&[(&n, entry), (&next, loop_block)]);
+    builder.build_call(tick_fn, &[], "");
+    builder.build_store(counter.as_pointer_value(), next);
+
+    let done = builder.build_int_compare(IntPredicate::EQ, next, i32_type.const_zero(), "done");
+
+    builder.build_conditional_branch(done, exit, loop_block);
+    builder.position_at_end(exit);
+
+    let result = builder.build_call(fn_value, &[next.into(), step.into()], "result");
+
+    builder.build_return(Some(&result.try_as_basic_value().left().unwrap()));
+
+    assert!(module.verify().is_ok());
+
+    // Clone into the same module
+    let clone = fn_value.clone_into(&module, "count_down_clone").unwrap();
+    let clone_blocks = clone.get_basic_blocks();
+
+    assert_eq!(clone.count_basic_blocks(), 3);
+    assert_eq!(clone_blocks[1].get_name().to_str(), Ok("loop"));
+    assert_eq!(
+        clone.get_first_param().unwrap().into_int_value().get_name().to_str(),
+        Ok("n")
+    );
+    assert_eq!(clone.count_attributes(AttributeLoc::Param(1)), 1);
+
+    let clone_phi = PhiValue::try_from(clone_blocks[1].get_first_instruction().unwrap()).unwrap();
+
+    assert_eq!(clone_phi.get_name().to_str(), Ok("i"));
+    assert_eq!(clone_phi.get_incoming(0).unwrap().1, clone_blocks[0]);
+    assert_eq!(clone_phi.get_incoming(1).unwrap().1, clone_blocks[1]);
+    assert!(clone.to_string().contains("@count_down_clone("));
+    assert!(module.verify().is_ok());
+
+    // Clone into another module, redeclaring referenced globals
+    let other_module = context.create_module("other_mod");
+    let other_clone = fn_value.clone_into(&other_module, "count_down").unwrap();
+
+    assert_eq!(other_module.get_function("count_down"), Some(other_clone));
+    assert!(other_module.get_function("tick").is_some());
+    assert!(other_module.get_global("counter").is_some());
+    assert!(other_module.verify().is_ok());
+
+    // Specialize the step param
+    let i32_one = i32_type.const_int(1, false);
+    let f32_one = context.f32_type().const_float(1.);
+
+    assert_eq!(
+        fn_value.specialize("bad", &[(2, i32_one.into())]),
+        Err("Parameter index is out of bounds.")
+    );
+    assert_eq!(
+        fn_value.specialize("bad", &[(1, i32_one.into()), (1, i32_one.into())]),
+        Err("Parameter is specialized more than once.")
+    );
+    assert_eq!(
+        fn_value.specialize("bad", &[(0, n.into())]),
+        Err("Specialized parameter value must be a constant.")
+    );
+    assert_eq!(
+        fn_value.specialize("bad", &[(1, f32_one.into())]),
+        Err("Constant type does not match the parameter type.")
+    );
+
+    let count_down_by_one = fn_value
+        .specialize("count_down_by_one", &[(1, i32_one.into())])
+        .unwrap();
+
+    assert_eq!(count_down_by_one.count_params(), 1);
+    assert_eq!(count_down_by_one.get_linkage(), Internal);
+    assert_eq!(count_down_by_one.count_attributes(AttributeLoc::Param(0)), 0);
+    assert_eq!(
+        tick_fn.specialize("bad", &[]),
+        Err("Cannot specialize a function declaration.")
+    );
+    assert!(module.get_function("bad").is_none());
+    assert!(module.verify().is_ok());
+}
+
+#[test]
+fn test_function_clone_constant_expressions() {
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = context.create_module("my_mod");
+    let i32_type = context.i32_type();
+    let table = module.add_global(i32_type.array_type(4), None, "table");
+    let counter = module.add_global(i32_type, None, "counter");
+    let counter_alias = module.add_alias("counter_alias", counter);
+    let fn_value = module.add_function("store", context.void_type().fn_type(&[], false), None);
+    let indexes = [i32_type.const_zero(), i32_type.const_int(2, false)];
+
+    table.set_initializer(&i32_type.array_type(4).const_zero());
+    counter.set_initializer(&i32_type.const_zero());
+
+    #[cfg(not(any(
+        feature = "llvm4-0",
+        feature = "llvm5-0",
+        feature = "llvm6-0",
+        feature = "llvm7-0",
+        feature = "llvm8-0",
+        feature = "llvm9-0",
+        feature = "llvm10-0",
+        feature = "llvm11-0",
+        feature = "llvm12-0",
+        feature = "llvm13-0",
+        feature = "llvm14-0"
+    )))]
+    let element = unsafe {
+        table
+            .as_pointer_value()
+            .const_in_bounds_gep(i32_type.array_type(4), &indexes)
+    };
+    #[cfg(any(
+        feature = "llvm4-0",
+        feature = "llvm5-0",
+        feature = "llvm6-0",
+        feature = "llvm7-0",
+        feature = "llvm8-0",
+        feature = "llvm9-0",
+        feature = "llvm10-0",
+        feature = "llvm11-0",
+        feature = "llvm12-0",
+        feature = "llvm13-0",
+        feature = "llvm14-0"
+    ))]
+    let element = unsafe { table.as_pointer_value().const_in_bounds_gep(&indexes) };
+
+    builder.position_at_end(context.append_basic_block(fn_value, "entry"));
+    builder.build_store(element, i32_type.const_int(42, false));
+    builder.build_store(counter_alias.as_pointer_value(), i32_type.const_int(1, false));
+    builder.build_return(None);
+
+    assert!(module.verify().is_ok());
+
+    let other_module = context.create_module("other_mod");
+    let other_store = fn_value.clone_into(&other_module, "store").unwrap();
+    let other_table = other_module.get_global("table").unwrap();
+    let other_counter_alias = other_module.get_global("counter_alias").unwrap();
+    let store_element = other_store
+        .get_first_basic_block()
+        .unwrap()
+        .get_first_instruction()
+        .unwrap();
+    let store_alias = store_element.get_next_instruction().unwrap();
+    let element_operand = store_element.get_operand(1).unwrap().left().unwrap();
+
+    assert!(other_table.is_declaration());
+    assert!(other_counter_alias.is_declaration());
+    assert_ne!(element_operand.into_pointer_value(), element);
+    assert!(element_operand
+        .into_pointer_value()
+        .print_to_string()
+        .to_string()
+        .contains("@table"));
+    assert_eq!(
+        store_alias.get_operand(1).unwrap().left().unwrap().into_pointer_value(),
+        other_counter_alias.as_pointer_value()
+    );
+    assert!(other_module.verify().is_ok());
+
+    // Wrap flags are kept, and the personality function is redeclared too
+    let i64_type = context.i64_type();
+    let counter_address = counter.as_pointer_value().const_to_int(i64_type);
+    let personality = module.add_function("personality", i32_type.fn_type(&[], true), None);
+    let increment = module.add_function("increment", context.void_type().fn_type(&[], false), None);
+
+    increment.set_personality_function(personality);
+    builder.position_at_end(context.append_basic_block(increment, "entry"));
+    builder.build_store(
+        counter.as_pointer_value(),
+        counter_address
+            .const_nsw_add(i64_type.const_int(1, false))
+            .const_truncate(i32_type),
+    );
+    builder.build_return(None);
+
+    let other_increment = increment.clone_into(&other_module, "increment").unwrap();
+
+    assert_eq!(
+        other_increment.get_personality_function(),
+        other_module.get_function("personality")
+    );
+    assert!(other_increment.to_string().contains("add nsw"));
+    assert!(other_module.verify().is_ok());
+
+    // Other constant expressions over globals can't be rebuilt
+    let scale = module.add_function("scale", context.void_type().fn_type(&[], false), None);
+
+    builder.position_at_end(context.append_basic_block(scale, "entry"));
+    builder.build_store(
+        counter.as_pointer_value(),
+        counter_address
+            .const_mul(i64_type.const_int(3, false))
+            .const_truncate(i32_type),
+    );
+    builder.build_return(None);
+
+    assert!(module.verify().is_ok());
+    assert_eq!(
+        scale.clone_into(&other_module, "scale"),
+        Err("Cannot rebuild a constant expression over a remapped global value.")
+    );
+    assert!(other_module.get_function("scale").is_none());
+    assert!(other_module.verify().is_ok());
+}
+
+#[test]
+fn test_delete_functions_and_globals() {
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = context.create_module("my_mod");
+    let i32_type = context.i32_type();
+    let fn_type = i32_type.fn_type(&[], false);
+    let old_fn = module.add_function("old_fn", fn_type, None);
+    let new_fn = module.add_function("new_fn", fn_type, None);
+    let other_fn = module.add_function("other_fn", context.void_type().fn_type(&[], false), None);
+    let caller = module.add_function("caller", fn_type, None);
+    let old_global = module.add_global(i32_type, None, "old_global");
+    let new_global = module.add_global(i32_type, None, "new_global");
+    let entry = context.append_basic_block(caller, "entry");
+
+    old_global.set_initializer(&i32_type.const_zero());
+    new_global.set_initializer(&i32_type.const_zero());
+
+    builder.position_at_end(entry);
+
+    let call = builder.build_call(old_fn, &[], "call");
+
+    builder.build_store(old_global.as_pointer_value(), call.try_as_basic_value().left().unwrap());
+    builder.build_return(Some(&i32_type.const_zero()));
+
+    assert_eq!(unsafe { old_fn.try_delete() }, Err("Function still has uses."));
+    assert_eq!(unsafe { old_global.try_delete() }, Err("Global value still has uses."));
+    assert_eq!(
+        unsafe { old_fn.replace_all_uses_and_delete(old_fn) },
+        Err("Cannot replace a function with itself.")
+    );
+    assert_eq!(
+        unsafe { old_fn.replace_all_uses_and_delete(other_fn) },
+        Err("Replacement function has a different type.")
+    );
+    assert_eq!(
+        unsafe { old_global.replace_all_uses_and_delete(old_global) },
+        Err("Cannot replace a global value with itself.")
+    );
+    assert!(module.get_function("old_fn").is_some());
+    assert!(module.get_global("old_global").is_some());
+
+    assert!(unsafe { old_fn.replace_all_uses_and_delete(new_fn) }.is_ok());
+    assert!(unsafe { old_global.replace_all_uses_and_delete(new_global) }.is_ok());
+    assert!(module.get_function("old_fn").is_none());
+    assert!(module.get_global("old_global").is_none());
+    assert!(module.verify().is_ok());
+
+    assert!(unsafe { other_fn.as_global_value().try_delete() }.is_ok());
+    assert!(module.get_function("other_fn").is_none());
+}